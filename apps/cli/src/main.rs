@@ -1,16 +1,20 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use platform_passer_session::{run_client_session, run_server_session, SessionEvent, LogLevel};
+use platform_passer_core::config::{config_dir, AppConfig};
+use platform_passer_session::{run_client_session, run_server_session, SessionCommand, SessionEvent, LogLevel};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use tokio::sync::mpsc;
-use tracing::{info, error};
+use tracing::{info, warn, error};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Config file to apply. Defaults to config.json in the config directory, if it exists.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -53,17 +57,37 @@ async fn main() -> Result<()> {
         .init();
 
     let cli = Cli::parse();
+    let config = load_config(cli.config)?;
 
     match cli.command {
-        Commands::Server { bind } => run_server(bind).await,
-        Commands::Client { server, send_file } => run_client(server, send_file).await,
+        Commands::Server { bind } => run_server(bind, config).await,
+        Commands::Client { server, send_file } => run_client(server, send_file, config).await,
     }
 }
 
-async fn run_server(bind_addr: SocketAddr) -> Result<()> {
+/// Reads `path`, or the default config file if there is one. Without either, sessions keep
+/// their defaults; `PLATFORM_PASSER_PSK` still applies.
+fn load_config(path: Option<PathBuf>) -> Result<Option<AppConfig>> {
+    let path = match path {
+        Some(path) => path,
+        None => match config_dir().join("config.json") {
+            default if default.exists() => default,
+            _ => return Ok(None),
+        },
+    };
+    let file = std::fs::File::open(&path).context(format!("Failed to open {}", path.display()))?;
+    let config = serde_json::from_reader(file).context(format!("Invalid config file {}", path.display()))?;
+    info!("Using config from {}", path.display());
+    Ok(Some(config))
+}
+
+async fn run_server(bind_addr: SocketAddr, config: Option<AppConfig>) -> Result<()> {
     let (tx, mut rx) = mpsc::channel(100);
     
-    let (_cmd_tx, cmd_rx) = mpsc::channel(1); 
+    let (cmd_tx, cmd_rx) = mpsc::channel(1); 
+    if let Some(config) = config {
+        let _ = cmd_tx.try_send(SessionCommand::UpdateConfig(Box::new(config)));
+    }
     // Spawn session
     tokio::spawn(async move {
         if let Err(e) = run_server_session(bind_addr, cmd_rx, tx.clone()).await {
@@ -84,7 +108,19 @@ async fn run_server(bind_addr: SocketAddr) -> Result<()> {
                 }
             }
             SessionEvent::Connected(addr) => info!("Connected: {}", addr),
+            SessionEvent::ProtocolNegotiated { peer, name, version, capabilities } => info!("Negotiated protocol v{} with {} ({}) [{}]", version, name, peer, capabilities),
+            SessionEvent::Latency { peer, rtt_ms, jitter_ms, quality } => info!("RTT to {}: {:.1} ms (jitter {:.1} ms, {})", peer, rtt_ms, jitter_ms, quality),
+            SessionEvent::InputLatency { peer, report } => info!("Input from {}: mean {:.1} ms, max {:.1} ms, {:.0}% within 20 ms, {} lost, {} out of order", peer, report.mean_ms, report.max_ms, report.fraction_within_ms(20) * 100.0, report.lost, report.out_of_order),
+            SessionEvent::ScreenLock { locked } => info!("Screen lock {}", if locked { "on" } else { "off" }),
             SessionEvent::Disconnected => info!("Disconnected"),
+            SessionEvent::PairingRequest { peer, name, addr } => {
+                info!("{} ({}) at {} wants to pair. Type the code it shows and press Enter, or just Enter to decline.", name, peer, addr);
+                read_pairing_code(addr, cmd_tx.clone());
+            }
+            SessionEvent::PairingCode { peer, name, code, .. } => info!("Enter this code on {} ({}) to pair: {}", name, peer, code),
+            SessionEvent::Paired { peer, name } => info!("Paired with {} ({})", name, peer),
+            SessionEvent::ProtocolViolation { peer, reason } => error!("Disconnected {} (protocol violation): {}", peer, reason),
+            SessionEvent::TransferFailed { id, reason } => warn!("Transfer {} failed: {}", id, reason),
             SessionEvent::Error(msg) => error!("{}", msg),
             SessionEvent::Waiting(msg) => info!("Waiting: {}", msg),
             SessionEvent::Connecting(addr) => info!("Connecting to: {}", addr),
@@ -95,9 +131,12 @@ async fn run_server(bind_addr: SocketAddr) -> Result<()> {
     Ok(())
 }
 
-async fn run_client(server_addr: SocketAddr, send_file_path: Option<PathBuf>) -> Result<()> {
+async fn run_client(server_addr: SocketAddr, send_file_path: Option<PathBuf>, config: Option<AppConfig>) -> Result<()> {
     let (tx, mut rx) = mpsc::channel(100);
-    let (_cmd_tx, cmd_rx) = mpsc::channel(1); 
+    let (cmd_tx, cmd_rx) = mpsc::channel(1); 
+    if let Some(config) = config {
+        let _ = cmd_tx.try_send(SessionCommand::UpdateConfig(Box::new(config)));
+    }
 
      tokio::spawn(async move {
         if let Err(e) = run_client_session(server_addr, send_file_path, cmd_rx, tx.clone()).await {
@@ -117,7 +156,19 @@ async fn run_client(server_addr: SocketAddr, send_file_path: Option<PathBuf>) ->
                 }
             }
             SessionEvent::Connected(addr) => info!("Connected: {}", addr),
+            SessionEvent::ProtocolNegotiated { peer, name, version, capabilities } => info!("Negotiated protocol v{} with {} ({}) [{}]", version, name, peer, capabilities),
+            SessionEvent::Latency { peer, rtt_ms, jitter_ms, quality } => info!("RTT to {}: {:.1} ms (jitter {:.1} ms, {})", peer, rtt_ms, jitter_ms, quality),
+            SessionEvent::InputLatency { peer, report } => info!("Input from {}: mean {:.1} ms, max {:.1} ms, {:.0}% within 20 ms, {} lost, {} out of order", peer, report.mean_ms, report.max_ms, report.fraction_within_ms(20) * 100.0, report.lost, report.out_of_order),
+            SessionEvent::ScreenLock { locked } => info!("Screen lock {}", if locked { "on" } else { "off" }),
             SessionEvent::Disconnected => info!("Disconnected"),
+            SessionEvent::PairingRequest { peer, name, addr } => info!("Pairing request from {} ({}) at {} ignored", name, peer, addr),
+            SessionEvent::PairingCode { peer, name, addr, code } => {
+                info!("Enter this code on {} ({}) to pair: {}. Once it accepts, type the code here and press Enter, or just Enter to decline.", name, peer, code);
+                read_pairing_code(addr, cmd_tx.clone());
+            }
+            SessionEvent::Paired { peer, name } => info!("Paired with {} ({})", name, peer),
+            SessionEvent::ProtocolViolation { peer, reason } => error!("Disconnected {} (protocol violation): {}", peer, reason),
+            SessionEvent::TransferFailed { id, reason } => warn!("Transfer {} failed: {}", id, reason),
             SessionEvent::Error(msg) => error!("{}", msg),
             SessionEvent::Waiting(msg) => info!("Waiting: {}", msg),
             SessionEvent::Connecting(addr) => info!("Connecting to: {}", addr),
@@ -127,3 +178,19 @@ async fn run_client(server_addr: SocketAddr, send_file_path: Option<PathBuf>) ->
     }
     Ok(())
 }
+
+/// Reads the code for a pairing from stdin without holding up the event loop.
+fn read_pairing_code(addr: String, cmd_tx: mpsc::Sender<SessionCommand>) {
+    tokio::spawn(async move {
+        let line = tokio::task::spawn_blocking(|| {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).map(|_| line)
+        })
+        .await;
+        let code = match line {
+            Ok(Ok(line)) if !line.trim().is_empty() => Some(line.trim().to_string()),
+            _ => None,
+        };
+        let _ = cmd_tx.send(SessionCommand::ConfirmPairing { addr, code }).await;
+    });
+}
@@ -50,7 +50,7 @@ fn save_config(config: AppConfig, state: State<AppState>) -> Result<(), String>
         let tx_clone = tx.clone();
         let config_clone = config.clone();
         tauri::async_runtime::spawn(async move {
-            let _ = tx_clone.send(SessionCommand::UpdateConfig(config_clone)).await;
+            let _ = tx_clone.send(SessionCommand::UpdateConfig(Box::new(config_clone))).await;
         });
     }
     
@@ -58,15 +58,7 @@ fn save_config(config: AppConfig, state: State<AppState>) -> Result<(), String>
 }
 
 fn get_config_path() -> PathBuf {
-    #[cfg(target_os = "windows")]
-    let base = PathBuf::from(std::env::var("APPDATA").unwrap_or_else(|_| ".".to_string()));
-    #[cfg(not(target_os = "windows"))]
-    let base = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string())).join(".config");
-    
-    let mut path = base;
-    path.push("platform-passer");
-    path.push("config.json");
-    path
+    platform_passer_core::config::config_dir().join("config.json")
 }
 
 fn load_config() -> Option<AppConfig> {
@@ -104,6 +96,8 @@ fn start_server(ip: String, port: u16, window: WebviewWindow, state: State<AppSt
 
     // Clear old tx if any
     let (cmd_tx, cmd_rx) = mpsc::channel(10);
+    // Sessions start from defaults; hand them the saved config before anything else
+    let _ = cmd_tx.try_send(SessionCommand::UpdateConfig(Box::new(state.config.lock().unwrap().clone())));
     *state.command_tx.lock().unwrap() = Some(cmd_tx);
 
     let running_clone = state.running.clone();
@@ -149,6 +143,7 @@ fn start_server(ip: String, port: u16, window: WebviewWindow, state: State<AppSt
                                     }
                                     ("Connected".to_string(), format!("Connected to {}", s))
                                 },
+                                SessionEvent::ProtocolNegotiated { ref peer, ref name, version, capabilities } => ("ProtocolNegotiated".to_string(), format!("Negotiated protocol v{} with {} ({}) [{}]", version, name, peer, capabilities)),
                                 SessionEvent::Disconnected => {
                                      let enabled = config_clone.lock().unwrap().notifications_enabled;
                                     if enabled {
@@ -159,6 +154,26 @@ fn start_server(ip: String, port: u16, window: WebviewWindow, state: State<AppSt
                                     }
                                     ("Disconnected".to_string(), "Disconnected".to_string())
                                 },
+                                SessionEvent::Latency { ref peer, rtt_ms, jitter_ms, quality } => {
+                                    let _ = window.emit("latency", LatencyPayload { rtt_ms, jitter_ms, quality: quality.to_string() });
+                                    ("Latency".to_string(), format!("RTT to {}: {:.1} ms (jitter {:.1} ms, {})", peer, rtt_ms, jitter_ms, quality))
+                                },
+                                SessionEvent::InputLatency { ref peer, ref report } => ("InputLatency".to_string(), format!("Input from {}: mean {:.1} ms, max {:.1} ms, {:.0}% within 20 ms, {} lost, {} out of order", peer, report.mean_ms, report.max_ms, report.fraction_within_ms(20) * 100.0, report.lost, report.out_of_order)),
+                                SessionEvent::ScreenLock { locked } => {
+                                    let _ = window.emit("screen-lock", locked);
+                                    ("ScreenLock".to_string(), format!("Screen lock {}", if locked { "on" } else { "off" }))
+                                },
+                                SessionEvent::PairingRequest { ref peer, ref name, ref addr } => {
+                                    let _ = window.emit("pairing-request", PairingRequestPayload { peer: peer.clone(), name: name.clone(), addr: addr.clone() });
+                                    ("PairingRequest".to_string(), format!("{} ({}) at {} wants to pair", name, peer, addr))
+                                },
+                                SessionEvent::PairingCode { ref peer, ref name, ref addr, ref code } => {
+                                    let _ = window.emit("pairing-code", PairingCodePayload { peer: peer.clone(), name: name.clone(), addr: addr.clone(), code: code.clone() });
+                                    ("PairingCode".to_string(), format!("Enter this code on {} ({}) to pair: {}", name, peer, code))
+                                },
+                                SessionEvent::Paired { ref peer, ref name } => ("Paired".to_string(), format!("Paired with {} ({})", name, peer)),
+                                SessionEvent::ProtocolViolation { ref peer, ref reason } => ("ProtocolViolation".to_string(), format!("Disconnected {} (protocol violation): {}", peer, reason)),
+                                SessionEvent::TransferFailed { id, ref reason } => ("TransferFailed".to_string(), format!("Transfer {} failed: {}", id, reason)),
                                 SessionEvent::Error(ref s) => ("Error".to_string(), format!("Error: {}", s)),
                             };
                 
@@ -197,6 +212,8 @@ fn connect_to(ip: String, port: u16, window: WebviewWindow, state: State<AppStat
     
     // Create command channel
     let (cmd_tx, cmd_rx) = mpsc::channel(10);
+    // Sessions start from defaults; hand them the saved config before anything else
+    let _ = cmd_tx.try_send(SessionCommand::UpdateConfig(Box::new(state.config.lock().unwrap().clone())));
     *state.command_tx.lock().unwrap() = Some(cmd_tx);
     
     let running_clone = state.running.clone();
@@ -245,6 +262,7 @@ fn connect_to(ip: String, port: u16, window: WebviewWindow, state: State<AppStat
                                     }
                                     ("Connected".to_string(), format!("Connected to {}", s))
                                 },
+                                SessionEvent::ProtocolNegotiated { ref peer, ref name, version, capabilities } => ("ProtocolNegotiated".to_string(), format!("Negotiated protocol v{} with {} ({}) [{}]", version, name, peer, capabilities)),
                                 SessionEvent::Disconnected => {
                                      let enabled = config_clone.lock().unwrap().notifications_enabled;
                                     if enabled {
@@ -255,6 +273,26 @@ fn connect_to(ip: String, port: u16, window: WebviewWindow, state: State<AppStat
                                     }
                                     ("Disconnected".to_string(), "Disconnected".to_string())
                                 },
+                                SessionEvent::Latency { ref peer, rtt_ms, jitter_ms, quality } => {
+                                    let _ = window.emit("latency", LatencyPayload { rtt_ms, jitter_ms, quality: quality.to_string() });
+                                    ("Latency".to_string(), format!("RTT to {}: {:.1} ms (jitter {:.1} ms, {})", peer, rtt_ms, jitter_ms, quality))
+                                },
+                                SessionEvent::InputLatency { ref peer, ref report } => ("InputLatency".to_string(), format!("Input from {}: mean {:.1} ms, max {:.1} ms, {:.0}% within 20 ms, {} lost, {} out of order", peer, report.mean_ms, report.max_ms, report.fraction_within_ms(20) * 100.0, report.lost, report.out_of_order)),
+                                SessionEvent::ScreenLock { locked } => {
+                                    let _ = window.emit("screen-lock", locked);
+                                    ("ScreenLock".to_string(), format!("Screen lock {}", if locked { "on" } else { "off" }))
+                                },
+                                SessionEvent::PairingRequest { ref peer, ref name, ref addr } => {
+                                    let _ = window.emit("pairing-request", PairingRequestPayload { peer: peer.clone(), name: name.clone(), addr: addr.clone() });
+                                    ("PairingRequest".to_string(), format!("{} ({}) at {} wants to pair", name, peer, addr))
+                                },
+                                SessionEvent::PairingCode { ref peer, ref name, ref addr, ref code } => {
+                                    let _ = window.emit("pairing-code", PairingCodePayload { peer: peer.clone(), name: name.clone(), addr: addr.clone(), code: code.clone() });
+                                    ("PairingCode".to_string(), format!("Enter this code on {} ({}) to pair: {}", name, peer, code))
+                                },
+                                SessionEvent::Paired { ref peer, ref name } => ("Paired".to_string(), format!("Paired with {} ({})", name, peer)),
+                                SessionEvent::ProtocolViolation { ref peer, ref reason } => ("ProtocolViolation".to_string(), format!("Disconnected {} (protocol violation): {}", peer, reason)),
+                                SessionEvent::TransferFailed { id, ref reason } => ("TransferFailed".to_string(), format!("Transfer {} failed: {}", id, reason)),
                                 SessionEvent::Error(ref s) => ("Error".to_string(), format!("Error: {}", s)),
                             };
                 
@@ -284,6 +322,35 @@ fn connect_to(ip: String, port: u16, window: WebviewWindow, state: State<AppStat
     format!("Connecting to {}:{}...", ip, port)
 }
 
+#[command]
+fn set_screen_lock(locked: bool, state: State<AppState>) -> String {
+    let tx_opt = state.command_tx.lock().unwrap();
+    if let Some(tx) = &*tx_opt {
+        let tx_clone = tx.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = tx_clone.send(SessionCommand::SetScreenLock(locked)).await;
+        });
+        if locked { "Screen locked".to_string() } else { "Screen unlocked".to_string() }
+    } else {
+        "No active session".to_string()
+    }
+}
+
+#[command]
+fn confirm_pairing(addr: String, code: Option<String>, state: State<AppState>) -> String {
+    let tx_opt = state.command_tx.lock().unwrap();
+    if let Some(tx) = &*tx_opt {
+        let tx_clone = tx.clone();
+        let declined = code.is_none();
+        tauri::async_runtime::spawn(async move {
+            let _ = tx_clone.send(SessionCommand::ConfirmPairing { addr, code }).await;
+        });
+        if declined { "Pairing declined".to_string() } else { "Pairing code sent".to_string() }
+    } else {
+        "No active session".to_string()
+    }
+}
+
 #[command]
 fn stop_session(state: State<AppState>) -> String {
     let tx_opt = state.command_tx.lock().unwrap();
@@ -304,6 +371,28 @@ struct Payload {
     message: String,
 }
 
+#[derive(serde::Serialize, Clone)]
+struct LatencyPayload {
+    rtt_ms: f64,
+    jitter_ms: f64,
+    quality: String,
+}
+
+#[derive(serde::Serialize, Clone)]
+struct PairingRequestPayload {
+    peer: String,
+    name: String,
+    addr: String,
+}
+
+#[derive(serde::Serialize, Clone)]
+struct PairingCodePayload {
+    peer: String,
+    name: String,
+    addr: String,
+    code: String,
+}
+
 
 #[command]
 fn check_accessibility() -> bool {
@@ -562,7 +651,7 @@ fn main() {
                 }
             }
         })
-        .invoke_handler(tauri::generate_handler![start_server, connect_to, stop_session, send_file_action, check_accessibility, get_config, save_config, hide_from_dock, show_in_dock, test_notification])
+        .invoke_handler(tauri::generate_handler![start_server, connect_to, stop_session, set_screen_lock, confirm_pairing, send_file_action, check_accessibility, get_config, save_config, hide_from_dock, show_in_dock, test_notification])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
         .run(|_app_handle, _event| {
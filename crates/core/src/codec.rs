@@ -0,0 +1,138 @@
+//! `tokio_util` codec for `Frame`.
+//!
+//! The same `FrameCodec` serves both transports:
+//!
+//! - byte streams (TCP, pipes) through `Framed`/`FramedRead`/`FramedWrite`, where the decoder
+//!   parses partial reads incrementally and may see several envelopes in one buffer;
+//! - message transports (WebSocket) through `encode_message`/`decode_message`, where every
+//!   message carries exactly one envelope.
+use crate::config::FrameLimits;
+use crate::wire::{decode_legacy_handshake, decode_payload, encode_payload, tag, EnvelopeHeader, FrameError, HEADER_LEN, LEGACY_HANDSHAKE_PREFIX};
+use crate::Frame;
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameCodec {
+    limits: FrameLimits,
+}
+
+impl FrameCodec {
+    pub fn new(limits: FrameLimits) -> Self {
+        Self { limits }
+    }
+
+    pub fn limits(&self) -> &FrameLimits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: FrameLimits) {
+        self.limits = limits;
+    }
+
+    /// Encodes `frame` as a single message.
+    pub fn encode_message(&self, frame: &Frame) -> Result<Vec<u8>, FrameError> {
+        let mut buf = BytesMut::new();
+        encode_into(frame, &mut buf)?;
+        Ok(buf.to_vec())
+    }
+
+    /// Decodes a message that must hold exactly one envelope.
+    ///
+    /// Frame types this build does not know yield `FrameError::UnknownType`, which is recoverable.
+    /// A handshake from a peer before the envelope decodes too, so it can be rejected properly.
+    pub fn decode_message(&self, msg: &[u8]) -> Result<Frame, FrameError> {
+        if msg.starts_with(&LEGACY_HANDSHAKE_PREFIX) {
+            let max = self.limits.max_for(tag::HANDSHAKE);
+            if msg.len() > max as usize {
+                return Err(FrameError::TooLarge { tag: tag::HANDSHAKE, len: msg.len() as u32, max });
+            }
+            return decode_legacy_handshake(msg).map(Frame::Handshake);
+        }
+        let mut src = BytesMut::from(msg);
+        let (tag, payload) = match self.split_envelope(&mut src)? {
+            Some(envelope) => envelope,
+            None if msg.len() < HEADER_LEN => {
+                return Err(FrameError::Truncated { expected: HEADER_LEN, actual: msg.len() });
+            }
+            None => {
+                let mut header = [0u8; HEADER_LEN];
+                header.copy_from_slice(&msg[..HEADER_LEN]);
+                let expected = EnvelopeHeader::parse(&header)?.len as usize;
+                return Err(FrameError::Truncated { expected, actual: msg.len() - HEADER_LEN });
+            }
+        };
+        if !src.is_empty() {
+            return Err(FrameError::Decode(format!("{} bytes after envelope", src.len())));
+        }
+        decode_payload(tag, &payload)
+    }
+
+    /// Removes one complete envelope from the front of `src`, or returns `None` if more bytes
+    /// are needed. The header is checked against the limits before the payload is buffered.
+    fn split_envelope(&self, src: &mut BytesMut) -> Result<Option<(u16, BytesMut)>, FrameError> {
+        if src.len() < HEADER_LEN {
+            src.reserve(HEADER_LEN - src.len());
+            return Ok(None);
+        }
+        let mut header = [0u8; HEADER_LEN];
+        header.copy_from_slice(&src[..HEADER_LEN]);
+        let header = EnvelopeHeader::parse(&header)?;
+        header.check(&self.limits)?;
+
+        let total = HEADER_LEN + header.len as usize;
+        if src.len() < total {
+            src.reserve(total - src.len());
+            return Ok(None);
+        }
+        src.advance(HEADER_LEN);
+        Ok(Some((header.tag, src.split_to(header.len as usize))))
+    }
+}
+
+fn encode_into(frame: &Frame, dst: &mut BytesMut) -> Result<(), FrameError> {
+    let payload = encode_payload(frame)?;
+    let len = u32::try_from(payload.len()).map_err(|_| FrameError::TooLarge {
+        tag: frame.tag(),
+        len: u32::MAX,
+        max: u32::MAX,
+    })?;
+    dst.reserve(HEADER_LEN + payload.len());
+    dst.put_slice(&EnvelopeHeader { tag: frame.tag(), len }.to_bytes());
+    dst.put_slice(&payload);
+    Ok(())
+}
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+    type Error = FrameError;
+
+    /// Frame types this build does not know are skipped rather than ending the stream.
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, FrameError> {
+        loop {
+            let Some((tag, payload)) = self.split_envelope(src)? else {
+                return Ok(None);
+            };
+            match decode_payload(tag, &payload) {
+                Err(e) if e.is_recoverable() => continue,
+                other => return other.map(Some),
+            }
+        }
+    }
+}
+
+impl Encoder<&Frame> for FrameCodec {
+    type Error = FrameError;
+
+    fn encode(&mut self, frame: &Frame, dst: &mut BytesMut) -> Result<(), FrameError> {
+        encode_into(frame, dst)
+    }
+}
+
+impl Encoder<Frame> for FrameCodec {
+    type Error = FrameError;
+
+    fn encode(&mut self, frame: Frame, dst: &mut BytesMut) -> Result<(), FrameError> {
+        encode_into(&frame, dst)
+    }
+}
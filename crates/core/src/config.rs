@@ -1,4 +1,8 @@
+use crate::hotkey::{modifiers, Chord};
+use crate::{Capability, Frame};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
@@ -11,6 +15,17 @@ pub struct AppConfig {
     pub last_bind_port: u16,
     pub last_mode: String,
     pub notifications_enabled: bool,
+    #[serde(default)]
+    pub frame_limits: FrameLimits,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub hotkeys: HotkeyConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// What this machine is called on its peers. Empty for the host name; see `name_or_host`.
+    #[serde(default)]
+    pub device_name: String,
 }
 
 impl Default for AppConfig {
@@ -25,24 +40,44 @@ impl Default for AppConfig {
             last_bind_port: 4433,
             last_mode: "client".to_string(),
             notifications_enabled: true,
+            frame_limits: FrameLimits::default(),
+            network: NetworkConfig::default(),
+            hotkeys: HotkeyConfig::default(),
+            security: SecurityConfig::default(),
+            device_name: String::new(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl AppConfig {
+    /// The name this machine goes by on its peers: `device_name`, or the host name if it is empty.
+    pub fn name_or_host(&self) -> String {
+        match self.device_name.trim() {
+            "" => host_name().to_string(),
+            name => name.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Topology {
     /// Information about the machine running this instance
     pub local: ScreenInfo,
-    /// List of known remote peers and their layout
+    /// List of known remote peers and their layout. Each `RemoteScreen::id` is the peer's
+    /// device ID, and its `info` only stands in until the peer reports its own screen.
     pub remotes: Vec<RemoteScreen>,
 }
 
-impl Default for Topology {
-    fn default() -> Self {
-        Self {
-            local: ScreenInfo::default(),
-            remotes: Vec::new(),
+impl Topology {
+    /// This topology with each remote's screen replaced by the one its peer reported in the
+    /// handshake, keyed by device ID. Peers without a `RemoteScreen` are not placed.
+    pub fn with_peer_screens(mut self, screens: &HashMap<String, ScreenInfo>) -> Self {
+        for remote in &mut self.remotes {
+            if let Some(info) = screens.get(&remote.id) {
+                remote.info = info.clone();
+            }
         }
+        self
     }
 }
 
@@ -51,28 +86,48 @@ pub struct ScreenInfo {
     pub width: u32,
     pub height: u32,
     pub dpi_scale: f32, // Default 1.0. Retina/HighDPI > 1.0
+    /// The monitors making up this screen, empty when it is treated as one. Detected at
+    /// runtime or received in the handshake, so it is neither saved nor part of this struct's
+    /// own encoding.
+    #[serde(skip)]
+    pub displays: Vec<DisplayInfo>,
 }
 
 impl Default for ScreenInfo {
     fn default() -> Self {
-        Self { width: 1920, height: 1080, dpi_scale: 1.0 }
+        Self { width: 1920, height: 1080, dpi_scale: 1.0, displays: Vec::new() }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// One monitor, in its machine's desktop coordinates (logical points on macOS, pixels on
+/// Windows). The machine's screen spans the bounding box of all of them.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DisplayInfo {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub dpi_scale: f32,
+    pub primary: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RemoteScreen {
-    pub id: String, // Hostname or IP
+    /// The peer's device ID.
+    pub id: String,
     pub position: ScreenPosition,
     pub info: ScreenInfo,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum ScreenPosition {
     Left,
     Right,
     Top,
     Bottom,
-    // Absolute { x: i32, y: i32 } could be added later
+    /// Top-left corner at this pixel of the virtual desktop, where this machine's screen spans
+    /// `(0, 0)` to its own `width` and `height`.
+    Absolute { x: i32, y: i32 },
 }
 
 /// Maximum allowed delta threshold to prevent catastrophic errors
@@ -87,6 +142,14 @@ pub struct InputConfig {
     /// Delta threshold for filtering abnormal mouse movements (max 500)
     #[serde(default = "default_delta_threshold")]
     pub mouse_delta_threshold: f32,
+    /// Used for peers without an entry in `peer_layout_modes`.
+    #[serde(default)]
+    pub layout_mode: LayoutMode,
+    /// Keyed by the peer's device ID.
+    #[serde(default)]
+    pub peer_layout_modes: HashMap<String, LayoutMode>,
+    #[serde(default)]
+    pub edge_guards: EdgeGuardConfig,
 }
 
 fn default_delta_threshold() -> f32 {
@@ -101,10 +164,89 @@ impl Default for InputConfig {
             maintain_aspect_ratio: true,
             scroll_reverse: false,
             mouse_delta_threshold: 100.0,
+            layout_mode: LayoutMode::default(),
+            peer_layout_modes: HashMap::new(),
+            edge_guards: EdgeGuardConfig::default(),
+        }
+    }
+}
+
+impl InputConfig {
+    pub fn layout_mode_for(&self, peer: &str) -> LayoutMode {
+        self.peer_layout_modes.get(peer).copied().unwrap_or(self.layout_mode)
+    }
+}
+
+/// Conditions an edge must meet before the cursor crosses to another screen. All off by default,
+/// which switches as soon as the cursor touches an edge.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct EdgeGuardConfig {
+    /// How long the cursor has to keep pushing against the edge.
+    pub dwell_ms: u64,
+    /// Touch the edge, pull back, and touch it again within `double_tap_window_ms`.
+    pub double_tap: bool,
+    pub double_tap_window_ms: u64,
+    /// Stretch at each end of an edge, in pixels, that never switches.
+    pub corner_dead_zone_px: u32,
+    /// Do not switch while any mouse button is down, such as mid-drag.
+    pub block_while_button_held: bool,
+}
+
+impl Default for EdgeGuardConfig {
+    fn default() -> Self {
+        Self {
+            dwell_ms: 0,
+            double_tap: false,
+            double_tap_window_ms: 500,
+            corner_dead_zone_px: 0,
+            block_while_button_held: false,
+        }
+    }
+}
+
+/// How keystrokes are sent to a peer.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum LayoutMode {
+    /// Physical keys; the peer's layout decides which characters they type.
+    #[default]
+    Physical,
+    /// The characters this machine's layout types. Shortcuts and non-printing keys stay physical.
+    Characters,
+}
+
+/// Key chords the input source acts on itself instead of forwarding. `None` leaves an action unbound.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct HotkeyConfig {
+    /// Gives input back to this machine from any peer.
+    pub return_to_local: Option<Chord>,
+    /// Toggles whether the cursor may leave the current screen through an edge.
+    pub lock_to_screen: Option<Chord>,
+    /// Moves to the next peer in topology order, then back to this machine.
+    pub cycle_peers: Option<Chord>,
+    pub switch_to_peer: Vec<PeerHotkey>,
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            return_to_local: Some(Chord::new(modifiers::CTRL | modifiers::ALT, 0x29)), // Escape
+            lock_to_screen: None,
+            cycle_peers: None,
+            switch_to_peer: Vec::new(),
         }
     }
 }
 
+/// Moves input straight to one peer.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PeerHotkey {
+    /// The peer's `RemoteScreen::id`.
+    pub peer: String,
+    pub chord: Chord,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClipboardConfig {
     pub sync_enabled: bool,
@@ -119,3 +261,217 @@ impl Default for ClipboardConfig {
         }
     }
 }
+
+/// Maximum payload size in bytes accepted per frame family. Larger frames are treated as abuse.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct FrameLimits {
+    /// Handshake, heartbeat, notifications and file transfer control frames
+    pub control: u32,
+    pub input: u32,
+    /// Clipboard text, images and file manifests
+    pub clipboard: u32,
+    pub file_data: u32,
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        Self {
+            control: 64 * 1024,
+            input: 4 * 1024,
+            clipboard: 32 * 1024 * 1024,
+            file_data: 1024 * 1024,
+        }
+    }
+}
+
+impl FrameLimits {
+    /// The largest limit of any family; also used to bound frames of unknown type.
+    pub fn largest(&self) -> u32 {
+        self.control.max(self.input).max(self.clipboard).max(self.file_data)
+    }
+}
+
+/// Liveness settings for an established session.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// How often each side sends a heartbeat.
+    pub heartbeat_interval_ms: u64,
+    /// Consecutive unanswered heartbeats before the peer is considered dead.
+    pub missed_heartbeats: u32,
+    /// Longest time without receiving anything from the peer before it is considered dead.
+    pub read_timeout_ms: u64,
+    /// Connect and accept over `wss://`. On unless turned off; both ends must agree, and a peer
+    /// that does not is reported as such rather than retried.
+    pub tls: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval_ms: 5000,
+            missed_heartbeats: 3,
+            read_timeout_ms: 15000,
+            tls: true,
+        }
+    }
+}
+
+/// Lower bound for both durations, so a zero in the config cannot spin the session loops.
+const MIN_NETWORK_INTERVAL_MS: u64 = 100;
+
+impl NetworkConfig {
+    pub fn heartbeat_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.heartbeat_interval_ms.max(MIN_NETWORK_INTERVAL_MS))
+    }
+
+    pub fn read_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.read_timeout_ms.max(MIN_NETWORK_INTERVAL_MS))
+    }
+}
+
+/// Environment variable that overrides `SecurityConfig::pre_shared_key`.
+pub const PRE_SHARED_KEY_ENV: &str = "PLATFORM_PASSER_PSK";
+
+/// How peers are let in beyond pairing.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SecurityConfig {
+    /// A secret shared by machines that cannot confirm pairing codes, such as headless ones.
+    /// Peers that prove they know it are let in without pairing; while it is set, peers that
+    /// cannot are refused.
+    pub pre_shared_key: Option<String>,
+    /// What each peer may do, keyed by its device ID.
+    pub peer_policies: HashMap<String, PeerPolicy>,
+}
+
+impl SecurityConfig {
+    /// The secret in effect: `PLATFORM_PASSER_PSK` if set, else the configured one. Empty
+    /// values count as unset.
+    pub fn pre_shared_key(&self) -> Option<String> {
+        let set = |key: &String| !key.is_empty();
+        std::env::var(PRE_SHARED_KEY_ENV).ok().filter(set).or_else(|| self.pre_shared_key.clone().filter(set))
+    }
+
+    pub fn policy_for(&self, peer: &str) -> PeerPolicy {
+        self.peer_policies.get(peer).copied().unwrap_or_default()
+    }
+}
+
+/// What a peer may do once it is let in. Peers without a policy may do everything.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct PeerPolicy {
+    /// Input in either direction: controlling the peer, or being controlled by it.
+    pub allow_input: bool,
+    /// Clipboard updates from the peer are applied here.
+    pub allow_clipboard_in: bool,
+    /// Clipboard updates here are sent to the peer.
+    pub allow_clipboard_out: bool,
+    /// Files from the peer are accepted, copied ones included.
+    pub allow_file_receive: bool,
+    /// Files are sent to the peer, copied ones included.
+    pub allow_file_send: bool,
+    /// Largest file, in bytes, sent to or accepted from the peer. `None` for no limit.
+    pub max_transfer_bytes: Option<u64>,
+}
+
+impl Default for PeerPolicy {
+    fn default() -> Self {
+        Self {
+            allow_input: true,
+            allow_clipboard_in: true,
+            allow_clipboard_out: true,
+            allow_file_receive: true,
+            allow_file_send: true,
+            max_transfer_bytes: None,
+        }
+    }
+}
+
+/// An action a `PeerPolicy` refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PolicyDenial {
+    Input,
+    ClipboardIn,
+    ClipboardOut,
+    FileReceive,
+    FileSend,
+    TooLarge { size: u64, max: u64 },
+}
+
+impl std::fmt::Display for PolicyDenial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyDenial::Input => write!(f, "input"),
+            PolicyDenial::ClipboardIn => write!(f, "clipboard from the peer"),
+            PolicyDenial::ClipboardOut => write!(f, "clipboard to the peer"),
+            PolicyDenial::FileReceive => write!(f, "files from the peer"),
+            PolicyDenial::FileSend => write!(f, "files to the peer"),
+            PolicyDenial::TooLarge { size, max } => write!(f, "a {} byte file (limit {} bytes)", size, max),
+        }
+    }
+}
+
+impl PeerPolicy {
+    /// Checks a frame about to be sent to the peer.
+    pub fn check_outgoing(&self, frame: &Frame) -> Result<(), PolicyDenial> {
+        self.check(frame, false)
+    }
+
+    /// Checks a frame received from the peer before it is acted on.
+    pub fn check_incoming(&self, frame: &Frame) -> Result<(), PolicyDenial> {
+        self.check(frame, true)
+    }
+
+    fn check(&self, frame: &Frame, incoming: bool) -> Result<(), PolicyDenial> {
+        const CLIPBOARD: [Capability; 3] = [Capability::ClipboardText, Capability::ClipboardImage, Capability::ClipboardFiles];
+        let denial = match frame.capability() {
+            Some(Capability::Input) => (!self.allow_input).then_some(PolicyDenial::Input),
+            Some(cap) if CLIPBOARD.contains(&cap) && incoming => (!self.allow_clipboard_in).then_some(PolicyDenial::ClipboardIn),
+            Some(cap) if CLIPBOARD.contains(&cap) => (!self.allow_clipboard_out).then_some(PolicyDenial::ClipboardOut),
+            // Responses only answer requests, which were checked on their way
+            Some(Capability::FileTransfer) if matches!(frame, Frame::FileTransferResponse(_)) => None,
+            Some(Capability::FileTransfer) if incoming => (!self.allow_file_receive).then_some(PolicyDenial::FileReceive),
+            Some(Capability::FileTransfer) => (!self.allow_file_send).then_some(PolicyDenial::FileSend),
+            _ => None,
+        };
+        let too_large = match (frame, self.max_transfer_bytes) {
+            (Frame::FileTransferRequest(req), Some(max)) if req.file_size > max => Some(PolicyDenial::TooLarge { size: req.file_size, max }),
+            _ => None,
+        };
+        denial.or(too_large).map_or(Ok(()), Err)
+    }
+}
+
+/// Where the config file, the TLS certificate and pinned peer fingerprints live:
+/// `%APPDATA%\platform-passer` on Windows, `~/.config/platform-passer` elsewhere.
+pub fn config_dir() -> std::path::PathBuf {
+    #[cfg(target_os = "windows")]
+    let base = std::path::PathBuf::from(std::env::var("APPDATA").unwrap_or_else(|_| ".".to_string()));
+    #[cfg(not(target_os = "windows"))]
+    let base = std::path::PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string())).join(".config");
+    base.join("platform-passer")
+}
+
+/// The host name, which names this machine until the user picks another. Falls back to the
+/// OS name where none can be found. Looked up on first use, as macOS has to run `scutil` for it.
+pub fn host_name() -> &'static str {
+    static HOST_NAME: OnceLock<String> = OnceLock::new();
+    HOST_NAME.get_or_init(|| {
+        #[cfg(target_os = "macos")]
+        let host = std::process::Command::new("scutil")
+            .args(["--get", "ComputerName"])
+            .output()
+            .ok()
+            .map(|out| String::from_utf8_lossy(&out.stdout).into_owned());
+        #[cfg(target_os = "windows")]
+        let host = std::env::var("COMPUTERNAME").ok();
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        let host = std::env::var("HOSTNAME").ok().or_else(|| std::fs::read_to_string("/etc/hostname").ok());
+        host.map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| std::env::consts::OS.to_string())
+    })
+}
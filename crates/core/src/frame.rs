@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 pub enum Frame {
     Handshake(Handshake),
     Heartbeat(Heartbeat),
-    Input(InputEvent),
+    Input(InputFrame),
     Clipboard(ClipboardEvent),
     FileTransferRequest(FileTransferRequest),
     FileTransferResponse(FileTransferResponse),
@@ -12,6 +12,73 @@ pub enum Frame {
     FileEnd { id: u32 },
     ScreenSwitch(ScreenSide),
     Notification { title: String, message: String },
+    /// Sent instead of a `Handshake` reply when the session cannot be established.
+    HandshakeReject(RejectReason),
+    /// The sender's held modifiers and lock keys, sent on entering the peer's screen and after a
+    /// lock key toggles. The receiving sink brings its own state in line before further keys.
+    ModifierState(ModifierState),
+    /// Device authentication and pairing, exchanged right after the handshake. Nothing else is
+    /// sent to a peer until the server has answered `DeviceAuth::Accepted`.
+    DeviceAuth(DeviceAuth),
+}
+
+/// Steps of the device authentication that follows the handshake, in order. The client commits
+/// to its nonce before it sees the server's, so neither side can steer the pairing code both
+/// derive from the exchange. Each side then proves it holds the key it announced. The client
+/// says whether it trusts the server, and the server answers `Accepted` only if the trust is
+/// mutual; otherwise `PairingRequired` while its user enters the code the client shows.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum DeviceAuth {
+    /// Client to server: its long-term Ed25519 public key and a hash of its nonce.
+    Commit { public_key: Vec<u8>, commitment: Vec<u8> },
+    /// Server to client: its long-term Ed25519 public key and nonce.
+    Hello { public_key: Vec<u8>, nonce: Vec<u8> },
+    /// Client to server: the nonce committed to.
+    Reveal { nonce: Vec<u8> },
+    /// Both ways: the sender's signature over the exchange.
+    Proof(Vec<u8>),
+    /// From the client: it does not trust the server yet. From the server: the devices are not
+    /// paired yet, and it is waiting for its user to enter the code.
+    PairingRequired,
+    /// From the client: it trusts the server. From the server: the session starts.
+    Accepted,
+    /// Both ways, in place of the client's verdict and before the server's `Accepted`: an
+    /// HMAC over the exchange keyed with the pre-shared key. Protocol 14 and later.
+    SecretProof(Vec<u8>),
+}
+
+/// Which modifiers are held and which lock keys are on.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ModifierState {
+    /// HID modifier byte: bit `n` is usage `0xE0 + n`, so Left Control, Left Shift, Left Alt,
+    /// Left GUI, then the same four on the right.
+    pub held: u8,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl ModifierState {
+    /// HID usages of the eight modifier keys, in `held` bit order.
+    pub const MODIFIER_USAGES: std::ops::RangeInclusive<u16> = 0xE0..=0xE7;
+
+    pub fn is_held(&self, usage: u16) -> bool {
+        Self::bit(usage).is_some_and(|bit| self.held & bit != 0)
+    }
+
+    pub fn set_held(&mut self, usage: u16, held: bool) {
+        if let Some(bit) = Self::bit(usage) {
+            if held {
+                self.held |= bit;
+            } else {
+                self.held &= !bit;
+            }
+        }
+    }
+
+    fn bit(usage: u16) -> Option<u8> {
+        Self::MODIFIER_USAGES.contains(&usage).then(|| 1 << (usage - 0xE0))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
@@ -64,15 +131,262 @@ use crate::config::ScreenInfo;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Handshake {
+    /// Highest protocol version the sender supports.
     pub version: u32,
+    /// Lowest protocol version the sender still supports.
+    pub min_version: u32,
+    /// The sender's device ID, derived from the device key it proves right after.
     pub client_id: String,
-    pub capabilities: Vec<String>,
+    pub capabilities: CapabilitySet,
     pub screen_info: Option<ScreenInfo>,
+    /// The name the sender's user gave it, empty if unknown. Trails the payload like
+    /// `ScreenInfo::displays`, so it is not part of this struct's own encoding.
+    #[serde(skip)]
+    pub device_name: String,
+}
+
+impl Handshake {
+    /// Builds a handshake advertising the protocol range of this build.
+    pub fn new(client_id: String, capabilities: CapabilitySet, screen_info: Option<ScreenInfo>) -> Self {
+        Self {
+            version: crate::protocol::PROTOCOL_VERSION,
+            min_version: crate::protocol::MIN_PROTOCOL_VERSION,
+            client_id,
+            capabilities,
+            screen_info,
+            device_name: String::new(),
+        }
+    }
+
+    /// Names the sending device.
+    pub fn with_device_name(mut self, name: impl Into<String>) -> Self {
+        self.device_name = name.into();
+        self
+    }
+
+    /// Picks the protocol version to use with `peer`. Both sides compute the same result.
+    pub fn negotiate_version(&self, peer: &Handshake) -> Result<u32, RejectReason> {
+        crate::protocol::negotiate_version(self.min_version, self.version, peer.min_version, peer.version)
+    }
+}
+
+/// Optional frame families. A family is only exchanged when both peers advertise it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    Input,
+    ClipboardText,
+    ClipboardImage,
+    ClipboardFiles,
+    FileTransfer,
+    Notifications,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 6] = [
+        Capability::Input,
+        Capability::ClipboardText,
+        Capability::ClipboardImage,
+        Capability::ClipboardFiles,
+        Capability::FileTransfer,
+        Capability::Notifications,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Input => "input",
+            Capability::ClipboardText => "clipboard-text",
+            Capability::ClipboardImage => "clipboard-image",
+            Capability::ClipboardFiles => "clipboard-files",
+            Capability::FileTransfer => "file-transfer",
+            Capability::Notifications => "notifications",
+        }
+    }
+
+    // Bit positions are part of the wire format: never reorder, only append.
+    fn bit(self) -> u64 {
+        1 << match self {
+            Capability::Input => 0,
+            Capability::ClipboardText => 1,
+            Capability::ClipboardImage => 2,
+            Capability::ClipboardFiles => 3,
+            Capability::FileTransfer => 4,
+            Capability::Notifications => 5,
+        }
+    }
+}
+
+/// Bitmask of capabilities. Bits unknown to this build are kept so they intersect away harmlessly.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CapabilitySet(u64);
+
+impl CapabilitySet {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Every capability this build knows how to handle.
+    pub fn all() -> Self {
+        Capability::ALL.iter().fold(Self::empty(), |set, cap| set.with(*cap))
+    }
+
+    pub fn with(self, cap: Capability) -> Self {
+        Self(self.0 | cap.bit())
+    }
+
+    pub fn without(self, cap: Capability) -> Self {
+        Self(self.0 & !cap.bit())
+    }
+
+    pub fn contains(&self, cap: Capability) -> bool {
+        self.0 & cap.bit() != 0
+    }
+
+    pub fn intersection(&self, other: &CapabilitySet) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Capability> + '_ {
+        Capability::ALL.iter().copied().filter(move |cap| self.contains(*cap))
+    }
+
+    /// Whether `frame` may be exchanged under this (negotiated) set.
+    pub fn allows(&self, frame: &Frame) -> bool {
+        frame.capability().is_none_or(|cap| self.contains(cap))
+    }
+}
+
+impl std::fmt::Display for CapabilitySet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<&str> = self.iter().map(Capability::name).collect();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+impl Frame {
+    /// The capability a frame belongs to, or `None` for control frames every peer must understand.
+    pub fn capability(&self) -> Option<Capability> {
+        match self {
+            Frame::Handshake(_) | Frame::Heartbeat(_) | Frame::HandshakeReject(_) | Frame::DeviceAuth(_) => None,
+            Frame::Input(_) | Frame::ScreenSwitch(_) | Frame::ModifierState(_) => Some(Capability::Input),
+            Frame::Clipboard(ClipboardEvent::Text(_)) => Some(Capability::ClipboardText),
+            Frame::Clipboard(ClipboardEvent::Image { .. }) => Some(Capability::ClipboardImage),
+            Frame::Clipboard(ClipboardEvent::Files { .. }) => Some(Capability::ClipboardFiles),
+            Frame::FileTransferRequest(_)
+            | Frame::FileTransferResponse(_)
+            | Frame::FileData { .. }
+            | Frame::FileEnd { .. } => Some(Capability::FileTransfer),
+            Frame::Notification { .. } => Some(Capability::Notifications),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum RejectReason {
+    /// The peer's supported range does not overlap ours.
+    UnsupportedVersion { min_supported: u32, max_supported: u32 },
+    /// The device is not trusted and pairing was declined or timed out.
+    NotPaired,
+    /// Only one side has a pre-shared key, or the keys differ. Protocol 14 and later.
+    SecretMismatch,
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectReason::UnsupportedVersion { min_supported, max_supported } => write!(
+                f,
+                "unsupported protocol version (peer supports {}..={})",
+                min_supported, max_supported
+            ),
+            RejectReason::NotPaired => write!(f, "device not paired"),
+            RejectReason::SecretMismatch => write!(f, "pre-shared key mismatch"),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Heartbeat {
+    /// Sender's clock in microseconds since the Unix epoch. Protocol 6 and later; whole seconds
+    /// before.
     pub timestamp: u64,
+    /// Set on the echo of a peer's heartbeat. Protocol 6 and later.
+    pub reply: bool,
+}
+
+impl Heartbeat {
+    pub fn ping() -> Self {
+        Self { timestamp: unix_time_us(), reply: false }
+    }
+
+    /// The echo to send back for this heartbeat.
+    pub fn to_reply(&self) -> Self {
+        Self { timestamp: self.timestamp, reply: true }
+    }
+}
+
+/// A captured `InputEvent` together with its capture stamp.
+///
+/// Peers older than protocol 5 send no stamp; their frames decode with `stamp: None`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InputFrame {
+    pub event: InputEvent,
+    pub stamp: Option<InputStamp>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct InputStamp {
+    /// Per-connection counter, incremented once per stamped frame sent to the peer.
+    pub seq: u64,
+    /// Capture time in microseconds since the Unix epoch, from the sender's clock.
+    pub captured_at_us: u64,
+}
+
+impl InputFrame {
+    /// Stamps `event` with `seq` and the current time.
+    pub fn stamped(event: InputEvent, seq: u64) -> Self {
+        Self { event, stamp: Some(InputStamp { seq, captured_at_us: unix_time_us() }) }
+    }
+
+    /// Stamps `event` with the current time. An `InputSequencer` numbers it once it is certain
+    /// to reach a peer.
+    pub fn captured(event: InputEvent) -> Self {
+        Self::stamped(event, 0)
+    }
+}
+
+/// Numbers the stamped input frames sent to one peer. Applied after every filter that may drop
+/// a frame on its way there, so that the peer counts only frames lost in transit as lost.
+#[derive(Debug, Default)]
+pub struct InputSequencer {
+    next: u64,
+}
+
+impl InputSequencer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives `frame` the next sequence number, if it is stamped input.
+    pub fn number(&mut self, frame: &mut Frame) {
+        if let Frame::Input(InputFrame { stamp: Some(stamp), .. }) = frame {
+            stamp.seq = self.next;
+            self.next += 1;
+        }
+    }
+}
+
+impl From<InputEvent> for InputFrame {
+    fn from(event: InputEvent) -> Self {
+        Self { event, stamp: None }
+    }
+}
+
+/// Microseconds since the Unix epoch on this machine's clock.
+pub fn unix_time_us() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -80,9 +394,79 @@ pub enum InputEvent {
     /// Normalized coordinates from 0.0 to 1.0
     MouseMove { x: f32, y: f32 },
     MouseButton { button: MouseButton, is_down: bool },
-    Keyboard { key_code: u32, is_down: bool },
+    /// Windows virtual-key code, from before protocol 9. No longer sent; kept so the variants
+    /// after it keep their wire index.
+    KeyboardVk { key_code: u32, is_down: bool },
+    /// Whole-line scroll, from before protocol 8. No longer sent; sources emit `SmoothScroll`.
     Scroll { dx: f32, dy: f32 },
     ScreenSwitch(ScreenSide),
+    /// High-resolution scroll. Protocol 8 and later.
+    ///
+    /// Deltas are fractional and must not be rounded by the sender. A sink that can only emit
+    /// whole steps carries the remainder into the next event of the same gesture (see
+    /// `ScrollAccumulator` in the input crate) and drops it when the gesture begins or ends.
+    SmoothScroll { dx: f32, dy: f32, unit: ScrollUnit, phase: ScrollPhase },
+    /// Physical key as a USB HID usage (keyboard page 0x07, see `keymap`). Protocol 9 and later.
+    ///
+    /// `usage` is 0 for keys the table does not know; only `hint` identifies those.
+    Keyboard { usage: u16, is_down: bool, hint: Option<KeyHint> },
+    /// Characters typed on the sender's layout. Protocol 10 and later.
+    ///
+    /// Sources emit it immediately before the key-down that produced it; each peer then gets
+    /// either the text or the key, depending on its `LayoutMode`.
+    Text(String),
+}
+
+/// The capturing platform's own code for a key.
+///
+/// A sink on the same platform injects this instead of converting the usage, so keys the HID
+/// table does not cover still arrive. Other platforms ignore it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum KeyHint {
+    Windows { vk: u32, scancode: u16 },
+    Macos { keycode: u16 },
+    Linux { evdev: u16 },
+}
+
+/// Nominal pixels per scroll line, for converting between `ScrollUnit`s.
+pub const PIXELS_PER_LINE: f32 = 10.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ScrollUnit {
+    /// Wheel lines (notches). Fractions come from high-resolution wheels.
+    Line,
+    /// Pixels, as reported by trackpads and precision touchpads.
+    Pixel,
+}
+
+impl ScrollUnit {
+    /// Converts `delta` in this unit to lines.
+    pub fn to_lines(self, delta: f32) -> f32 {
+        match self {
+            ScrollUnit::Line => delta,
+            ScrollUnit::Pixel => delta / PIXELS_PER_LINE,
+        }
+    }
+}
+
+/// Where a scroll event sits within a gesture.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ScrollPhase {
+    /// A discrete wheel event outside any gesture.
+    None,
+    Began,
+    Changed,
+    Ended,
+    /// Inertial scrolling after the fingers lifted.
+    Momentum,
+    MomentumEnded,
+}
+
+impl ScrollPhase {
+    /// Whether this event closes the gesture, so leftover fractions should be dropped.
+    pub fn is_end(self) -> bool {
+        matches!(self, ScrollPhase::Ended | ScrollPhase::MomentumEnded)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
@@ -90,4 +474,36 @@ pub enum MouseButton {
     Left,
     Right,
     Middle,
+    /// First thumb button, usually "back" (X1 on Windows). Protocol 7 and later.
+    Back,
+    /// Second thumb button, usually "forward" (X2 on Windows). Protocol 7 and later.
+    Forward,
+    /// Any further button by its zero-based number. Protocol 7 and later.
+    Other(u8),
+}
+
+impl MouseButton {
+    /// Maps a zero-based button number (0 left, 1 right, 2 middle, 3 back, 4 forward, ...)
+    /// as reported by macOS and by most mouse drivers.
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            0 => MouseButton::Left,
+            1 => MouseButton::Right,
+            2 => MouseButton::Middle,
+            3 => MouseButton::Back,
+            4 => MouseButton::Forward,
+            n => MouseButton::Other(n),
+        }
+    }
+
+    pub fn index(self) -> u8 {
+        match self {
+            MouseButton::Left => 0,
+            MouseButton::Right => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Back => 3,
+            MouseButton::Forward => 4,
+            MouseButton::Other(n) => n,
+        }
+    }
 }
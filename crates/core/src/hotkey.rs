@@ -0,0 +1,182 @@
+//! Key chords as written in the config, such as `"Ctrl+Alt+Escape"`.
+use serde::{Deserialize, Serialize};
+
+/// Side-independent modifier flags a chord requires.
+pub mod modifiers {
+    pub const CTRL: u8 = 0x1;
+    pub const SHIFT: u8 = 0x2;
+    pub const ALT: u8 = 0x4;
+    /// Command on macOS, the Windows key elsewhere.
+    pub const META: u8 = 0x8;
+}
+
+/// A key pressed while exactly a set of modifiers is held, either side counting.
+///
+/// In the config it is a `+`-separated string of modifier names and one key name:
+/// `Ctrl`/`Control`, `Shift`, `Alt`/`Option`, `Meta`/`Cmd`/`Command`/`Win`/`Super`, then a
+/// letter, digit, `F1`..`F24`, a named key such as `Escape` or `PageUp`, or a raw HID usage
+/// like `0x46`. Names are case-insensitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Chord {
+    /// `modifiers::*` flags.
+    pub modifiers: u8,
+    /// HID usage of the non-modifier key.
+    pub key: u16,
+}
+
+impl Chord {
+    pub fn new(modifiers: u8, key: u16) -> Self {
+        Self { modifiers, key }
+    }
+
+    /// The side-independent flag for a HID modifier usage (0xE0..=0xE7), if it is one.
+    pub fn modifier_flag(usage: u16) -> Option<u8> {
+        match usage {
+            0xE0 | 0xE4 => Some(modifiers::CTRL),
+            0xE1 | 0xE5 => Some(modifiers::SHIFT),
+            0xE2 | 0xE6 => Some(modifiers::ALT),
+            0xE3 | 0xE7 => Some(modifiers::META),
+            _ => None,
+        }
+    }
+}
+
+/// Named keys, beside letters, digits and function keys. The first name for a usage is the one
+/// chords are written back with.
+const KEY_NAMES: &[(&str, u16)] = &[
+    ("Enter", 0x28),
+    ("Return", 0x28),
+    ("Escape", 0x29),
+    ("Esc", 0x29),
+    ("Backspace", 0x2A),
+    ("Tab", 0x2B),
+    ("Space", 0x2C),
+    ("Minus", 0x2D),
+    ("Equal", 0x2E),
+    ("LeftBracket", 0x2F),
+    ("RightBracket", 0x30),
+    ("Backslash", 0x31),
+    ("Semicolon", 0x33),
+    ("Quote", 0x34),
+    ("Grave", 0x35),
+    ("Comma", 0x36),
+    ("Period", 0x37),
+    ("Slash", 0x38),
+    ("CapsLock", 0x39),
+    ("PrintScreen", 0x46),
+    ("ScrollLock", 0x47),
+    ("Pause", 0x48),
+    ("Insert", 0x49),
+    ("Home", 0x4A),
+    ("PageUp", 0x4B),
+    ("Delete", 0x4C),
+    ("End", 0x4D),
+    ("PageDown", 0x4E),
+    ("Right", 0x4F),
+    ("Left", 0x50),
+    ("Down", 0x51),
+    ("Up", 0x52),
+    ("NumLock", 0x53),
+];
+
+const MODIFIER_NAMES: &[(&str, u8)] = &[
+    ("ctrl", modifiers::CTRL),
+    ("control", modifiers::CTRL),
+    ("shift", modifiers::SHIFT),
+    ("alt", modifiers::ALT),
+    ("option", modifiers::ALT),
+    ("meta", modifiers::META),
+    ("cmd", modifiers::META),
+    ("command", modifiers::META),
+    ("win", modifiers::META),
+    ("super", modifiers::META),
+];
+
+fn parse_key(name: &str) -> Option<u16> {
+    let lower = name.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        return u16::from_str_radix(hex, 16).ok().filter(|usage| Chord::modifier_flag(*usage).is_none());
+    }
+    let mut chars = lower.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            'a'..='z' => Some(0x04 + (c as u16 - 'a' as u16)),
+            '1'..='9' => Some(0x1E + (c as u16 - '1' as u16)),
+            '0' => Some(0x27),
+            _ => None,
+        };
+    }
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u16>().ok()) {
+        return match n {
+            1..=12 => Some(0x3A + n - 1),
+            13..=24 => Some(0x68 + n - 13),
+            _ => None,
+        };
+    }
+    KEY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, usage)| *usage)
+}
+
+fn key_name(usage: u16) -> String {
+    match usage {
+        0x04..=0x1D => ((b'A' + (usage - 0x04) as u8) as char).to_string(),
+        0x1E..=0x26 => ((b'1' + (usage - 0x1E) as u8) as char).to_string(),
+        0x27 => "0".to_string(),
+        0x3A..=0x45 => format!("F{}", usage - 0x3A + 1),
+        0x68..=0x73 => format!("F{}", usage - 0x68 + 13),
+        _ => match KEY_NAMES.iter().find(|(_, u)| *u == usage) {
+            Some((name, _)) => name.to_string(),
+            None => format!("{:#04x}", usage),
+        },
+    }
+}
+
+impl std::str::FromStr for Chord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key_part = parts.pop().filter(|k| !k.is_empty()).ok_or_else(|| format!("missing key in chord {:?}", s))?;
+        let key = parse_key(key_part).ok_or_else(|| format!("unknown key {:?} in chord {:?}", key_part, s))?;
+        let mut mods = 0;
+        for part in parts {
+            let flag = MODIFIER_NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(part))
+                .map(|(_, flag)| *flag)
+                .ok_or_else(|| format!("unknown modifier {:?} in chord {:?}", part, s))?;
+            mods |= flag;
+        }
+        Ok(Chord { modifiers: mods, key })
+    }
+}
+
+impl std::fmt::Display for Chord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (flag, name) in [
+            (modifiers::CTRL, "Ctrl"),
+            (modifiers::ALT, "Alt"),
+            (modifiers::SHIFT, "Shift"),
+            (modifiers::META, "Meta"),
+        ] {
+            if self.modifiers & flag != 0 {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", key_name(self.key))
+    }
+}
+
+impl TryFrom<String> for Chord {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Chord> for String {
+    fn from(chord: Chord) -> Self {
+        chord.to_string()
+    }
+}
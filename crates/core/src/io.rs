@@ -1,26 +1,41 @@
+use crate::config::FrameLimits;
+use crate::wire::{decode_payload, encode_frame, EnvelopeHeader, FrameError, HEADER_LEN};
 use crate::Frame;
-use anyhow::Result;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
-pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &Frame) -> Result<()> {
-    let bytes = bincode::serialize(frame)?;
-    let len = bytes.len() as u32;
-    writer.write_u32_le(len).await?;
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &Frame) -> Result<(), FrameError> {
+    let bytes = encode_frame(frame)?;
     writer.write_all(&bytes).await?;
     writer.flush().await?;
     Ok(())
 }
 
-pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Frame>> {
-    let len = match reader.read_u32_le().await {
-        Ok(l) => l,
-        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
-        Err(e) => return Err(e.into()),
-    };
+/// Reads the next frame, skipping frame types this build does not understand.
+///
+/// The declared length is checked against `limits` before the payload buffer is allocated.
+/// Exactly one envelope is read per call; for a long-lived stream, `FramedRead` with a
+/// `FrameCodec` avoids the separate header read.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R, limits: &FrameLimits) -> Result<Option<Frame>, FrameError> {
+    loop {
+        let mut header = [0u8; HEADER_LEN];
+        match reader.read_exact(&mut header).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let header = EnvelopeHeader::parse(&header)?;
+        header.check(limits)?;
 
-    let mut buf = vec![0u8; len as usize];
-    reader.read_exact(&mut buf).await?;
+        let expected = header.len as usize;
+        let mut buf = Vec::with_capacity(expected);
+        let actual = (&mut *reader).take(header.len as u64).read_to_end(&mut buf).await?;
+        if actual < expected {
+            return Err(FrameError::Truncated { expected, actual });
+        }
 
-    let frame: Frame = bincode::deserialize(&buf)?;
-    Ok(Some(frame))
+        match decode_payload(header.tag, &buf) {
+            Err(e) if e.is_recoverable() => continue,
+            other => return other.map(Some),
+        }
+    }
 }
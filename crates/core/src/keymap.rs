@@ -0,0 +1,228 @@
+//! Physical key codes: USB HID usages (keyboard page 0x07) and their platform equivalents.
+//!
+//! Every platform maps to and from HID only, so adding a platform means adding one column
+//! rather than a table per pair. Windows scancodes use the set 1 value, with `0xE0` in the
+//! high byte for extended keys (`0xE01D` is Right Control).
+//!
+//! Usages describe where a key sits, not what it types, so the same usage produces `Q` on
+//! QWERTY and `A` on AZERTY. Windows virtual-key codes are layout dependent; prefer the
+//! scancode when converting Windows input.
+
+/// Marks a key that has no code on a platform.
+const NA: u16 = u16::MAX;
+
+struct Key {
+    usage: u16,
+    vk: u16,
+    scancode: u16,
+    macos: u16,
+    evdev: u16,
+}
+
+const fn key(usage: u16, vk: u16, scancode: u16, macos: u16, evdev: u16) -> Key {
+    Key { usage, vk, scancode, macos, evdev }
+}
+
+/// Reverse lookups return the first row that matches, so where a code is shared (keypad Enter
+/// has the same virtual-key code as Enter) the main key comes first.
+#[rustfmt::skip]
+const KEYS: &[Key] = &[
+    //  usage   vk      scan    macos  evdev
+    key(0x04, 0x41, 0x001E,   0,  30), // A
+    key(0x05, 0x42, 0x0030,  11,  48), // B
+    key(0x06, 0x43, 0x002E,   8,  46), // C
+    key(0x07, 0x44, 0x0020,   2,  32), // D
+    key(0x08, 0x45, 0x0012,  14,  18), // E
+    key(0x09, 0x46, 0x0021,   3,  33), // F
+    key(0x0A, 0x47, 0x0022,   5,  34), // G
+    key(0x0B, 0x48, 0x0023,   4,  35), // H
+    key(0x0C, 0x49, 0x0017,  34,  23), // I
+    key(0x0D, 0x4A, 0x0024,  38,  36), // J
+    key(0x0E, 0x4B, 0x0025,  40,  37), // K
+    key(0x0F, 0x4C, 0x0026,  37,  38), // L
+    key(0x10, 0x4D, 0x0032,  46,  50), // M
+    key(0x11, 0x4E, 0x0031,  45,  49), // N
+    key(0x12, 0x4F, 0x0018,  31,  24), // O
+    key(0x13, 0x50, 0x0019,  35,  25), // P
+    key(0x14, 0x51, 0x0010,  12,  16), // Q
+    key(0x15, 0x52, 0x0013,  15,  19), // R
+    key(0x16, 0x53, 0x001F,   1,  31), // S
+    key(0x17, 0x54, 0x0014,  17,  20), // T
+    key(0x18, 0x55, 0x0016,  32,  22), // U
+    key(0x19, 0x56, 0x002F,   9,  47), // V
+    key(0x1A, 0x57, 0x0011,  13,  17), // W
+    key(0x1B, 0x58, 0x002D,   7,  45), // X
+    key(0x1C, 0x59, 0x0015,  16,  21), // Y
+    key(0x1D, 0x5A, 0x002C,   6,  44), // Z
+    key(0x1E, 0x31, 0x0002,  18,   2), // 1
+    key(0x1F, 0x32, 0x0003,  19,   3), // 2
+    key(0x20, 0x33, 0x0004,  20,   4), // 3
+    key(0x21, 0x34, 0x0005,  21,   5), // 4
+    key(0x22, 0x35, 0x0006,  23,   6), // 5
+    key(0x23, 0x36, 0x0007,  22,   7), // 6
+    key(0x24, 0x37, 0x0008,  26,   8), // 7
+    key(0x25, 0x38, 0x0009,  28,   9), // 8
+    key(0x26, 0x39, 0x000A,  25,  10), // 9
+    key(0x27, 0x30, 0x000B,  29,  11), // 0
+    key(0x28, 0x0D, 0x001C,  36,  28), // Enter
+    key(0x29, 0x1B, 0x0001,  53,   1), // Escape
+    key(0x2A, 0x08, 0x000E,  51,  14), // Backspace
+    key(0x2B, 0x09, 0x000F,  48,  15), // Tab
+    key(0x2C, 0x20, 0x0039,  49,  57), // Space
+    key(0x2D, 0xBD, 0x000C,  27,  12), // -
+    key(0x2E, 0xBB, 0x000D,  24,  13), // =
+    key(0x2F, 0xDB, 0x001A,  33,  26), // [
+    key(0x30, 0xDD, 0x001B,  30,  27), // ]
+    key(0x31, 0xDC, 0x002B,  42,  43), // \
+    key(0x33, 0xBA, 0x0027,  41,  39), // ;
+    key(0x34, 0xDE, 0x0028,  39,  40), // '
+    key(0x35, 0xC0, 0x0029,  50,  41), // `
+    key(0x36, 0xBC, 0x0033,  43,  51), // ,
+    key(0x37, 0xBE, 0x0034,  47,  52), // .
+    key(0x38, 0xBF, 0x0035,  44,  53), // /
+    key(0x39, 0x14, 0x003A,  57,  58), // Caps Lock
+
+    // Function keys
+    key(0x3A, 0x70, 0x003B, 122,  59), // F1
+    key(0x3B, 0x71, 0x003C, 120,  60), // F2
+    key(0x3C, 0x72, 0x003D,  99,  61), // F3
+    key(0x3D, 0x73, 0x003E, 118,  62), // F4
+    key(0x3E, 0x74, 0x003F,  96,  63), // F5
+    key(0x3F, 0x75, 0x0040,  97,  64), // F6
+    key(0x40, 0x76, 0x0041,  98,  65), // F7
+    key(0x41, 0x77, 0x0042, 100,  66), // F8
+    key(0x42, 0x78, 0x0043, 101,  67), // F9
+    key(0x43, 0x79, 0x0044, 109,  68), // F10
+    key(0x44, 0x7A, 0x0057, 103,  87), // F11
+    key(0x45, 0x7B, 0x0058, 111,  88), // F12
+
+    // Navigation
+    key(0x46, 0x2C, 0xE037,  NA,  99), // Print Screen
+    key(0x47, 0x91, 0x0046,  NA,  70), // Scroll Lock
+    key(0x48, 0x13,     NA,  NA, 119), // Pause
+    key(0x49, 0x2D, 0xE052, 114, 110), // Insert (Help on Mac keyboards)
+    key(0x4A, 0x24, 0xE047, 115, 102), // Home
+    key(0x4B, 0x21, 0xE049, 116, 104), // Page Up
+    key(0x4C, 0x2E, 0xE053, 117, 111), // Forward Delete
+    key(0x4D, 0x23, 0xE04F, 119, 107), // End
+    key(0x4E, 0x22, 0xE051, 121, 109), // Page Down
+    key(0x4F, 0x27, 0xE04D, 124, 106), // Right
+    key(0x50, 0x25, 0xE04B, 123, 105), // Left
+    key(0x51, 0x28, 0xE050, 125, 108), // Down
+    key(0x52, 0x26, 0xE048, 126, 103), // Up
+
+    // Keypad
+    key(0x53, 0x90, 0xE045,  71,  69), // Num Lock (Clear on Mac keyboards)
+    key(0x54, 0x6F, 0xE035,  75,  98), // Keypad /
+    key(0x55, 0x6A, 0x0037,  67,  55), // Keypad *
+    key(0x56, 0x6D, 0x004A,  78,  74), // Keypad -
+    key(0x57, 0x6B, 0x004E,  69,  78), // Keypad +
+    key(0x58, 0x0D, 0xE01C,  76,  96), // Keypad Enter
+    key(0x59, 0x61, 0x004F,  83,  79), // Keypad 1
+    key(0x5A, 0x62, 0x0050,  84,  80), // Keypad 2
+    key(0x5B, 0x63, 0x0051,  85,  81), // Keypad 3
+    key(0x5C, 0x64, 0x004B,  86,  75), // Keypad 4
+    key(0x5D, 0x65, 0x004C,  87,  76), // Keypad 5
+    key(0x5E, 0x66, 0x004D,  88,  77), // Keypad 6
+    key(0x5F, 0x67, 0x0047,  89,  71), // Keypad 7
+    key(0x60, 0x68, 0x0048,  91,  72), // Keypad 8
+    key(0x61, 0x69, 0x0049,  92,  73), // Keypad 9
+    key(0x62, 0x60, 0x0052,  82,  82), // Keypad 0
+    key(0x63, 0x6E, 0x0053,  65,  83), // Keypad .
+    key(0x67, 0x92, 0x0059,  81, 117), // Keypad = (VK_OEM_NEC_EQUAL)
+
+    // Keys outside the US layout
+    key(0x64, 0xE2, 0x0056,  10,  86), // ISO \ next to Left Shift (VK_OEM_102)
+    key(0x65, 0x5D, 0xE05D,  NA, 127), // Application / Menu
+    key(0x87, 0xC1, 0x0073,  94,  89), // JIS Ro
+    key(0x89,   NA, 0x007D,  93, 124), // JIS Yen
+    key(0x90,   NA,     NA, 104, 122), // Kana / Hangul
+    key(0x91,   NA,     NA, 102, 123), // Eisu / Hanja
+
+    // F13 and up
+    key(0x68, 0x7C, 0x0064, 105, 183), // F13
+    key(0x69, 0x7D, 0x0065, 107, 184), // F14
+    key(0x6A, 0x7E, 0x0066, 113, 185), // F15
+    key(0x6B, 0x7F, 0x0067, 106, 186), // F16
+    key(0x6C, 0x80, 0x0068,  64, 187), // F17
+    key(0x6D, 0x81, 0x0069,  79, 188), // F18
+    key(0x6E, 0x82, 0x006A,  80, 189), // F19
+    key(0x6F, 0x83, 0x006B,  90, 190), // F20
+
+    // Media
+    key(0x7F, 0xAD, 0xE020,  74, 113), // Mute
+    key(0x80, 0xAF, 0xE030,  72, 115), // Volume Up
+    key(0x81, 0xAE, 0xE02E,  73, 114), // Volume Down
+
+    // Modifiers
+    key(0xE0, 0xA2, 0x001D,  59,  29), // Left Control
+    key(0xE1, 0xA0, 0x002A,  56,  42), // Left Shift
+    key(0xE2, 0xA4, 0x0038,  58,  56), // Left Alt / Option
+    key(0xE3, 0x5B, 0xE05B,  55, 125), // Left Windows / Command
+    key(0xE4, 0xA3, 0xE01D,  62,  97), // Right Control
+    key(0xE5, 0xA1, 0x0036,  60,  54), // Right Shift
+    key(0xE6, 0xA5, 0xE038,  61, 100), // Right Alt / Option
+    key(0xE7, 0x5C, 0xE05C,  54, 126), // Right Windows / Command
+];
+
+/// Virtual-key codes that name a key without saying which one; they map to the left-hand key.
+const VK_ALIASES: &[(u16, u16)] = &[
+    (0x10, 0xE1), // VK_SHIFT
+    (0x11, 0xE0), // VK_CONTROL
+    (0x12, 0xE2), // VK_MENU
+    (0x0C, 0x53), // VK_CLEAR
+];
+
+fn by_usage(usage: u16) -> Option<&'static Key> {
+    KEYS.iter().find(|k| k.usage == usage)
+}
+
+fn by_code(code: u16, column: fn(&Key) -> u16) -> Option<u16> {
+    if code == NA {
+        return None;
+    }
+    KEYS.iter().find(|k| column(k) == code).map(|k| k.usage)
+}
+
+fn present(code: u16) -> Option<u16> {
+    (code != NA).then_some(code)
+}
+
+/// All usages with at least one platform mapping, in table order.
+pub fn usages() -> impl Iterator<Item = u16> {
+    KEYS.iter().map(|k| k.usage)
+}
+
+pub fn hid_to_windows_vk(usage: u16) -> Option<u32> {
+    by_usage(usage).and_then(|k| present(k.vk)).map(u32::from)
+}
+
+pub fn windows_vk_to_hid(vk: u32) -> Option<u16> {
+    let vk = u16::try_from(vk).ok()?;
+    by_code(vk, |k| k.vk).or_else(|| VK_ALIASES.iter().find(|(alias, _)| *alias == vk).map(|(_, usage)| *usage))
+}
+
+/// Set 1 scancode, with `0xE0` in the high byte for extended keys.
+pub fn hid_to_windows_scancode(usage: u16) -> Option<u16> {
+    by_usage(usage).and_then(|k| present(k.scancode))
+}
+
+pub fn windows_scancode_to_hid(scancode: u16) -> Option<u16> {
+    by_code(scancode, |k| k.scancode)
+}
+
+pub fn hid_to_macos_keycode(usage: u16) -> Option<u16> {
+    by_usage(usage).and_then(|k| present(k.macos))
+}
+
+pub fn macos_keycode_to_hid(keycode: u16) -> Option<u16> {
+    by_code(keycode, |k| k.macos)
+}
+
+pub fn hid_to_evdev(usage: u16) -> Option<u16> {
+    by_usage(usage).and_then(|k| present(k.evdev))
+}
+
+pub fn evdev_to_hid(code: u16) -> Option<u16> {
+    by_code(code, |k| k.evdev)
+}
@@ -1,7 +1,19 @@
 pub mod frame;
 pub mod io;
 pub mod config;
+pub mod protocol;
+pub mod wire;
+pub mod codec;
+pub mod keymap;
+pub mod hotkey;
+pub mod topology;
+pub mod mapping;
 
 pub use frame::*;
 pub use io::*;
 pub use config::*;
+pub use protocol::*;
+pub use wire::{encode_frame, decode_frame, FrameError};
+pub use codec::FrameCodec;
+pub use hotkey::Chord;
+pub use topology::VirtualDesktop;
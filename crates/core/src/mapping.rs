@@ -0,0 +1,52 @@
+//! How screens of different size line up on the virtual desktop, and how cursor motion carries
+//! over onto them.
+//!
+//! Everything is in desktop units: logical points on macOS, pixels on Windows. Each OS already
+//! scales those for its display density, so a unit is roughly the same physical size on every
+//! machine and sizes compare directly. `dpi_scale` (device pixels per unit) is not applied again.
+use crate::config::ScreenPosition;
+use crate::topology::Rect;
+
+/// Where a remote screen of size `remote` goes next to a local screen of size `local`, which
+/// sits at the origin.
+///
+/// With `maintain_aspect_ratio`, side remotes keep their own size and are centered on the shared
+/// edge, so a cursor crossing it stays level with where it left, however the two resolutions
+/// differ. Where one screen overhangs the other, the overhang faces nothing and does not switch.
+/// Without it, side remotes are scaled to span the whole shared edge and positions along it
+/// carry over proportionally. `Absolute` remotes always sit at their own size.
+pub fn place(local: (f64, f64), remote: (f64, f64), position: &ScreenPosition, maintain_aspect_ratio: bool) -> Rect {
+    let (local_width, local_height) = local;
+    let (width, height) = remote;
+    if let ScreenPosition::Absolute { x, y } = position {
+        return Rect::new(*x as f64, *y as f64, width, height);
+    }
+    if maintain_aspect_ratio {
+        let (x, y) = ((local_width - width) / 2.0, (local_height - height) / 2.0);
+        return match position {
+            ScreenPosition::Left => Rect::new(-width, y, width, height),
+            ScreenPosition::Right => Rect::new(local_width, y, width, height),
+            ScreenPosition::Top => Rect::new(x, -height, width, height),
+            _ => Rect::new(x, local_height, width, height),
+        };
+    }
+    match position {
+        ScreenPosition::Left => {
+            let w = width * local_height / height;
+            Rect::new(-w, 0.0, w, local_height)
+        }
+        ScreenPosition::Right => Rect::new(local_width, 0.0, width * local_height / height, local_height),
+        ScreenPosition::Top => {
+            let h = height * local_width / width;
+            Rect::new(0.0, -h, local_width, h)
+        }
+        _ => Rect::new(0.0, local_height, local_width, height * local_width / width),
+    }
+}
+
+/// Normalized motion on a screen placed at `rect` for `(dx, dy)` desktop units of local motion.
+/// A screen at its own size moves the cursor as far as it moved here; a scaled one moves it
+/// proportionally, keeping its aspect ratio either way.
+pub fn motion(rect: &Rect, dx: f64, dy: f64) -> (f64, f64) {
+    (dx / rect.width, dy / rect.height)
+}
@@ -0,0 +1,65 @@
+use crate::frame::{DeviceAuth, Frame, RejectReason};
+
+/// Newest protocol version spoken by this build.
+///
+/// Bump this whenever the wire layout of `Frame` changes.
+/// - 1: initial release, no negotiation.
+/// - 2: handshake advertises a `min_version..=version` range.
+/// - 3: handshake capabilities are a `CapabilitySet` bitmask.
+/// - 4: frames travel inside the tagged, length-delimited envelope from `wire`.
+/// - 5: input payloads may carry a trailing sequence number and capture timestamp.
+/// - 6: heartbeats carry microsecond timestamps and a reply flag; both sides send them.
+/// - 7: `MouseButton::Back`, `Forward` and `Other(n)`.
+/// - 8: `InputEvent::SmoothScroll` with units and gesture phases.
+/// - 9: `InputEvent::Keyboard` carries HID usages; older peers get `KeyboardVk`.
+/// - 10: `InputEvent::Text` for layout-independent typing.
+/// - 11: `Frame::ModifierState` snapshots of held modifiers and lock keys.
+/// - 12: the handshake's screen info may be followed by a list of displays.
+/// - 13: `Frame::DeviceAuth`; peers prove their device keys and pair before anything else.
+/// - 14: `DeviceAuth::SecretProof` and `RejectReason::SecretMismatch` for pre-shared keys.
+/// - 15: the handshake id is the device ID, and the device's name trails the handshake.
+pub const PROTOCOL_VERSION: u32 = 15;
+
+/// Oldest protocol version this build can still talk to.
+///
+/// Raised only when older peers can no longer be served at all, never just because
+/// `PROTOCOL_VERSION` moved: newer features are adapted or dropped per peer in
+/// `Frame::for_version` instead.
+/// - 4: frames travel inside the `wire` envelope, which earlier peers cannot read. Their bare
+///   handshakes still decode, so they are turned away with `RejectReason::UnsupportedVersion`.
+/// - 13: device authentication is required; earlier peers cannot prove who they are.
+pub const MIN_PROTOCOL_VERSION: u32 = 13;
+
+/// First version that can prove a pre-shared key.
+pub const PRE_SHARED_KEY_VERSION: u32 = 14;
+
+/// First version whose handshake id is the device ID. Older peers send a placeholder, so their
+/// ID is taken from the key they prove instead.
+pub const DEVICE_ID_VERSION: u32 = 15;
+
+/// Returns the highest version supported by both ranges.
+///
+/// The rejection reports our own range so the peer can tell the user which side needs upgrading.
+pub fn negotiate_version(local_min: u32, local_max: u32, peer_min: u32, peer_max: u32) -> Result<u32, RejectReason> {
+    let version = local_max.min(peer_max);
+    if version >= local_min.max(peer_min) {
+        Ok(version)
+    } else {
+        Err(RejectReason::UnsupportedVersion {
+            min_supported: local_min,
+            max_supported: local_max,
+        })
+    }
+}
+
+impl Frame {
+    /// Adapts `self` for a peer that negotiated `version`, or drops it if that peer cannot
+    /// decode it. Every outbound frame goes through here.
+    pub fn for_version(self, version: u32) -> Option<Frame> {
+        match &self {
+            Frame::DeviceAuth(DeviceAuth::SecretProof(_)) if version < 14 => None,
+            Frame::HandshakeReject(RejectReason::SecretMismatch) if version < 14 => Some(Frame::HandshakeReject(RejectReason::NotPaired)),
+            _ => Some(self),
+        }
+    }
+}
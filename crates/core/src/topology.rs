@@ -0,0 +1,238 @@
+//! Every machine's screen as a rectangle on one shared virtual desktop, and where the cursor
+//! goes when it leaves one. Pure geometry, no OS calls: sources feed it normalized positions.
+use crate::config::{RemoteScreen, ScreenInfo, Topology};
+use crate::mapping;
+
+/// How close to a monitor's edge the cursor has to be to count as on it, as a fraction of its
+/// machine's screen size (at least one pixel).
+pub const EDGE_TOLERANCE: f64 = 0.002;
+
+/// How far inside the new monitor the cursor lands, as a fraction of its size, so it does not
+/// cross straight back.
+pub const ENTRY_INSET: f64 = 0.05;
+
+/// An axis-aligned rectangle in virtual desktop pixels. `y` grows downwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+
+    /// Half-open: the right and bottom edges belong to the neighbor.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// The desktop point at normalized `(nx, ny)` inside this rectangle.
+    pub fn point_at(&self, nx: f64, ny: f64) -> (f64, f64) {
+        (self.x + nx * self.width, self.y + ny * self.height)
+    }
+
+    /// How far desktop point `(x, y)` is from the rectangle, 0 inside it.
+    pub fn distance_to(&self, x: f64, y: f64) -> f64 {
+        let dx = (self.x - x).max(x - self.right()).max(0.0);
+        let dy = (self.y - y).max(y - self.bottom()).max(0.0);
+        dx.hypot(dy)
+    }
+
+    /// The normalized position of desktop point `(x, y)`, clamped to the rectangle.
+    pub fn normalize(&self, x: f64, y: f64) -> (f64, f64) {
+        (((x - self.x) / self.width).clamp(0.0, 1.0), ((y - self.y) / self.height).clamp(0.0, 1.0))
+    }
+}
+
+/// A side of a screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Edge {
+    pub const ALL: [Edge; 4] = [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom];
+}
+
+/// The cursor leaving one screen for another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crossing<'a> {
+    /// The screen it enters, `None` for this machine.
+    pub to: Option<&'a RemoteScreen>,
+    /// The edge of the screen it left.
+    pub edge: Edge,
+    /// Normalized entry point on the new screen, `ENTRY_INSET` into the monitor it lands on.
+    pub x: f64,
+    pub y: f64,
+}
+
+/// This machine's screen at the origin, with every remote placed around it.
+///
+/// `Left`/`Right`/`Top`/`Bottom` remotes are placed by `mapping::place`: at their own size and
+/// centered on the shared edge when keeping the aspect ratio, otherwise scaled to span it.
+/// `Absolute` remotes sit at their own size with their top-left corner at the given desktop pixel.
+///
+/// Each machine's monitors are placed inside its screen, and crossings are worked out per
+/// monitor: seams between a machine's own monitors never switch, and neither do edges that face
+/// a gap in the arrangement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualDesktop {
+    /// This machine first, then the remotes in topology order.
+    machines: Vec<Machine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Machine {
+    /// `None` for this machine.
+    remote: Option<RemoteScreen>,
+    bounds: Rect,
+    displays: Vec<Rect>,
+}
+
+impl Machine {
+    /// `info`'s screen stretched over `bounds`, monitors and all.
+    fn new(remote: Option<RemoteScreen>, info: &ScreenInfo, bounds: Rect) -> Self {
+        let (width, height, layout) = layout_of(info);
+        let (sx, sy) = (bounds.width / width, bounds.height / height);
+        let displays = layout
+            .iter()
+            .map(|d| Rect::new(bounds.x + d.x * sx, bounds.y + d.y * sy, d.width * sx, d.height * sy))
+            .collect();
+        Self { remote, bounds, displays }
+    }
+
+    /// The monitor containing desktop point `(x, y)`, or the nearest one.
+    fn display_near(&self, x: f64, y: f64) -> Rect {
+        self.displays
+            .iter()
+            .copied()
+            .min_by(|a, b| a.distance_to(x, y).total_cmp(&b.distance_to(x, y)))
+            .unwrap_or(self.bounds)
+    }
+}
+
+impl VirtualDesktop {
+    pub fn new(topology: &Topology, maintain_aspect_ratio: bool) -> Self {
+        let (width, height, _) = layout_of(&topology.local);
+        let local = Machine::new(None, &topology.local, Rect::new(0.0, 0.0, width, height));
+        let remotes = topology.remotes.iter().map(|r| {
+            let (w, h, _) = layout_of(&r.info);
+            let bounds = mapping::place((width, height), (w, h), &r.position, maintain_aspect_ratio);
+            Machine::new(Some(r.clone()), &r.info, bounds)
+        });
+        Self { machines: std::iter::once(local).chain(remotes).collect() }
+    }
+
+    pub fn local(&self) -> Rect {
+        self.machines[0].bounds
+    }
+
+    pub fn remotes(&self) -> impl Iterator<Item = &RemoteScreen> {
+        self.machines.iter().filter_map(|m| m.remote.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.len() == 1
+    }
+
+    /// Where the remote with `RemoteScreen::id` `id` sits, if there is one.
+    pub fn rect_of(&self, id: &str) -> Option<Rect> {
+        self.machine(Some(id)).map(|m| m.bounds)
+    }
+
+    /// Normalized motion on remote `on` for `(dx, dy)` desktop pixels of motion here.
+    pub fn motion(&self, on: &str, dx: f64, dy: f64) -> Option<(f64, f64)> {
+        self.rect_of(on).map(|rect| mapping::motion(&rect, dx, dy))
+    }
+
+    /// Where each monitor of remote `from` (`None` for this machine) sits.
+    pub fn displays_of(&self, from: Option<&str>) -> Option<&[Rect]> {
+        self.machine(from).map(|m| m.displays.as_slice())
+    }
+
+    /// Where a cursor at normalized `(x, y)` on remote `from` (`None` for this machine) goes if it
+    /// pushes past the edge of the monitor it is on. `None` if it is not against an edge, or
+    /// nothing lies beyond it. In a corner, the left and right edges are tried before the top
+    /// and bottom.
+    pub fn exit(&self, from: Option<&str>, x: f64, y: f64) -> Option<Crossing<'_>> {
+        let origin = self.machine(from)?;
+        let (px, py) = origin.bounds.point_at(x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+        let display = origin.display_near(px, py);
+        let reach_x = (EDGE_TOLERANCE * origin.bounds.width).max(1.0);
+        let reach_y = (EDGE_TOLERANCE * origin.bounds.height).max(1.0);
+
+        Edge::ALL.into_iter().find_map(|edge| {
+            // Half a pixel past the edge, so the half-open rectangles never both claim it
+            let (bx, by) = match edge {
+                Edge::Left if px - display.x <= reach_x => (display.x - 0.5, py),
+                Edge::Right if display.right() - px <= reach_x => (display.right() + 0.5, py),
+                Edge::Top if py - display.y <= reach_y => (px, display.y - 0.5),
+                Edge::Bottom if display.bottom() - py <= reach_y => (px, display.bottom() + 0.5),
+                _ => return None,
+            };
+            if origin.displays.iter().any(|d| d.contains(bx, by)) {
+                return None;
+            }
+            let (to, entered) = self.display_at(bx, by, origin)?;
+            let (ex, ey) = match edge {
+                Edge::Left => (bx.min(entered.right() - ENTRY_INSET * entered.width), by),
+                Edge::Right => (bx.max(entered.x + ENTRY_INSET * entered.width), by),
+                Edge::Top => (bx, by.min(entered.bottom() - ENTRY_INSET * entered.height)),
+                Edge::Bottom => (bx, by.max(entered.y + ENTRY_INSET * entered.height)),
+            };
+            let (x, y) = to.bounds.normalize(ex, ey);
+            Some(Crossing { to: to.remote.as_ref(), edge, x, y })
+        })
+    }
+
+    /// This machine, or the remote with `RemoteScreen::id` `id`. Positions only place screens;
+    /// several remotes can share one.
+    fn machine(&self, id: Option<&str>) -> Option<&Machine> {
+        match id {
+            None => self.machines.first(),
+            Some(id) => self.machines[1..].iter().find(|m| m.remote.as_ref().is_some_and(|r| r.id == id)),
+        }
+    }
+
+    /// The first monitor of a machine other than `skip` that contains desktop point `(x, y)`.
+    fn display_at(&self, x: f64, y: f64, skip: &Machine) -> Option<(&Machine, Rect)> {
+        self.machines
+            .iter()
+            .filter(|m| !std::ptr::eq(*m, skip))
+            .find_map(|m| m.displays.iter().find(|d| d.contains(x, y)).map(|d| (m, *d)))
+    }
+}
+
+/// `info`'s size and its monitors relative to its top-left corner. Without a monitor list the
+/// whole screen is one monitor.
+fn layout_of(info: &ScreenInfo) -> (f64, f64, Vec<Rect>) {
+    if info.displays.is_empty() {
+        let (width, height) = (info.width.max(1) as f64, info.height.max(1) as f64);
+        return (width, height, vec![Rect::new(0.0, 0.0, width, height)]);
+    }
+    let left = info.displays.iter().map(|d| d.x).min().unwrap_or(0);
+    let top = info.displays.iter().map(|d| d.y).min().unwrap_or(0);
+    let displays: Vec<Rect> = info
+        .displays
+        .iter()
+        .map(|d| Rect::new((d.x - left) as f64, (d.y - top) as f64, d.width.max(1) as f64, d.height.max(1) as f64))
+        .collect();
+    let width = displays.iter().map(Rect::right).fold(1.0, f64::max);
+    let height = displays.iter().map(Rect::bottom).fold(1.0, f64::max);
+    (width, height, displays)
+}
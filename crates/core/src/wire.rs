@@ -0,0 +1,288 @@
+//! Versioned envelope around each `Frame`.
+//!
+//! Layout (little endian):
+//!
+//! ```text
+//! +--------+----------+-------------+-----------------+
+//! | format | tag: u16 | length: u32 | payload (bytes) |
+//! +--------+----------+-------------+-----------------+
+//! ```
+//!
+//! The payload is the bincode encoding of the variant's contents. Because every payload is
+//! delimited by its own length, a decoder can skip frame types it does not know and ignore
+//! fields appended to the end of a payload by a newer peer.
+//!
+//! Every length is checked against `FrameLimits` before anything is allocated or decoded.
+//!
+//! Peers before protocol 4 sent bare bincode frames with no envelope. Their handshake is still
+//! recognized (see `decode_legacy_handshake`) so that the version mismatch surfaces as a typed
+//! `RejectReason::UnsupportedVersion` instead of a malformed frame.
+use crate::codec::FrameCodec;
+use crate::config::FrameLimits;
+use crate::{CapabilitySet, Frame, Handshake, Heartbeat, InputFrame};
+use std::fmt;
+
+/// Envelope layout version. Changes only if the header itself changes.
+pub const WIRE_FORMAT: u8 = 1;
+
+/// Size of the fixed envelope header in bytes.
+pub const HEADER_LEN: usize = 7;
+
+/// Frame type tags. These are part of the wire format: never reuse or renumber a tag.
+pub mod tag {
+    pub const HANDSHAKE: u16 = 1;
+    pub const HEARTBEAT: u16 = 2;
+    pub const INPUT: u16 = 3;
+    pub const CLIPBOARD: u16 = 4;
+    pub const FILE_TRANSFER_REQUEST: u16 = 5;
+    pub const FILE_TRANSFER_RESPONSE: u16 = 6;
+    pub const FILE_DATA: u16 = 7;
+    pub const FILE_END: u16 = 8;
+    pub const SCREEN_SWITCH: u16 = 9;
+    pub const NOTIFICATION: u16 = 10;
+    pub const HANDSHAKE_REJECT: u16 = 11;
+    pub const MODIFIER_STATE: u16 = 12;
+    pub const DEVICE_AUTH: u16 = 13;
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    /// The declared payload length exceeds the limit for its frame family.
+    TooLarge { tag: u16, len: u32, max: u32 },
+    /// Fewer bytes were available than the envelope declares.
+    Truncated { expected: usize, actual: usize },
+    /// A frame type this build does not know. Safe to skip.
+    UnknownType(u16),
+    /// The envelope or payload is malformed.
+    Decode(String),
+    Io(std::io::Error),
+}
+
+impl FrameError {
+    /// Whether the connection can continue after this error.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, FrameError::UnknownType(_))
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TooLarge { tag, len, max } => {
+                write!(f, "frame type {} is {} bytes, limit is {}", tag, len, max)
+            }
+            FrameError::Truncated { expected, actual } => {
+                write!(f, "truncated frame: expected {} bytes, got {}", expected, actual)
+            }
+            FrameError::UnknownType(tag) => write!(f, "unknown frame type {}", tag),
+            FrameError::Decode(msg) => write!(f, "malformed frame: {}", msg),
+            FrameError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<bincode::Error> for FrameError {
+    fn from(e: bincode::Error) -> Self {
+        FrameError::Decode(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for FrameError {
+    fn from(e: std::io::Error) -> Self {
+        FrameError::Io(e)
+    }
+}
+
+impl FrameLimits {
+    /// Limit for a frame type. Unknown types get the largest limit so they can still be skipped.
+    pub fn max_for(&self, frame_tag: u16) -> u32 {
+        match frame_tag {
+            tag::INPUT | tag::SCREEN_SWITCH | tag::MODIFIER_STATE => self.input,
+            tag::CLIPBOARD => self.clipboard,
+            tag::FILE_DATA => self.file_data,
+            tag::HANDSHAKE
+            | tag::HEARTBEAT
+            | tag::FILE_TRANSFER_REQUEST
+            | tag::FILE_TRANSFER_RESPONSE
+            | tag::FILE_END
+            | tag::NOTIFICATION
+            | tag::HANDSHAKE_REJECT
+            | tag::DEVICE_AUTH => self.control,
+            _ => self.largest(),
+        }
+    }
+
+    /// Largest complete envelope that can be accepted, for sizing transport buffers.
+    pub fn max_envelope_len(&self) -> usize {
+        HEADER_LEN + self.largest() as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnvelopeHeader {
+    pub tag: u16,
+    pub len: u32,
+}
+
+impl EnvelopeHeader {
+    pub fn parse(bytes: &[u8; HEADER_LEN]) -> Result<Self, FrameError> {
+        if bytes[0] != WIRE_FORMAT {
+            return Err(FrameError::Decode(format!("unsupported wire format {}", bytes[0])));
+        }
+        Ok(Self {
+            tag: u16::from_le_bytes([bytes[1], bytes[2]]),
+            len: u32::from_le_bytes([bytes[3], bytes[4], bytes[5], bytes[6]]),
+        })
+    }
+
+    /// Rejects headers whose payload would exceed `limits`.
+    pub fn check(&self, limits: &FrameLimits) -> Result<(), FrameError> {
+        let max = limits.max_for(self.tag);
+        if self.len > max {
+            return Err(FrameError::TooLarge { tag: self.tag, len: self.len, max });
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(self) -> [u8; HEADER_LEN] {
+        let tag = self.tag.to_le_bytes();
+        let len = self.len.to_le_bytes();
+        [WIRE_FORMAT, tag[0], tag[1], len[0], len[1], len[2], len[3]]
+    }
+}
+
+impl Frame {
+    pub fn tag(&self) -> u16 {
+        match self {
+            Frame::Handshake(_) => tag::HANDSHAKE,
+            Frame::Heartbeat(_) => tag::HEARTBEAT,
+            Frame::Input(_) => tag::INPUT,
+            Frame::Clipboard(_) => tag::CLIPBOARD,
+            Frame::FileTransferRequest(_) => tag::FILE_TRANSFER_REQUEST,
+            Frame::FileTransferResponse(_) => tag::FILE_TRANSFER_RESPONSE,
+            Frame::FileData { .. } => tag::FILE_DATA,
+            Frame::FileEnd { .. } => tag::FILE_END,
+            Frame::ScreenSwitch(_) => tag::SCREEN_SWITCH,
+            Frame::Notification { .. } => tag::NOTIFICATION,
+            Frame::HandshakeReject(_) => tag::HANDSHAKE_REJECT,
+            Frame::ModifierState(_) => tag::MODIFIER_STATE,
+            Frame::DeviceAuth(_) => tag::DEVICE_AUTH,
+        }
+    }
+}
+
+pub(crate) fn encode_payload(frame: &Frame) -> Result<Vec<u8>, FrameError> {
+    let payload = match frame {
+        Frame::Handshake(h) => {
+            // Displays and then the device name trail the handshake so that older peers, which
+            // stop after `screen_info`, ignore them. The displays are written, if empty, when a
+            // name follows them.
+            let mut payload = bincode::serialize(h)?;
+            if let Some(info) = h.screen_info.as_ref().filter(|info| !info.displays.is_empty() || !h.device_name.is_empty()) {
+                payload.extend_from_slice(&bincode::serialize(&info.displays)?);
+            }
+            if !h.device_name.is_empty() {
+                payload.extend_from_slice(&bincode::serialize(&h.device_name)?);
+            }
+            payload
+        }
+        Frame::Heartbeat(hb) => bincode::serialize(hb)?,
+        Frame::Input(input) => {
+            // The stamp trails the event so that older peers, which decode only the event, ignore it.
+            let mut payload = bincode::serialize(&input.event)?;
+            if let Some(stamp) = &input.stamp {
+                payload.extend_from_slice(&bincode::serialize(stamp)?);
+            }
+            payload
+        }
+        Frame::Clipboard(ev) => bincode::serialize(ev)?,
+        Frame::FileTransferRequest(req) => bincode::serialize(req)?,
+        Frame::FileTransferResponse(resp) => bincode::serialize(resp)?,
+        Frame::FileData { id, chunk } => bincode::serialize(&(id, chunk))?,
+        Frame::FileEnd { id } => bincode::serialize(id)?,
+        Frame::ScreenSwitch(side) => bincode::serialize(side)?,
+        Frame::Notification { title, message } => bincode::serialize(&(title, message))?,
+        Frame::HandshakeReject(reason) => bincode::serialize(reason)?,
+        Frame::ModifierState(state) => bincode::serialize(state)?,
+        Frame::DeviceAuth(auth) => bincode::serialize(auth)?,
+    };
+    Ok(payload)
+}
+
+/// Encodes `frame` into a single envelope. Shorthand for `FrameCodec::encode_message`.
+pub fn encode_frame(frame: &Frame) -> Result<Vec<u8>, FrameError> {
+    FrameCodec::default().encode_message(frame)
+}
+
+/// Decodes a payload for `tag`.
+///
+/// Bytes left over after the known fields are ignored, so peers may append fields to a payload.
+pub fn decode_payload(tag: u16, payload: &[u8]) -> Result<Frame, FrameError> {
+    let frame = match tag {
+        tag::HANDSHAKE => {
+            let mut rest = payload;
+            let mut handshake: Handshake = bincode::deserialize_from(&mut rest)?;
+            if let Some(info) = handshake.screen_info.as_mut() {
+                if !rest.is_empty() {
+                    info.displays = bincode::deserialize_from(&mut rest)?;
+                }
+            }
+            if !rest.is_empty() {
+                handshake.device_name = bincode::deserialize_from(&mut rest)?;
+            }
+            Frame::Handshake(handshake)
+        }
+        tag::HEARTBEAT => {
+            let mut rest = payload;
+            let timestamp = bincode::deserialize_from(&mut rest)?;
+            let reply = if rest.is_empty() { false } else { bincode::deserialize_from(&mut rest)? };
+            Frame::Heartbeat(Heartbeat { timestamp, reply })
+        }
+        tag::INPUT => {
+            let mut rest = payload;
+            let event = bincode::deserialize_from(&mut rest)?;
+            let stamp = if rest.is_empty() { None } else { Some(bincode::deserialize_from(&mut rest)?) };
+            Frame::Input(InputFrame { event, stamp })
+        }
+        tag::CLIPBOARD => Frame::Clipboard(bincode::deserialize(payload)?),
+        tag::FILE_TRANSFER_REQUEST => Frame::FileTransferRequest(bincode::deserialize(payload)?),
+        tag::FILE_TRANSFER_RESPONSE => Frame::FileTransferResponse(bincode::deserialize(payload)?),
+        tag::FILE_DATA => {
+            let (id, chunk) = bincode::deserialize(payload)?;
+            Frame::FileData { id, chunk }
+        }
+        tag::FILE_END => Frame::FileEnd { id: bincode::deserialize(payload)? },
+        tag::SCREEN_SWITCH => Frame::ScreenSwitch(bincode::deserialize(payload)?),
+        tag::NOTIFICATION => {
+            let (title, message) = bincode::deserialize(payload)?;
+            Frame::Notification { title, message }
+        }
+        tag::HANDSHAKE_REJECT => Frame::HandshakeReject(bincode::deserialize(payload)?),
+        tag::MODIFIER_STATE => Frame::ModifierState(bincode::deserialize(payload)?),
+        tag::DEVICE_AUTH => Frame::DeviceAuth(bincode::deserialize(payload)?),
+        other => return Err(FrameError::UnknownType(other)),
+    };
+    Ok(frame)
+}
+
+/// How a bare bincode `Frame::Handshake` from a peer before protocol 4 begins: the variant
+/// index, which no envelope can start with since `WIRE_FORMAT` is not 0.
+pub const LEGACY_HANDSHAKE_PREFIX: [u8; 4] = [0, 0, 0, 0];
+
+/// Decodes the handshake of a peer before protocol 4, enough to negotiate with it: its version,
+/// then its minimum version from protocol 2 on, then its id. The fields after the id changed
+/// layout between those versions and are not needed to turn the peer away, so they are skipped.
+pub(crate) fn decode_legacy_handshake(msg: &[u8]) -> Result<Handshake, FrameError> {
+    let mut rest = msg.strip_prefix(&LEGACY_HANDSHAKE_PREFIX).ok_or_else(|| FrameError::Decode("not a legacy handshake".to_string()))?;
+    let version: u32 = bincode::deserialize_from(&mut rest)?;
+    let min_version = if version >= 2 { bincode::deserialize_from(&mut rest)? } else { version };
+    let client_id = bincode::deserialize_from(&mut rest)?;
+    Ok(Handshake { version, min_version, client_id, capabilities: CapabilitySet::empty(), screen_info: None, device_name: String::new() })
+}
+
+/// Decodes exactly one envelope, enforcing `limits`. Shorthand for `FrameCodec::decode_message`.
+pub fn decode_frame(bytes: &[u8], limits: &FrameLimits) -> Result<Frame, FrameError> {
+    FrameCodec::new(*limits).decode_message(bytes)
+}
@@ -0,0 +1,133 @@
+//! `FrameCodec` behaviour over both the stream and the message transport.
+use bytes::BytesMut;
+use futures_util::{SinkExt, StreamExt};
+use platform_passer_core::wire::{tag, EnvelopeHeader, HEADER_LEN};
+use platform_passer_core::{
+    encode_frame, ClipboardEvent, Frame, FrameCodec, FrameError, FrameLimits, Heartbeat, InputEvent,
+    InputFrame, MouseButton,
+};
+use tokio_util::codec::{Decoder, Encoder, FramedRead, FramedWrite};
+
+fn sample_frames() -> Vec<Frame> {
+    vec![
+        Frame::Heartbeat(Heartbeat { timestamp: 42, reply: false }),
+        Frame::Input(InputFrame::stamped(InputEvent::MouseButton { button: MouseButton::Right, is_down: false }, 1)),
+        Frame::Clipboard(ClipboardEvent::Text("hello".into())),
+        Frame::FileData { id: 3, chunk: vec![0xab; 300] },
+        Frame::FileEnd { id: 3 },
+    ]
+}
+
+fn encode_all(frames: &[Frame]) -> Vec<u8> {
+    let mut codec = FrameCodec::default();
+    let mut buf = BytesMut::new();
+    for frame in frames {
+        codec.encode(frame, &mut buf).unwrap();
+    }
+    buf.to_vec()
+}
+
+#[test]
+fn decodes_byte_by_byte() {
+    let frames = sample_frames();
+    let bytes = encode_all(&frames);
+
+    let mut codec = FrameCodec::default();
+    let mut buf = BytesMut::new();
+    let mut decoded = Vec::new();
+    for b in bytes {
+        buf.extend_from_slice(&[b]);
+        while let Some(frame) = codec.decode(&mut buf).unwrap() {
+            decoded.push(frame);
+        }
+    }
+    assert!(buf.is_empty());
+    assert_eq!(decoded.len(), frames.len());
+    for (got, want) in decoded.iter().zip(&frames) {
+        assert_eq!(encode_frame(got).unwrap(), encode_frame(want).unwrap());
+    }
+}
+
+#[test]
+fn decodes_several_frames_from_one_buffer() {
+    let frames = sample_frames();
+    let mut buf = BytesMut::from(&encode_all(&frames)[..]);
+    let mut codec = FrameCodec::default();
+    for want in &frames {
+        let got = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(encode_frame(&got).unwrap(), encode_frame(want).unwrap());
+    }
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+}
+
+#[test]
+fn stream_encoding_matches_message_encoding() {
+    let codec = FrameCodec::default();
+    for frame in sample_frames() {
+        assert_eq!(encode_all(std::slice::from_ref(&frame)), codec.encode_message(&frame).unwrap());
+    }
+}
+
+#[test]
+fn stream_decoder_skips_unknown_frames() {
+    let mut bytes = EnvelopeHeader { tag: 0x7fff, len: 2 }.to_bytes().to_vec();
+    bytes.extend_from_slice(&[1, 2]);
+    bytes.extend_from_slice(&encode_frame(&Frame::FileEnd { id: 5 }).unwrap());
+
+    let mut buf = BytesMut::from(&bytes[..]);
+    match FrameCodec::default().decode(&mut buf).unwrap() {
+        Some(Frame::FileEnd { id }) => assert_eq!(id, 5),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn stream_decoder_rejects_oversized_header_without_full_payload() {
+    let limits = FrameLimits { input: 16, ..FrameLimits::default() };
+    let mut buf = BytesMut::from(&EnvelopeHeader { tag: tag::INPUT, len: 17 }.to_bytes()[..]);
+    let err = FrameCodec::new(limits).decode(&mut buf).unwrap_err();
+    assert!(matches!(err, FrameError::TooLarge { tag: tag::INPUT, len: 17, max: 16 }));
+}
+
+#[test]
+fn message_must_hold_exactly_one_envelope() {
+    let codec = FrameCodec::default();
+    let one = codec.encode_message(&Frame::FileEnd { id: 1 }).unwrap();
+
+    assert!(matches!(codec.decode_message(&one), Ok(Frame::FileEnd { id: 1 })));
+    assert!(matches!(
+        codec.decode_message(&one[..HEADER_LEN - 2]),
+        Err(FrameError::Truncated { expected: HEADER_LEN, actual: 5 })
+    ));
+    assert!(matches!(
+        codec.decode_message(&one[..one.len() - 1]),
+        Err(FrameError::Truncated { expected: 4, actual: 3 })
+    ));
+
+    let mut two = one.clone();
+    two.extend_from_slice(&one);
+    assert!(matches!(codec.decode_message(&two), Err(FrameError::Decode(_))));
+}
+
+#[tokio::test]
+async fn framed_round_trip_over_a_duplex_stream() {
+    let (client, server) = tokio::io::duplex(64);
+    let frames = sample_frames();
+
+    let expected: Vec<Vec<u8>> = frames.iter().map(|f| encode_frame(f).unwrap()).collect();
+    let writer = tokio::spawn(async move {
+        let mut sink = FramedWrite::new(client, FrameCodec::default());
+        for frame in frames {
+            sink.send(frame).await.unwrap();
+        }
+    });
+
+    let received: Vec<Frame> = FramedRead::new(server, FrameCodec::default())
+        .map(|r| r.unwrap())
+        .collect()
+        .await;
+    writer.await.unwrap();
+
+    let received: Vec<Vec<u8>> = received.iter().map(|f| encode_frame(f).unwrap()).collect();
+    assert_eq!(received, expected);
+}
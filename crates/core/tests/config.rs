@@ -0,0 +1,21 @@
+//! Config values that stand for something the machine reports.
+use platform_passer_core::config::{host_name, AppConfig};
+
+#[test]
+fn an_unnamed_device_goes_by_its_host_name() {
+    assert_eq!(AppConfig::default().device_name, "");
+    let mut value = serde_json::to_value(AppConfig::default()).unwrap();
+    value.as_object_mut().unwrap().remove("device_name");
+    let config: AppConfig = serde_json::from_value(value).unwrap();
+    assert!(config.device_name.is_empty());
+    assert_eq!(config.name_or_host(), host_name());
+    assert!(!host_name().is_empty());
+}
+
+#[test]
+fn a_chosen_name_is_kept() {
+    let config = AppConfig { device_name: " office laptop ".into(), ..AppConfig::default() };
+    assert_eq!(config.name_or_host(), "office laptop");
+    let blank = AppConfig { device_name: "  ".into(), ..AppConfig::default() };
+    assert_eq!(blank.name_or_host(), host_name());
+}
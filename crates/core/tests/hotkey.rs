@@ -0,0 +1,52 @@
+//! Hotkey chords as written in the config.
+use platform_passer_core::config::{AppConfig, HotkeyConfig};
+use platform_passer_core::hotkey::modifiers;
+use platform_passer_core::Chord;
+
+#[test]
+fn parses_modifiers_and_key_names_in_any_case() {
+    assert_eq!("Ctrl+Alt+Escape".parse(), Ok(Chord::new(modifiers::CTRL | modifiers::ALT, 0x29)));
+    assert_eq!("cmd + shift + pageup".parse(), Ok(Chord::new(modifiers::META | modifiers::SHIFT, 0x4B)));
+    assert_eq!("Win+L".parse(), Ok(Chord::new(modifiers::META, 0x0F)));
+    assert_eq!("F13".parse(), Ok(Chord::new(0, 0x68)));
+    assert_eq!("Control+0x46".parse(), Ok(Chord::new(modifiers::CTRL, 0x46)));
+}
+
+#[test]
+fn rejects_unknown_names_and_bare_modifiers() {
+    assert!("Ctrl+Hyper+A".parse::<Chord>().is_err());
+    assert!("Ctrl+Nope".parse::<Chord>().is_err());
+    assert!("Ctrl+".parse::<Chord>().is_err());
+    assert!("F25".parse::<Chord>().is_err());
+    // A modifier usage cannot be the chord key
+    assert!("Ctrl+0xE1".parse::<Chord>().is_err());
+}
+
+#[test]
+fn written_back_in_canonical_form() {
+    let chord: Chord = "shift+ctrl+esc".parse().unwrap();
+    assert_eq!(chord.to_string(), "Ctrl+Shift+Escape");
+    assert_eq!(chord.to_string().parse(), Ok(chord));
+}
+
+#[test]
+fn config_round_trips_through_json() {
+    let json = r#"{"return_to_local":"Ctrl+Alt+Escape","lock_to_screen":"ScrollLock","switch_to_peer":[{"peer":"laptop","chord":"Ctrl+Alt+1"}]}"#;
+    let config: HotkeyConfig = serde_json::from_str(json).unwrap();
+    assert_eq!(config.lock_to_screen, Some(Chord::new(0, 0x47)));
+    assert_eq!(config.cycle_peers, None);
+    assert_eq!(config.switch_to_peer[0].chord, Chord::new(modifiers::CTRL | modifiers::ALT, 0x1E));
+
+    let again: HotkeyConfig = serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+    assert_eq!(again, config);
+    assert!(serde_json::from_str::<HotkeyConfig>(r#"{"cycle_peers":"Ctrl+Bogus"}"#).is_err());
+}
+
+#[test]
+fn older_configs_get_the_default_escape_hatch() {
+    let mut value = serde_json::to_value(AppConfig::default()).unwrap();
+    value.as_object_mut().unwrap().remove("hotkeys");
+    let config: AppConfig = serde_json::from_value(value).unwrap();
+    assert_eq!(config.hotkeys, HotkeyConfig::default());
+    assert!(config.hotkeys.return_to_local.is_some());
+}
@@ -0,0 +1,119 @@
+//! Lining up screens of different resolution and carrying the cursor across them.
+use platform_passer_core::config::{RemoteScreen, ScreenInfo, ScreenPosition, Topology};
+use platform_passer_core::mapping::{motion, place};
+use platform_passer_core::topology::{Rect, ENTRY_INSET};
+use platform_passer_core::{CapabilitySet, Frame, FrameCodec, FrameLimits, Handshake, VirtualDesktop};
+use std::collections::HashMap;
+
+fn screen(width: u32, height: u32, dpi_scale: f32) -> ScreenInfo {
+    ScreenInfo { width, height, dpi_scale, displays: Vec::new() }
+}
+
+/// A 1920x1080 screen with `info` at `position`.
+fn desktop(position: ScreenPosition, info: ScreenInfo, maintain_aspect_ratio: bool) -> VirtualDesktop {
+    let remote = RemoteScreen { id: "peer".to_string(), position, info };
+    VirtualDesktop::new(&Topology { local: screen(1920, 1080, 1.0), remotes: vec![remote] }, maintain_aspect_ratio)
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+#[test]
+fn taller_remote_is_centered_at_its_own_size() {
+    let rect = place((1920.0, 1080.0), (2560.0, 1440.0), &ScreenPosition::Right, true);
+    assert_eq!(rect, Rect::new(1920.0, -180.0, 2560.0, 1440.0));
+    let rect = place((1920.0, 1080.0), (1280.0, 800.0), &ScreenPosition::Bottom, true);
+    assert_eq!(rect, Rect::new(320.0, 1080.0, 1280.0, 800.0));
+}
+
+#[test]
+fn scaled_remote_spans_the_shared_edge() {
+    let rect = place((1920.0, 1080.0), (2560.0, 1440.0), &ScreenPosition::Right, false);
+    assert_eq!(rect, Rect::new(1920.0, 0.0, 1920.0, 1080.0));
+}
+
+#[test]
+fn absolute_remote_ignores_the_aspect_setting() {
+    let position = ScreenPosition::Absolute { x: -1280, y: 100 };
+    let expected = Rect::new(-1280.0, 100.0, 1280.0, 720.0);
+    assert_eq!(place((1920.0, 1080.0), (1280.0, 720.0), &position, true), expected);
+    assert_eq!(place((1920.0, 1080.0), (1280.0, 720.0), &position, false), expected);
+}
+
+#[test]
+fn crossing_into_a_1440p_screen_keeps_the_height() {
+    let d = desktop(ScreenPosition::Right, screen(2560, 1440, 1.0), true);
+    // 270 px down the 1080p screen is 450 px down the 1440p one, hung 180 px higher
+    let crossing = d.exit(None, 1.0, 0.25).unwrap();
+    assert!(close(crossing.y, 450.0 / 1440.0));
+    assert!(close(crossing.x, ENTRY_INSET));
+
+    // And straight back across at the same height
+    let back = d.exit(Some("peer"), 0.0, 450.0 / 1440.0).unwrap();
+    assert!(back.to.is_none());
+    assert!(close(back.y, 0.25));
+}
+
+#[test]
+fn overhang_of_a_taller_screen_leads_nowhere() {
+    let d = desktop(ScreenPosition::Right, screen(2560, 1440, 1.0), true);
+    // The top 180 px of the remote's left edge face empty space above the local screen
+    assert!(d.exit(Some("peer"), 0.0, 0.05).is_none());
+    assert!(d.exit(Some("peer"), 0.0, 0.5).is_some());
+}
+
+#[test]
+fn shorter_screen_is_reached_only_where_it_sits() {
+    let d = desktop(ScreenPosition::Left, screen(1280, 720, 1.0), true);
+    // Centered, the 720 px screen faces 180..900 of the local left edge
+    assert!(d.exit(None, 0.0, 0.1).is_none());
+    let crossing = d.exit(None, 0.0, 0.5).unwrap();
+    assert!(close(crossing.y, 0.5));
+    assert!(close(crossing.x, 1.0 - ENTRY_INSET));
+}
+
+#[test]
+fn retina_points_compare_with_pixels_directly() {
+    // A 1440x900 point Retina display is placed by its points, not its 2880x1800 pixels
+    let d = desktop(ScreenPosition::Top, screen(1440, 900, 2.0), true);
+    assert_eq!(d.rect_of("peer"), Some(Rect::new(240.0, -900.0, 1440.0, 900.0)));
+}
+
+#[test]
+fn remotes_take_the_size_their_handshake_reports() {
+    // Configured without a size, the remote starts out as the 1920x1080 stand-in
+    let topology = Topology {
+        local: screen(1920, 1080, 1.0),
+        remotes: vec![RemoteScreen { id: "peer".to_string(), position: ScreenPosition::Right, info: ScreenInfo::default() }],
+    };
+    let guessed = VirtualDesktop::new(&topology, true);
+    assert_eq!(guessed.rect_of("peer"), Some(Rect::new(1920.0, 0.0, 1920.0, 1080.0)));
+
+    let codec = FrameCodec::new(FrameLimits::default());
+    let sent = Handshake::new("peer".to_string(), CapabilitySet::all(), Some(screen(2560, 1440, 1.0)));
+    let Frame::Handshake(received) = codec.decode_message(&codec.encode_message(&Frame::Handshake(sent)).unwrap()).unwrap() else {
+        panic!("expected a handshake")
+    };
+    let reported = HashMap::from([(received.client_id, received.screen_info.unwrap())]);
+    let d = VirtualDesktop::new(&topology.with_peer_screens(&reported), true);
+    assert_eq!(d.rect_of("peer"), Some(Rect::new(1920.0, -180.0, 2560.0, 1440.0)));
+    let (dx, _) = d.motion("peer", 256.0, 0.0).unwrap();
+    assert!(close(dx, 0.1));
+}
+
+#[test]
+fn motion_follows_the_placed_size() {
+    let own = desktop(ScreenPosition::Right, screen(2560, 1440, 1.0), true);
+    let (dx, dy) = own.motion("peer", 256.0, 144.0).unwrap();
+    assert!(close(dx, 0.1) && close(dy, 0.1));
+
+    // Scaled to span the edge, the remote moves as far across as the local screen would
+    let scaled = desktop(ScreenPosition::Right, screen(2560, 1440, 1.0), false);
+    let (dx, dy) = scaled.motion("peer", 192.0, 108.0).unwrap();
+    assert!(close(dx, 0.1) && close(dy, 0.1));
+
+    assert!(own.motion("elsewhere", 10.0, 10.0).is_none());
+    let (dx, dy) = motion(&Rect::new(0.0, 0.0, 1024.0, 768.0), 512.0, 384.0);
+    assert!(close(dx, 0.5) && close(dy, 0.5));
+}
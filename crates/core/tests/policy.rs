@@ -0,0 +1,69 @@
+//! Per-peer policies: which frames may go to a peer, and which from it are acted on.
+use platform_passer_core::config::{PeerPolicy, PolicyDenial, SecurityConfig};
+use platform_passer_core::{ClipboardEvent, FileTransferRequest, FileTransferResponse, Frame, Heartbeat, InputEvent, MouseButton, TransferPurpose};
+
+fn input() -> Frame {
+    Frame::Input(InputEvent::MouseButton { button: MouseButton::Left, is_down: true }.into())
+}
+
+fn clipboard() -> Frame {
+    Frame::Clipboard(ClipboardEvent::Text("hello".to_string()))
+}
+
+fn request(file_size: u64) -> Frame {
+    Frame::FileTransferRequest(FileTransferRequest { id: 1, filename: "a.txt".to_string(), file_size, purpose: TransferPurpose::Manual })
+}
+
+#[test]
+fn peers_without_a_policy_may_do_everything() {
+    let policy = SecurityConfig::default().policy_for("3f2a-91bc-07de-4411");
+    assert_eq!(policy, PeerPolicy::default());
+    for frame in [input(), clipboard(), request(u64::MAX), Frame::FileData { id: 1, chunk: vec![0; 4] }] {
+        assert_eq!(policy.check_outgoing(&frame), Ok(()));
+        assert_eq!(policy.check_incoming(&frame), Ok(()));
+    }
+}
+
+#[test]
+fn clipboard_and_files_are_checked_per_direction() {
+    // Receives clipboard, but never pushes files
+    let policy = PeerPolicy { allow_clipboard_out: false, allow_file_receive: false, ..PeerPolicy::default() };
+    assert_eq!(policy.check_incoming(&clipboard()), Ok(()));
+    assert_eq!(policy.check_outgoing(&clipboard()), Err(PolicyDenial::ClipboardOut));
+    assert_eq!(policy.check_outgoing(&request(10)), Ok(()));
+    assert_eq!(policy.check_incoming(&request(10)), Err(PolicyDenial::FileReceive));
+    assert_eq!(policy.check_incoming(&Frame::FileEnd { id: 1 }), Err(PolicyDenial::FileReceive));
+
+    // Answers to our own requests still get through
+    let response = Frame::FileTransferResponse(FileTransferResponse { id: 1, accepted: true });
+    let closed = PeerPolicy { allow_file_send: false, ..policy };
+    assert_eq!(closed.check_incoming(&response), Ok(()));
+    assert_eq!(closed.check_outgoing(&response), Ok(()));
+}
+
+#[test]
+fn input_is_refused_both_ways_but_control_frames_are_not() {
+    let policy = PeerPolicy { allow_input: false, ..PeerPolicy::default() };
+    assert_eq!(policy.check_outgoing(&input()), Err(PolicyDenial::Input));
+    assert_eq!(policy.check_incoming(&input()), Err(PolicyDenial::Input));
+    assert_eq!(policy.check_incoming(&Frame::Heartbeat(Heartbeat::ping())), Ok(()));
+}
+
+#[test]
+fn transfers_over_the_limit_are_refused() {
+    let policy = PeerPolicy { max_transfer_bytes: Some(1024), ..PeerPolicy::default() };
+    assert_eq!(policy.check_outgoing(&request(1024)), Ok(()));
+    assert_eq!(policy.check_outgoing(&request(1025)), Err(PolicyDenial::TooLarge { size: 1025, max: 1024 }));
+    assert_eq!(policy.check_incoming(&request(1025)), Err(PolicyDenial::TooLarge { size: 1025, max: 1024 }));
+}
+
+#[test]
+fn policies_load_from_config_with_unset_fields_allowed() {
+    let json = r#"{ "peer_policies": { "3f2a-91bc-07de-4411": { "allow_clipboard_out": false, "max_transfer_bytes": 4096 } } }"#;
+    let security: SecurityConfig = serde_json::from_str(json).unwrap();
+    let policy = security.policy_for("3f2a-91bc-07de-4411");
+    assert!(!policy.allow_clipboard_out);
+    assert!(policy.allow_input && policy.allow_clipboard_in && policy.allow_file_send);
+    assert_eq!(policy.max_transfer_bytes, Some(4096));
+    assert_eq!(security.policy_for("0000-0000-0000-0000"), PeerPolicy::default());
+}
@@ -0,0 +1,173 @@
+//! Placing screens on the virtual desktop and crossing between them.
+use platform_passer_core::config::{DisplayInfo, RemoteScreen, ScreenInfo, ScreenPosition, Topology};
+use platform_passer_core::topology::{Edge, Rect, ENTRY_INSET};
+use platform_passer_core::VirtualDesktop;
+use std::collections::HashMap;
+
+fn screen(width: u32, height: u32) -> ScreenInfo {
+    ScreenInfo { width, height, dpi_scale: 1.0, displays: Vec::new() }
+}
+
+fn display(x: i32, y: i32, width: u32, height: u32) -> DisplayInfo {
+    DisplayInfo { x, y, width, height, dpi_scale: 1.0, primary: x == 0 && y == 0 }
+}
+
+/// Two 1920x1080 monitors side by side, the right one 540 pixels lower.
+fn staggered() -> ScreenInfo {
+    ScreenInfo { width: 3840, height: 1620, dpi_scale: 1.0, displays: vec![display(0, 0, 1920, 1080), display(1920, 540, 1920, 1080)] }
+}
+
+fn remote(id: &str, position: ScreenPosition, info: ScreenInfo) -> RemoteScreen {
+    RemoteScreen { id: id.to_string(), position, info }
+}
+
+fn desktop(remotes: Vec<RemoteScreen>) -> VirtualDesktop {
+    VirtualDesktop::new(&Topology { local: screen(1920, 1080), remotes }, false)
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+#[test]
+fn side_remotes_span_the_shared_edge() {
+    let d = desktop(vec![
+        remote("mac", ScreenPosition::Left, screen(2560, 1440)),
+        remote("pad", ScreenPosition::Top, screen(1024, 768)),
+    ]);
+    assert_eq!(d.rect_of("mac"), Some(Rect::new(-1920.0, 0.0, 1920.0, 1080.0)));
+    assert_eq!(d.rect_of("pad"), Some(Rect::new(0.0, -1440.0, 1920.0, 1440.0)));
+    assert_eq!(d.rect_of("tv"), None);
+}
+
+#[test]
+fn remotes_sharing_a_position_are_told_apart_by_id() {
+    let remotes = vec![remote("mac", ScreenPosition::Right, screen(1920, 1080)), remote("pad", ScreenPosition::Right, screen(1280, 720))];
+    let d = VirtualDesktop::new(&Topology { local: screen(1920, 1080), remotes }, true);
+    assert_eq!(d.rect_of("mac"), Some(Rect::new(1920.0, 0.0, 1920.0, 1080.0)));
+    assert_eq!(d.rect_of("pad"), Some(Rect::new(1920.0, 180.0, 1280.0, 720.0)));
+    let (dx, _) = d.motion("pad", 128.0, 0.0).unwrap();
+    assert!(close(dx, 0.1));
+    assert!(d.exit(Some("pad"), 0.0, 0.5).unwrap().to.is_none());
+}
+
+#[test]
+fn side_crossing_keeps_the_proportional_position() {
+    let d = desktop(vec![remote("mac", ScreenPosition::Left, screen(2560, 1440))]);
+    let crossing = d.exit(None, 0.0, 0.25).unwrap();
+    assert_eq!(crossing.to.map(|r| r.id.as_str()), Some("mac"));
+    assert_eq!(crossing.edge, Edge::Left);
+    assert!(close(crossing.x, 1.0 - ENTRY_INSET));
+    assert!(close(crossing.y, 0.25));
+
+    // And back out of the remote's right edge
+    let back = d.exit(Some("mac"), 1.0, 0.6).unwrap();
+    assert!(back.to.is_none());
+    assert_eq!(back.edge, Edge::Right);
+    assert!(close(back.x, ENTRY_INSET));
+    assert!(close(back.y, 0.6));
+}
+
+#[test]
+fn edges_without_a_neighbor_do_not_cross() {
+    let d = desktop(vec![remote("mac", ScreenPosition::Right, screen(1920, 1080))]);
+    assert!(d.exit(None, 0.0, 0.5).is_none());
+    assert!(d.exit(None, 0.5, 0.0).is_none());
+    // Not on an edge at all
+    assert!(d.exit(None, 0.99, 0.5).is_none());
+    assert!(d.exit(None, 1.0, 0.5).is_some());
+}
+
+#[test]
+fn absolute_remotes_cover_only_their_own_span() {
+    // A 1280x720 screen to the right, lowered by 540 pixels
+    let d = desktop(vec![remote("laptop", ScreenPosition::Absolute { x: 1920, y: 540 }, screen(1280, 720))]);
+    assert!(d.exit(None, 1.0, 0.25).is_none());
+
+    let crossing = d.exit(None, 1.0, 0.75).unwrap();
+    assert_eq!(crossing.to.map(|r| r.id.as_str()), Some("laptop"));
+    // 810 px down the local screen is 270 px down the laptop
+    assert!(close(crossing.y, 270.0 / 720.0));
+    assert!(close(crossing.x, ENTRY_INSET));
+
+    // The laptop's left edge below the local screen leads nowhere
+    assert!(d.exit(Some("laptop"), 0.0, 0.9).is_none());
+}
+
+#[test]
+fn remotes_can_cross_into_each_other() {
+    let left = ScreenPosition::Absolute { x: -1920, y: 0 };
+    let farther = ScreenPosition::Absolute { x: -3840, y: 0 };
+    let d = desktop(vec![remote("a", left, screen(1920, 1080)), remote("b", farther, screen(1920, 1080))]);
+    let crossing = d.exit(Some("a"), 0.0, 0.5).unwrap();
+    assert_eq!(crossing.to.map(|r| r.id.as_str()), Some("b"));
+    let back = d.exit(Some("a"), 1.0, 0.5).unwrap();
+    assert!(back.to.is_none());
+}
+
+#[test]
+fn absolute_position_round_trips_through_the_config() {
+    let position = ScreenPosition::Absolute { x: -1280, y: 200 };
+    let json = serde_json::to_string(&position).unwrap();
+    assert_eq!(json, r#"{"Absolute":{"x":-1280,"y":200}}"#);
+    assert_eq!(serde_json::from_str::<ScreenPosition>(&json).unwrap(), position);
+    assert_eq!(serde_json::from_str::<ScreenPosition>(r#""Left""#).unwrap(), ScreenPosition::Left);
+}
+
+#[test]
+fn monitor_list_sets_the_screen_bounds() {
+    let d = VirtualDesktop::new(&Topology { local: staggered(), remotes: Vec::new() }, false);
+    assert_eq!(d.local(), Rect::new(0.0, 0.0, 3840.0, 1620.0));
+    assert_eq!(
+        d.displays_of(None).unwrap(),
+        [Rect::new(0.0, 0.0, 1920.0, 1080.0), Rect::new(1920.0, 540.0, 1920.0, 1080.0)]
+    );
+}
+
+#[test]
+fn seams_between_own_monitors_do_not_cross() {
+    let d = VirtualDesktop::new(&Topology {
+        local: staggered(),
+        remotes: vec![remote("pad", ScreenPosition::Top, screen(1920, 1080))],
+    }, false);
+    // The right edge of the left monitor, where it meets the lower one
+    assert!(d.exit(None, 1919.0 / 3840.0, 800.0 / 1620.0).is_none());
+    // The top of the lower monitor faces the gap above it, not the remote
+    assert!(d.exit(None, 0.75, 540.0 / 1620.0).is_none());
+    // The top of the upper monitor does cross
+    let crossing = d.exit(None, 0.25, 0.0).unwrap();
+    assert_eq!(crossing.to.map(|r| r.id.as_str()), Some("pad"));
+}
+
+#[test]
+fn remote_monitors_are_entered_individually() {
+    // A laptop screen to the right of a monitor on the remote: only the monitor's height is
+    // reachable from a local screen spanning the remote's whole left edge
+    let info = ScreenInfo {
+        width: 3200,
+        height: 1440,
+        dpi_scale: 1.0,
+        displays: vec![display(0, 0, 1920, 720), display(1920, 0, 1280, 1440)],
+    };
+    let d = desktop(vec![remote("mac", ScreenPosition::Right, info)]);
+    assert!(d.exit(None, 1.0, 0.75).is_none());
+
+    let crossing = d.exit(None, 1.0, 0.25).unwrap();
+    // Inset into the 1920-wide monitor, normalized over the remote's whole width
+    assert!(close(crossing.x, ENTRY_INSET * 1920.0 / 3200.0));
+    assert!(close(crossing.y, 0.25));
+}
+
+#[test]
+fn reported_screens_replace_the_configured_ones_by_id() {
+    let topology = Topology { local: screen(1920, 1080), remotes: vec![remote("mac", ScreenPosition::Right, ScreenInfo::default())] };
+    let reported = HashMap::from([("mac".to_string(), staggered()), ("tv".to_string(), screen(3840, 2160))]);
+    let d = VirtualDesktop::new(&topology.with_peer_screens(&reported), true);
+    assert_eq!(d.rect_of("mac"), Some(Rect::new(1920.0, -270.0, 3840.0, 1620.0)));
+    assert_eq!(
+        d.displays_of(Some("mac")).unwrap(),
+        [Rect::new(1920.0, -270.0, 1920.0, 1080.0), Rect::new(3840.0, 270.0, 1920.0, 1080.0)]
+    );
+    // A peer without a place in the topology stays off the desktop
+    assert_eq!(d.rect_of("tv"), None);
+}
@@ -0,0 +1,245 @@
+//! Golden-byte fixtures for the frame envelope. If one of these fails, the wire format changed:
+//! bump `PROTOCOL_VERSION` and update the fixture deliberately.
+use platform_passer_core::config::{DisplayInfo, ScreenInfo};
+use platform_passer_core::wire::{decode_frame, encode_frame, FrameError, HEADER_LEN};
+use platform_passer_core::{
+    read_frame, write_frame, CapabilitySet, Capability, ClipboardEvent, DeviceAuth, Frame, FrameLimits, Handshake, Heartbeat,
+    InputEvent, InputFrame, InputStamp, ModifierState, MouseButton, RejectReason,
+    MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+
+fn hex(s: &str) -> Vec<u8> {
+    let digits: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn assert_golden(frame: Frame, expected: &str) {
+    let expected = hex(expected);
+    let encoded = encode_frame(&frame).unwrap();
+    assert_eq!(encoded, expected, "encoding of {:?} changed", frame);
+    let decoded = decode_frame(&expected, &FrameLimits::default()).unwrap();
+    assert_eq!(encode_frame(&decoded).unwrap(), expected);
+}
+
+#[test]
+fn heartbeat_fixture() {
+    assert_golden(
+        Frame::Heartbeat(Heartbeat { timestamp: 0x0102_0304_0506_0708, reply: true }),
+        "01 0200 09000000 0807060504030201 01",
+    );
+}
+
+#[test]
+fn protocol_4_heartbeat_decodes_as_ping() {
+    match decode_frame(&hex("01 0200 08000000 0807060504030201"), &FrameLimits::default()).unwrap() {
+        Frame::Heartbeat(hb) => {
+            assert_eq!(hb.timestamp, 0x0102_0304_0506_0708);
+            assert!(!hb.reply);
+        }
+        other => panic!("unexpected decode: {:?}", other),
+    }
+}
+
+#[test]
+fn handshake_fixture() {
+    let handshake = Handshake {
+        version: 4,
+        min_version: 4,
+        client_id: "a".to_string(),
+        capabilities: CapabilitySet::empty().with(Capability::Input).with(Capability::ClipboardText),
+        screen_info: None,
+        device_name: String::new(),
+    };
+    assert_golden(
+        Frame::Handshake(handshake),
+        "01 0100 1a000000 04000000 04000000 0100000000000000 61 0300000000000000 00",
+    );
+}
+
+#[test]
+fn pre_envelope_handshakes_decode_for_rejection() {
+    // Bare bincode `Frame::Handshake`: variant, version, then from protocol 2 the minimum
+    let v1 = hex("00000000 01000000 0100000000000000 61 0100000000000000 0500000000000000 696e707574 00");
+    let v2 = hex("00000000 02000000 02000000 0100000000000000 61 0000000000000000 00");
+    for (bytes, version) in [(v1, 1), (v2, 2)] {
+        let peer = match decode_frame(&bytes, &FrameLimits::default()).unwrap() {
+            Frame::Handshake(h) => h,
+            other => panic!("unexpected decode: {:?}", other),
+        };
+        assert_eq!((peer.version, peer.min_version, peer.client_id.as_str()), (version, version, "a"));
+        let ours = Handshake::new("b".into(), CapabilitySet::all(), None);
+        assert_eq!(
+            ours.negotiate_version(&peer),
+            Err(RejectReason::UnsupportedVersion { min_supported: MIN_PROTOCOL_VERSION, max_supported: PROTOCOL_VERSION })
+        );
+    }
+}
+
+#[test]
+fn input_fixture() {
+    assert_golden(
+        Frame::Input(InputEvent::MouseButton { button: MouseButton::Left, is_down: true }.into()),
+        "01 0300 09000000 01000000 00000000 01",
+    );
+}
+
+#[test]
+fn stamped_input_fixture() {
+    let frame = Frame::Input(InputFrame {
+        event: InputEvent::MouseButton { button: MouseButton::Left, is_down: true },
+        stamp: Some(InputStamp { seq: 2, captured_at_us: 0x0102 }),
+    });
+    assert_golden(frame, "01 0300 19000000 01000000 00000000 01 0200000000000000 0201000000000000");
+}
+
+#[test]
+fn clipboard_text_fixture() {
+    assert_golden(
+        Frame::Clipboard(ClipboardEvent::Text("hi".to_string())),
+        "01 0400 0e000000 00000000 0200000000000000 6869",
+    );
+}
+
+#[test]
+fn file_frames_fixture() {
+    assert_golden(Frame::FileData { id: 7, chunk: vec![0xaa, 0xbb] }, "01 0700 0e000000 07000000 0200000000000000 aabb");
+    assert_golden(Frame::FileEnd { id: 7 }, "01 0800 04000000 07000000");
+}
+
+#[test]
+fn unknown_frame_type_is_recoverable() {
+    let unknown = hex("01 ffff 03000000 010203");
+    let err = decode_frame(&unknown, &FrameLimits::default()).unwrap_err();
+    assert!(matches!(err, FrameError::UnknownType(0xffff)));
+    assert!(err.is_recoverable());
+}
+
+#[test]
+fn trailing_payload_fields_are_ignored() {
+    // A newer peer appended two bytes to the heartbeat payload.
+    let extended = hex("01 0200 0b000000 0807060504030201 00 beef");
+    match decode_frame(&extended, &FrameLimits::default()).unwrap() {
+        Frame::Heartbeat(hb) => assert_eq!(hb.timestamp, 0x0102_0304_0506_0708),
+        other => panic!("unexpected decode: {:?}", other),
+    }
+}
+
+#[test]
+fn truncated_envelope_is_an_error() {
+    let limits = FrameLimits::default();
+    let full = encode_frame(&Frame::FileEnd { id: 7 }).unwrap();
+    assert!(matches!(decode_frame(&full[..HEADER_LEN - 1], &limits), Err(FrameError::Truncated { .. })));
+    assert!(matches!(
+        decode_frame(&full[..full.len() - 1], &limits),
+        Err(FrameError::Truncated { expected: 4, actual: 3 })
+    ));
+}
+
+#[test]
+fn oversized_frame_is_rejected_per_family() {
+    let limits = FrameLimits { input: 8, ..FrameLimits::default() };
+    let input = encode_frame(&Frame::Input(InputEvent::MouseButton { button: MouseButton::Left, is_down: true }.into())).unwrap();
+    let err = decode_frame(&input, &limits).unwrap_err();
+    assert!(matches!(err, FrameError::TooLarge { tag: 3, len: 9, max: 8 }));
+    assert!(!err.is_recoverable());
+    // The same size is fine for a family with a larger limit.
+    assert!(decode_frame(&encode_frame(&Frame::FileEnd { id: 1 }).unwrap(), &limits).is_ok());
+}
+
+#[tokio::test]
+async fn stream_reader_rejects_huge_length_before_allocating() {
+    let stream = hex("01 0700 ffffffff");
+    let err = read_frame(&mut stream.as_slice(), &FrameLimits::default()).await.unwrap_err();
+    assert!(matches!(err, FrameError::TooLarge { len: u32::MAX, .. }));
+}
+
+#[tokio::test]
+async fn stream_reader_skips_unknown_frames() {
+    let mut stream = hex("01 3412 02000000 0000");
+    write_frame(&mut stream, &Frame::FileEnd { id: 9 }).await.unwrap();
+
+    let limits = FrameLimits::default();
+    let mut reader = stream.as_slice();
+    match read_frame(&mut reader, &limits).await.unwrap() {
+        Some(Frame::FileEnd { id }) => assert_eq!(id, 9),
+        other => panic!("unexpected frame: {:?}", other),
+    }
+    assert!(read_frame(&mut reader, &limits).await.unwrap().is_none());
+}
+
+#[test]
+fn extended_mouse_button_fixture() {
+    assert_golden(
+        Frame::Input(InputEvent::MouseButton { button: MouseButton::Other(7), is_down: false }.into()),
+        "01 0300 0a000000 01000000 05000000 07 00",
+    );
+}
+
+#[test]
+fn modifier_state_fixture() {
+    let mut state = ModifierState { caps_lock: true, num_lock: true, ..ModifierState::default() };
+    state.set_held(0xE1, true);
+    state.set_held(0xE6, true);
+    assert_golden(Frame::ModifierState(state), "01 0c00 04000000 42 01 01 00");
+}
+
+#[test]
+fn device_auth_fixture() {
+    let hello = DeviceAuth::Hello { public_key: vec![1, 2], nonce: vec![3] };
+    assert_golden(Frame::DeviceAuth(hello), "01 0d00 17000000 01000000 0200000000000000 0102 0100000000000000 03");
+    assert_golden(Frame::DeviceAuth(DeviceAuth::Accepted), "01 0d00 04000000 05000000");
+}
+
+#[test]
+fn pre_shared_key_frames_are_adapted_for_protocol_13() {
+    let proof = Frame::DeviceAuth(DeviceAuth::SecretProof(vec![0; 32]));
+    assert!(proof.clone().for_version(13).is_none());
+    assert!(proof.for_version(14).is_some());
+    let reject = Frame::HandshakeReject(RejectReason::SecretMismatch);
+    assert!(matches!(reject.clone().for_version(13), Some(Frame::HandshakeReject(RejectReason::NotPaired))));
+    assert!(matches!(reject.for_version(14), Some(Frame::HandshakeReject(RejectReason::SecretMismatch))));
+}
+
+#[test]
+fn handshake_displays_trail_the_screen_info() {
+    let handshake = |info: &ScreenInfo| Frame::Handshake(Handshake::new("a".into(), CapabilitySet::empty(), Some(info.clone())));
+    let mut info = ScreenInfo { width: 3200, height: 1080, dpi_scale: 2.0, displays: Vec::new() };
+    let plain = encode_frame(&handshake(&info)).unwrap();
+    info.displays = vec![
+        DisplayInfo { x: 0, y: 0, width: 1920, height: 1080, dpi_scale: 2.0, primary: true },
+        DisplayInfo { x: 1920, y: 0, width: 1280, height: 1024, dpi_scale: 1.0, primary: false },
+    ];
+    let with_displays = encode_frame(&handshake(&info)).unwrap();
+
+    // Older peers read up to the end of the old payload and see the same handshake
+    assert_eq!(&with_displays[HEADER_LEN..plain.len()], &plain[HEADER_LEN..]);
+    let screen_info = |bytes: &[u8]| match decode_frame(bytes, &FrameLimits::default()).unwrap() {
+        Frame::Handshake(h) => h.screen_info.unwrap(),
+        other => panic!("unexpected decode: {:?}", other),
+    };
+    assert_eq!(screen_info(&with_displays), info);
+    assert!(screen_info(&plain).displays.is_empty());
+}
+
+#[test]
+fn handshake_device_name_trails_the_displays() {
+    let decode = |frame: &Frame| match decode_frame(&encode_frame(frame).unwrap(), &FrameLimits::default()).unwrap() {
+        Frame::Handshake(h) => h,
+        other => panic!("unexpected decode: {:?}", other),
+    };
+    let info = ScreenInfo { width: 1920, height: 1080, dpi_scale: 1.0, displays: Vec::new() };
+    for screen_info in [None, Some(info)] {
+        let unnamed = Handshake::new("3f2a-91bc-07de-4411".into(), CapabilitySet::empty(), screen_info);
+        let named = Frame::Handshake(unnamed.clone().with_device_name("office laptop"));
+        let decoded = decode(&named);
+        assert_eq!(decoded.device_name, "office laptop");
+        assert_eq!(decoded.screen_info, unnamed.screen_info);
+
+        let decoded = decode(&Frame::Handshake(unnamed));
+        assert!(decoded.device_name.is_empty());
+        assert_eq!(decoded.client_id, "3f2a-91bc-07de-4411");
+    }
+}
@@ -0,0 +1,99 @@
+//! Deciding when a cursor against a screen edge should cross, shared by every `InputSource`.
+use platform_passer_core::config::EdgeGuardConfig;
+use platform_passer_core::topology::Edge;
+use std::time::{Duration, Instant};
+
+/// One cursor sample, as seen by a source's edge check.
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeContact {
+    /// The edge the cursor is against, or `None` when it is clear of every switching edge.
+    pub edge: Option<Edge>,
+    /// Cursor position along that edge, in pixels from its top or left end.
+    pub along_px: f64,
+    /// Length of the edge in pixels.
+    pub length_px: f64,
+    pub buttons_held: bool,
+}
+
+struct Touch {
+    edge: Edge,
+    since: Instant,
+    /// Whether this touch is the second of a double tap.
+    second_tap: bool,
+}
+
+/// Applies the `EdgeGuardConfig` conditions to a stream of cursor samples.
+///
+/// Sources call `update` on every mouse move, local or remote, and switch screens only when it
+/// returns `true`. Dwell is measured between samples, so the cursor has to keep pushing against
+/// the edge for it to elapse. A touch in a dead corner or with a button held (when blocked) counts
+/// as leaving the edge.
+pub struct EdgeGuard {
+    config: EdgeGuardConfig,
+    touching: Option<Touch>,
+    /// The edge last touched and when the cursor left it, for double taps.
+    last_tap: Option<(Edge, Instant)>,
+}
+
+impl EdgeGuard {
+    pub fn new(config: EdgeGuardConfig) -> Self {
+        Self { config, touching: None, last_tap: None }
+    }
+
+    pub fn set_config(&mut self, config: EdgeGuardConfig) {
+        self.config = config;
+        self.reset();
+    }
+
+    /// Forgets the current touch and tap, e.g. after the screen switched.
+    pub fn reset(&mut self) {
+        self.touching = None;
+        self.last_tap = None;
+    }
+
+    /// Whether the cursor should cross `contact.edge` now. After a `true` the guard starts over.
+    pub fn update(&mut self, contact: &EdgeContact, now: Instant) -> bool {
+        let edge = match contact.edge {
+            Some(edge) if !self.blocked(contact) => edge,
+            _ => {
+                if let Some(touch) = self.touching.take() {
+                    self.last_tap = Some((touch.edge, now));
+                }
+                return false;
+            }
+        };
+
+        if self.touching.as_ref().is_none_or(|t| t.edge != edge) {
+            let window = Duration::from_millis(self.config.double_tap_window_ms);
+            let second_tap = matches!(&self.last_tap,
+                Some((e, left)) if *e == edge && now.duration_since(*left) <= window);
+            self.touching = Some(Touch { edge, since: now, second_tap });
+        }
+        let Some(touch) = &self.touching else {
+            return false;
+        };
+
+        if self.config.double_tap && !touch.second_tap {
+            return false;
+        }
+        if now.duration_since(touch.since) < Duration::from_millis(self.config.dwell_ms) {
+            return false;
+        }
+        self.reset();
+        true
+    }
+
+    fn blocked(&self, contact: &EdgeContact) -> bool {
+        if self.config.block_while_button_held && contact.buttons_held {
+            return true;
+        }
+        let dead = self.config.corner_dead_zone_px as f64;
+        dead > 0.0 && (contact.along_px < dead || contact.along_px > contact.length_px - dead)
+    }
+}
+
+impl Default for EdgeGuard {
+    fn default() -> Self {
+        Self::new(EdgeGuardConfig::default())
+    }
+}
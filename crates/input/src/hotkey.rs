@@ -0,0 +1,115 @@
+//! Spotting configured hotkey chords in a source's key events, shared by every `InputSource`.
+use platform_passer_core::config::{HotkeyConfig, RemoteScreen};
+use platform_passer_core::{Chord, InputEvent};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HotkeyAction {
+    ReturnToLocal,
+    LockToScreen,
+    /// Holds the peer's `RemoteScreen::id`.
+    SwitchToPeer(String),
+    CyclePeers,
+}
+
+/// What a source should do with a key event it fed to the matcher.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HotkeyOutcome {
+    /// Not part of a hotkey; handle it as usual.
+    Pass,
+    /// The chord key went down: run the action and do not forward the key, unless the action
+    /// had nothing to do and the source hands the key back with `HotkeyMatcher::forward`.
+    Triggered(HotkeyAction),
+    /// A repeat or the release of a chord key that already triggered; do not forward it.
+    Swallow,
+}
+
+/// Tracks held modifiers across the keyboard stream and matches key-downs against the chords.
+///
+/// A chord matches when its key goes down while exactly its modifiers are held, either side
+/// counting. Sources feed every physical `InputEvent::Keyboard`, local or remote, and leave out
+/// events they injected themselves.
+pub struct HotkeyMatcher {
+    bindings: Vec<(Chord, HotkeyAction)>,
+    /// HID modifier usages currently down.
+    held: Vec<u16>,
+    /// Chord keys that triggered and have not been released yet.
+    swallowed: Vec<u16>,
+}
+
+impl HotkeyMatcher {
+    pub fn new(config: &HotkeyConfig) -> Self {
+        let mut matcher = Self { bindings: Vec::new(), held: Vec::new(), swallowed: Vec::new() };
+        matcher.set_config(config);
+        matcher
+    }
+
+    /// Replaces the chords. Held keys are kept, so a chord released after the change is still swallowed.
+    pub fn set_config(&mut self, config: &HotkeyConfig) {
+        let mut bindings = Vec::new();
+        if let Some(chord) = config.return_to_local {
+            bindings.push((chord, HotkeyAction::ReturnToLocal));
+        }
+        if let Some(chord) = config.lock_to_screen {
+            bindings.push((chord, HotkeyAction::LockToScreen));
+        }
+        if let Some(chord) = config.cycle_peers {
+            bindings.push((chord, HotkeyAction::CyclePeers));
+        }
+        for peer in &config.switch_to_peer {
+            bindings.push((peer.chord, HotkeyAction::SwitchToPeer(peer.peer.clone())));
+        }
+        self.bindings = bindings;
+    }
+
+    /// Side-independent `hotkey::modifiers` flags of the modifiers currently held.
+    pub fn modifiers(&self) -> u8 {
+        self.held.iter().filter_map(|&usage| Chord::modifier_flag(usage)).fold(0, |acc, flag| acc | flag)
+    }
+
+    /// Stops swallowing chord key `usage`, which triggered an action that had nothing to do,
+    /// e.g. a peer chord naming an unknown peer. The key and its release reach the system as usual.
+    pub fn forward(&mut self, usage: u16) {
+        self.swallowed.retain(|&u| u != usage);
+    }
+
+    pub fn process(&mut self, event: &InputEvent) -> HotkeyOutcome {
+        let InputEvent::Keyboard { usage, is_down, .. } = *event else {
+            return HotkeyOutcome::Pass;
+        };
+        if Chord::modifier_flag(usage).is_some() {
+            self.held.retain(|&u| u != usage);
+            if is_down {
+                self.held.push(usage);
+            }
+            return HotkeyOutcome::Pass;
+        }
+        if self.swallowed.contains(&usage) {
+            if !is_down {
+                self.swallowed.retain(|&u| u != usage);
+            }
+            return HotkeyOutcome::Swallow;
+        }
+        if !is_down || usage == 0 {
+            return HotkeyOutcome::Pass;
+        }
+
+        let modifiers = self.modifiers();
+        match self.bindings.iter().find(|(chord, _)| chord.key == usage && chord.modifiers == modifiers) {
+            Some((_, action)) => {
+                self.swallowed.push(usage);
+                HotkeyOutcome::Triggered(action.clone())
+            }
+            None => HotkeyOutcome::Pass,
+        }
+    }
+}
+
+/// Where `CyclePeers` goes from the remote with id `active`: the next remote in topology order,
+/// or `None` for this machine once past the last one.
+pub fn next_peer<'a>(remotes: &'a [RemoteScreen], active: Option<&str>) -> Option<&'a RemoteScreen> {
+    let start = match active {
+        None => 0,
+        Some(id) => remotes.iter().position(|r| r.id == id)? + 1,
+    };
+    remotes.get(start)
+}
@@ -1,229 +1,33 @@
-pub fn macos_to_windows_vk(macos_code: u32) -> u32 {
-    match macos_code {
-        0 => 0x41, // A
-        1 => 0x53, // S
-        2 => 0x44, // D
-        3 => 0x46, // F
-        4 => 0x48, // H
-        5 => 0x47, // G
-        6 => 0x5A, // Z
-        7 => 0x58, // X
-        8 => 0x43, // C
-        9 => 0x56, // V
-        11 => 0x42, // B
-        12 => 0x51, // Q
-        13 => 0x57, // W
-        14 => 0x45, // E
-        15 => 0x52, // R
-        16 => 0x59, // Y
-        17 => 0x54, // T
-        18 => 0x31, // 1
-        19 => 0x32, // 2
-        20 => 0x33, // 3
-        21 => 0x34, // 4
-        22 => 0x36, // 6
-        23 => 0x35, // 5
-        24 => 0xBB, // =
-        25 => 0x39, // 9
-        26 => 0x37, // 7
-        27 => 0xBD, // -
-        28 => 0x38, // 8
-        29 => 0x30, // 0
-        30 => 0xDD, // ]
-        31 => 0x4F, // O
-        32 => 0x55, // U
-        33 => 0xDB, // [
-        34 => 0x49, // I
-        35 => 0x50, // P
-        36 => 0x0D, // Enter
-        37 => 0x4C, // L
-        38 => 0x4A, // J
-        39 => 0xDE, // '
-        40 => 0x4B, // K
-        41 => 0xBA, // ;
-        42 => 0xDC, // \
-        43 => 0xBC, // ,
-        44 => 0xBF, // /
-        45 => 0x4E, // N
-        46 => 0x4D, // M
-        47 => 0xBE, // .
-        48 => 0x09, // Tab
-        49 => 0x20, // Space
-        50 => 0xC0, // `
-        51 => 0x08, // Backspace
-        53 => 0x1B, // Escape
-
-        // Keypad
-        65 => 0x6E, // Keypad .
-        67 => 0x6A, // Keypad *
-        69 => 0x6B, // Keypad +
-        71 => 0x0C, // Keypad Clear (NumLock-ish)
-        75 => 0x6F, // Keypad /
-        76 => 0x0D, // Keypad Enter
-        78 => 0x6D, // Keypad -
-        81 => 0x92, // Keypad = (VK_OEM_NEC_EQUAL)
-        82 => 0x60, // Keypad 0
-        83 => 0x61, // Keypad 1
-        84 => 0x62, // Keypad 2
-        85 => 0x63, // Keypad 3
-        86 => 0x64, // Keypad 4
-        87 => 0x65, // Keypad 5
-        88 => 0x66, // Keypad 6
-        89 => 0x67, // Keypad 7
-        91 => 0x68, // Keypad 8
-        92 => 0x69, // Keypad 9
-
-        // Function Keys
-        122 => 0x70, // F1
-        120 => 0x71, // F2
-        99 => 0x72,  // F3
-        118 => 0x73, // F4
-        96 => 0x74,  // F5
-        97 => 0x75,  // F6
-        98 => 0x76,  // F7
-        100 => 0x77, // F8
-        101 => 0x78, // F9
-        109 => 0x79, // F10
-        103 => 0x7A, // F11
-        111 => 0x7B, // F12
-        105 => 0x7C, // F13
-        107 => 0x7D, // F14
-        113 => 0x7E, // F15
+//! Key code conversion between USB HID usages and each platform's codes.
+//!
+//! The tables live in `platform_passer_core::keymap`, next to the `Keyboard` frame whose usages
+//! they define.
+pub use platform_passer_core::keymap::*;
+use platform_passer_core::KeyHint;
+
+/// HID usage for a key the Windows hook reported. The scancode names the physical key and
+/// does not depend on the layout, so it is tried before the virtual-key code.
+pub fn windows_to_hid(vk: u32, scancode: u16) -> Option<u16> {
+    windows_scancode_to_hid(scancode).or_else(|| windows_vk_to_hid(vk))
+}
 
-        // Other common keys
-        114 => 0x2D, // Insert (Help on some Mac keyboards)
-        115 => 0x24, // Home
-        116 => 0x21, // Page Up
-        117 => 0x2E, // Forward Delete
-        119 => 0x23, // End
-        121 => 0x22, // Page Down
-        
-        // Modifiers
-        54 => 0x5C, // R-Command -> R-Windows Key
-        55 => 0x5B, // Command -> Windows Key
-        56 => 0x10, // Shift
-        57 => 0x14, // Caps Lock
-        58 => 0x12, // Option -> Alt
-        59 => 0x11, // Control -> Control
-        60 => 0x10, // R-Shift
-        61 => 0x12, // R-Option -> Alt
-        62 => 0x11, // R-Control -> Control
-        
-        // Arrows
-        123 => 0x25, // Left
-        124 => 0x27, // Right
-        125 => 0x28, // Down
-        126 => 0x26, // Up
-        
-        _ => macos_code,
+/// Virtual-key code and scancode to inject for a received key, or `None` if it has no Windows
+/// equivalent. Converted keys come back with a scancode and a zero virtual-key code, so the
+/// sink injects the physical key and lets the local layout decide what it types.
+pub fn windows_key(usage: u16, hint: Option<KeyHint>) -> Option<(u32, u16)> {
+    if let Some(KeyHint::Windows { vk, scancode }) = hint {
+        return Some((vk, scancode));
+    }
+    match hid_to_windows_scancode(usage) {
+        Some(scancode) => Some((0, scancode)),
+        None => hid_to_windows_vk(usage).map(|vk| (vk, 0)),
     }
 }
 
-pub fn windows_to_macos_keycode(win_vk: u32) -> u16 {
-    match win_vk {
-        0x41 => 0,   // A
-        0x53 => 1,   // S
-        0x44 => 2,   // D
-        0x46 => 3,   // F
-        0x48 => 4,   // H
-        0x47 => 5,   // G
-        0x5A => 6,   // Z
-        0x58 => 7,   // X
-        0x43 => 8,   // C
-        0x56 => 9,   // V
-        0x42 => 11,  // B
-        0x51 => 12,  // Q
-        0x57 => 13,  // W
-        0x45 => 14,  // E
-        0x52 => 15,  // R
-        0x59 => 16,  // Y
-        0x54 => 17,  // T
-        0x31 => 18,  // 1
-        0x32 => 19,  // 2
-        0x33 => 20,  // 3
-        0x34 => 21,  // 4
-        0x36 => 22,  // 6
-        0x35 => 23,  // 5
-        0xBB => 24,  // =
-        0x39 => 25,  // 9
-        0x37 => 26,  // 7
-        0xBD => 27,  // -
-        0x38 => 28,  // 8
-        0x30 => 29,  // 0
-        0xDD => 30,  // ]
-        0x4F => 31,  // O
-        0x55 => 32,  // U
-        0xDB => 33,  // [
-        0x49 => 34,  // I
-        0x50 => 35,  // P
-        0x0D => 36,  // Enter
-        0x4C => 37,  // L
-        0x4A => 38,  // J
-        0xDE => 39,  // '
-        0x4B => 40,  // K
-        0xBA => 41,  // ;
-        0xDC => 42,  // \
-        0xBC => 43,  // ,
-        0xBF => 44,  // /
-        0x4E => 45,  // N
-        0x4D => 46,  // M
-        0xBE => 47,  // .
-        0x09 => 48,  // Tab
-        0x20 => 49,  // Space
-        0xC0 => 50,  // `
-        0x08 => 51,  // Backspace
-        0x1B => 53,  // Escape
-        
-        // Numeric Keypad
-        0x6E => 65, // Decimal
-        0x6A => 67, // Multiply
-        0x6B => 69, // Add
-        0x90 => 71, // Num Lock (Clear)
-        0x6F => 75, // Divide
-        0x6D => 78, // Subtract
-        0x60 => 82, // 0
-        0x61 => 83, // 1
-        0x62 => 84, // 2
-        0x63 => 85, // 3
-        0x64 => 86, // 4
-        0x65 => 87, // 5
-        0x66 => 88, // 6
-        0x67 => 89, // 7
-        0x68 => 91, // 8
-        0x69 => 92, // 9
-
-        // Function Keys
-        0x70 => 122, // F1
-        0x71 => 120, // F2
-        0x72 => 99,  // F3
-        0x73 => 118, // F4
-        0x74 => 96,  // F5
-        0x75 => 97,  // F6
-        0x76 => 98,  // F7
-        0x77 => 100, // F8
-        0x78 => 101, // F9
-        0x79 => 109, // F10
-        0x7A => 103, // F11
-        0x7B => 111, // F12
-
-        // Modifiers
-        0x5B => 55,  // Windows Key -> Command
-        0x5C => 54,  // R-Windows Key -> R-Command
-        0x10 => 56,  // Shift
-        0x14 => 57,  // Caps Lock
-        0x12 => 58,  // Alt -> Option
-        0x11 => 59,  // Control -> Control
-        
-        // Arrows
-        0x25 => 123, // Left
-        0x27 => 124, // Right
-        0x28 => 125, // Down
-        0x26 => 126, // Up
-
-        // Keypad (Alias)
-        0x0C => 71, // Keypad Clear
-        0x92 => 81, // Keypad =
-
-        _ => win_vk as u16,
+/// macOS virtual keycode for a received key, or `None` if it has no macOS equivalent.
+pub fn macos_key(usage: u16, hint: Option<KeyHint>) -> Option<u16> {
+    match hint {
+        Some(KeyHint::Macos { keycode }) => Some(keycode),
+        _ => hid_to_macos_keycode(usage),
     }
 }
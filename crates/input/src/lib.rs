@@ -1,5 +1,12 @@
 pub mod traits;
 pub mod keymap;
+pub mod edge;
+pub mod hotkey;
+pub mod modifiers;
+pub mod pressed;
+pub mod scroll;
+pub mod screen_lock;
+pub mod text;
 
 #[cfg(target_os = "windows")]
 pub mod windows;
@@ -8,6 +15,12 @@ pub mod windows;
 pub mod macos;
 
 pub use traits::*;
+pub use edge::{EdgeContact, EdgeGuard};
+pub use pressed::PressedStateTracker;
+pub use hotkey::{HotkeyAction, HotkeyMatcher, HotkeyOutcome};
+pub use scroll::ScrollAccumulator;
+pub use screen_lock::ScreenLock;
+pub use text::LayoutFilter;
 
 #[cfg(target_os = "windows")]
 pub use windows::*;
@@ -4,43 +4,64 @@ use core_graphics::event::{CGEvent, CGEventTapLocation, CGEventType, CGMouseButt
 use core_graphics::event_source::CGEventSource;
 use core_graphics::geometry::CGPoint;
 use foreign_types::ForeignType;
-use platform_passer_core::InputEvent;
+use platform_passer_core::{InputEvent, ModifierState, ScreenSide, ScrollPhase, ScrollUnit, PIXELS_PER_LINE};
+use crate::{PressedStateTracker, ScrollAccumulator};
 use std::sync::Mutex;
-use std::collections::HashSet;
 use platform_passer_core::config::AppConfig;
 
 pub struct MacosInputSink {
     last_pos: Mutex<CGPoint>,
+    /// Origin and size of the bounding box of all displays, which positions are normalized
+    /// against. Looked up again after a config update.
+    desktop: Mutex<Option<(f64, f64, f64, f64)>>,
     scroll_multiplier: Mutex<f32>,
     scroll_reverse: Mutex<bool>,
-    pressed_keys: Mutex<HashSet<u16>>,
-    pressed_buttons: Mutex<HashSet<u32>>,
+    pressed: Mutex<PressedStateTracker>,
+    scroll_accumulator: Mutex<ScrollAccumulator>,
 }
 
 impl MacosInputSink {
     pub fn new() -> Self {
         Self {
             last_pos: Mutex::new(CGPoint::new(0.0, 0.0)),
+            desktop: Mutex::new(None),
             scroll_multiplier: Mutex::new(1.0),
             scroll_reverse: Mutex::new(false),
-            pressed_keys: Mutex::new(HashSet::new()),
-            pressed_buttons: Mutex::new(HashSet::new()),
+            pressed: Mutex::new(PressedStateTracker::new()),
+            scroll_accumulator: Mutex::new(ScrollAccumulator::new(PIXELS_PER_LINE)),
         }
     }
+
+    fn post_key(&self, source: CGEventSource, mac_keycode: u16, is_down: bool) -> Result<()> {
+        let cg_event = core_graphics::event::CGEvent::new_keyboard_event(
+            source,
+            mac_keycode,
+            is_down,
+        ).map_err(|_| anyhow!("Failed to create keyboard event"))?;
+        cg_event.post(CGEventTapLocation::HID);
+        Ok(())
+    }
+
+    fn desktop_bounds(&self) -> (f64, f64, f64, f64) {
+        let Ok(mut cache) = self.desktop.lock() else { return (0.0, 0.0, 1920.0, 1080.0) };
+        *cache.get_or_insert_with(|| super::utils::desktop_bounds().unwrap_or((0.0, 0.0, 1920.0, 1080.0)))
+    }
 }
 
 impl InputSink for MacosInputSink {
     fn inject_event(&self, event: InputEvent) -> Result<()> {
+        if let Ok(mut pressed) = self.pressed.lock() {
+            pressed.record(&event);
+        }
         let source = CGEventSource::new(core_graphics::event_source::CGEventSourceStateID::Private).map_err(|_| anyhow!("Failed to create event source"))?;
 
         match event {
             InputEvent::MouseMove { x, y } => {
-                let display_id = unsafe { core_graphics::display::CGMainDisplayID() };
-                let bounds = unsafe { core_graphics::display::CGDisplayBounds(display_id) };
-                
+                // Across every display, as the peer's virtual cursor spans this whole screen
+                let (left, top, width, height) = self.desktop_bounds();
                 let target_pos = CGPoint::new(
-                    (x as f64) * bounds.size.width,
-                    (y as f64) * bounds.size.height,
+                    left + (x as f64) * width,
+                    top + (y as f64) * height,
                 );
 
                 // Update last known position
@@ -56,41 +77,40 @@ impl InputSink for MacosInputSink {
                 ).map_err(|_| anyhow!("Failed to create mouse move event"))?;
                 cg_event.post(CGEventTapLocation::HID);
             }
-            InputEvent::Keyboard { key_code, is_down } => {
-                let mac_keycode = crate::keymap::windows_to_macos_keycode(key_code);
-                
-                if let Ok(mut keys) = self.pressed_keys.lock() {
-                    if is_down {
-                        keys.insert(mac_keycode);
-                    } else {
-                        keys.remove(&mac_keycode);
+            InputEvent::Text(text) => {
+                let utf16: Vec<u16> = text.encode_utf16().collect();
+                for chunk in utf16.chunks(MAX_EVENT_STRING_LEN) {
+                    for is_down in [true, false] {
+                        // The string replaces whatever keycode 0 would type on the local layout
+                        let Ok(cg_event) = CGEvent::new_keyboard_event(source.clone(), 0, is_down) else {
+                            tracing::debug!("InputSink: Unicode injection unavailable, typing ASCII keys instead");
+                            for key in crate::text::ascii_key_events(&text) {
+                                self.inject_event(key)?;
+                            }
+                            return Ok(());
+                        };
+                        cg_event.set_string_from_utf16_unchecked(chunk);
+                        cg_event.post(CGEventTapLocation::HID);
                     }
                 }
-
-                let cg_event = core_graphics::event::CGEvent::new_keyboard_event(
-                    source,
-                    mac_keycode,
-                    is_down,
-                ).map_err(|_| anyhow!("Failed to create keyboard event"))?;
-                cg_event.post(CGEventTapLocation::HID);
+            }
+            InputEvent::KeyboardVk { key_code, is_down } => {
+                match crate::keymap::windows_vk_to_hid(key_code).and_then(crate::keymap::hid_to_macos_keycode) {
+                    Some(mac_keycode) => self.post_key(source, mac_keycode, is_down)?,
+                    None => tracing::trace!("InputSink: No macOS key for virtual-key code {:#04x}", key_code),
+                }
+            }
+            InputEvent::Keyboard { usage, is_down, hint } => {
+                match crate::keymap::macos_key(usage, hint) {
+                    Some(mac_keycode) => self.post_key(source, mac_keycode, is_down)?,
+                    None => tracing::trace!("InputSink: No macOS key for HID usage {:#04x}", usage),
+                }
             }
             InputEvent::MouseButton { button, is_down } => {
-                let cg_button = match button {
-                    platform_passer_core::MouseButton::Left => CGMouseButton::Left,
-                    platform_passer_core::MouseButton::Right => CGMouseButton::Right,
-                    platform_passer_core::MouseButton::Middle => CGMouseButton::Center,
-                };
+                let cg_button = cg_mouse_button(button.index());
                 
-                // Using u32 representation
-                let btn_u32 = cg_button as u32;
-
-                if let Ok(mut btns) = self.pressed_buttons.lock() {
-                    if is_down {
-                        btns.insert(btn_u32);
-                    } else {
-                        btns.remove(&btn_u32);
-                    }
-                }
+                // Button number, which for Left/Right/Center equals the CGMouseButton value
+                let btn_u32 = button.index() as u32;
 
                 let etype = if is_down {
                     match cg_button {
@@ -118,20 +138,10 @@ impl InputSink for MacosInputSink {
                     pos,
                     cg_button,
                 ).map_err(|_| anyhow!("Failed to create mouse button event"))?;
+                cg_event.set_integer_value_field(MOUSE_EVENT_BUTTON_NUMBER, btn_u32 as i64);
                 cg_event.post(CGEventTapLocation::HID);
             }
             InputEvent::Scroll { dx: _dx, dy } => {
-                extern "C" {
-                    fn CGEventCreateScrollWheelEvent2(
-                        source: *mut std::ffi::c_void,
-                        units: u32,
-                        wheelCount: u32,
-                        wheel1: i32,
-                        wheel2: i32,
-                        wheel3: i32,
-                    ) -> *mut std::ffi::c_void;
-                }
-
                 unsafe {
                     let source_ptr: *mut std::ffi::c_void = std::mem::transmute(source);
                     let event_ptr = CGEventCreateScrollWheelEvent2(
@@ -153,8 +163,51 @@ impl InputSink for MacosInputSink {
                     }
                 }
             }
+            InputEvent::SmoothScroll { dx, dy, unit, phase } => {
+                let mult = if let Ok(guard) = self.scroll_multiplier.lock() { *guard } else { 1.0 };
+                let reverse = if let Ok(guard) = self.scroll_reverse.lock() { *guard } else { false };
+                let dy = if reverse { -dy } else { dy };
+
+                let (px_x, px_y) = match self.scroll_accumulator.lock() {
+                    Ok(mut acc) => acc.accumulate(dx * mult, dy * mult, unit, phase),
+                    Err(_) => return Ok(()),
+                };
+                // Phase-only events still matter: they start and stop momentum in apps
+                if px_x == 0 && px_y == 0 && phase == ScrollPhase::None {
+                    return Ok(());
+                }
+
+                let (scroll_phase, momentum_phase) = match phase {
+                    ScrollPhase::None => (0, 0),
+                    ScrollPhase::Began => (1, 0),
+                    ScrollPhase::Changed => (2, 0),
+                    ScrollPhase::Ended => (4, 0),
+                    ScrollPhase::Momentum => (0, 2),
+                    ScrollPhase::MomentumEnded => (0, 3),
+                };
+
+                unsafe {
+                    let source_ptr: *mut std::ffi::c_void = std::mem::transmute(source);
+                    let event_ptr = CGEventCreateScrollWheelEvent2(
+                        source_ptr,
+                        0, // Pixel units
+                        2, // wheel count
+                        px_y,
+                        px_x,
+                        0,
+                    );
+                    if !event_ptr.is_null() {
+                        let cg_event = CGEvent::from_ptr(event_ptr as *mut _);
+                        cg_event.set_integer_value_field(SCROLL_WHEEL_EVENT_IS_CONTINUOUS, (unit == ScrollUnit::Pixel) as i64);
+                        cg_event.set_integer_value_field(SCROLL_WHEEL_EVENT_SCROLL_PHASE, scroll_phase);
+                        cg_event.set_integer_value_field(SCROLL_WHEEL_EVENT_MOMENTUM_PHASE, momentum_phase);
+                        cg_event.post(CGEventTapLocation::HID);
+                    }
+                }
+            }
+            InputEvent::ScreenSwitch(ScreenSide::Local) => return self.reset_input(),
             InputEvent::ScreenSwitch(_) => {
-                // Sinks don't handle screen switches directly
+                // Entering is handled by the session; nothing to inject
             }
         }
 
@@ -162,6 +215,9 @@ impl InputSink for MacosInputSink {
     }
 
     fn update_config(&self, config: AppConfig) -> Result<()> {
+        if let Ok(mut guard) = self.desktop.lock() {
+            *guard = None;
+        }
         if let Ok(mut guard) = self.scroll_multiplier.lock() {
             *guard = config.input.scroll_speed_multiplier;
         }
@@ -172,48 +228,41 @@ impl InputSink for MacosInputSink {
     }
 
     fn reset_input(&self) -> Result<()> {
-        let source = CGEventSource::new(core_graphics::event_source::CGEventSourceStateID::Private).map_err(|_| anyhow!("Failed to create event source"))?;
-
-        if let Ok(mut keys) = self.pressed_keys.lock() {
-            for key in keys.drain() {
-                if let Ok(cg_event) = core_graphics::event::CGEvent::new_keyboard_event(
-                    source.clone(),
-                    key,
-                    false, // is_down = false -> key up
-                ) {
-                    cg_event.post(CGEventTapLocation::HID);
-                }
-            }
+        let held = match self.pressed.lock() {
+            Ok(mut pressed) => pressed.release_all(),
+            Err(_) => return Ok(()),
+        };
+        for event in held {
+            self.inject_event(event)?;
         }
+        Ok(())
+    }
 
-        if let Ok(mut btns) = self.pressed_buttons.lock() {
-            let pos = if let Ok(p) = self.last_pos.lock() { *p } else { CGPoint::new(0.0, 0.0) };
-            
-            for btn in btns.drain() {
-                let btn_cg: CGMouseButton = unsafe { std::mem::transmute(btn) };
-                
-                let etype = match btn {
-                    0 => CGEventType::LeftMouseUp, // Left
-                    1 => CGEventType::RightMouseUp, // Right
-                    _ => CGEventType::OtherMouseUp,
-                };
+    fn local_modifier_state(&self) -> Option<ModifierState> {
+        Some(super::utils::modifier_state(0))
+    }
 
-                if let Ok(cg_event) = core_graphics::event::CGEvent::new_mouse_event(
-                    source.clone(),
-                    etype,
-                    pos,
-                    btn_cg,
-                ) {
-                    cg_event.post(CGEventTapLocation::HID);
-                }
-            }
+    fn sync_modifiers(&self, target: &ModifierState) -> Result<()> {
+        let current = super::utils::modifier_state(0);
+        // Posted caps lock keys do not toggle the lock, so only modifiers go through key events
+        let keys_only = ModifierState { held: target.held, ..current };
+        for event in crate::modifiers::reconcile(&current, &keys_only) {
+            self.inject_event(event)?;
+        }
+        if current.caps_lock != target.caps_lock {
+            super::utils::set_caps_lock(target.caps_lock)?;
         }
-
         Ok(())
     }
 }
 
 
+impl Drop for MacosInputSink {
+    fn drop(&mut self) {
+        let _ = self.reset_input();
+    }
+}
+
 pub fn force_release_modifiers() {
     use core_graphics::event::{CGEvent, CGEventTapLocation};
     use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
@@ -229,3 +278,34 @@ pub fn force_release_modifiers() {
         }
     }
 }
+
+/// kCGMouseEventButtonNumber; selects which "other" button an OtherMouse event refers to.
+const MOUSE_EVENT_BUTTON_NUMBER: u32 = 3;
+/// Longest string one keyboard event carries; CGEventKeyboardSetUnicodeString truncates after 20 units.
+const MAX_EVENT_STRING_LEN: usize = 20;
+/// kCGScrollWheelEventIsContinuous; set for trackpad (pixel) scrolling.
+const SCROLL_WHEEL_EVENT_IS_CONTINUOUS: u32 = 88;
+/// kCGScrollWheelEventScrollPhase: 1 began, 2 changed, 4 ended.
+const SCROLL_WHEEL_EVENT_SCROLL_PHASE: u32 = 99;
+/// kCGScrollWheelEventMomentumPhase: 1 begin, 2 continue, 3 end.
+const SCROLL_WHEEL_EVENT_MOMENTUM_PHASE: u32 = 123;
+
+extern "C" {
+    fn CGEventCreateScrollWheelEvent2(
+        source: *mut std::ffi::c_void,
+        units: u32,
+        wheelCount: u32,
+        wheel1: i32,
+        wheel2: i32,
+        wheel3: i32,
+    ) -> *mut std::ffi::c_void;
+}
+
+/// Buttons beyond the first three are posted as `Center` with the button number field set.
+fn cg_mouse_button(index: u8) -> CGMouseButton {
+    match index {
+        0 => CGMouseButton::Left,
+        1 => CGMouseButton::Right,
+        _ => CGMouseButton::Center,
+    }
+}
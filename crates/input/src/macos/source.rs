@@ -1,7 +1,9 @@
-use crate::InputSource;
+use crate::{EdgeContact, EdgeGuard, HotkeyAction, HotkeyMatcher, HotkeyOutcome, InputSource, ScreenLock};
 use anyhow::{Result, anyhow};
-use platform_passer_core::InputEvent;
-use platform_passer_core::config::{AppConfig, Topology, ScreenPosition};
+use platform_passer_core::{InputEvent, KeyHint, ModifierState, ScrollPhase, ScrollUnit};
+use platform_passer_core::config::{AppConfig, RemoteScreen, ScreenInfo, Topology, ScreenPosition};
+use platform_passer_core::topology::{Edge, VirtualDesktop};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::thread;
 use core_foundation::runloop::{CFRunLoop, kCFRunLoopCommonModes};
@@ -15,6 +17,12 @@ extern "C" {
     fn CGDisplayShowCursor(display: u32) -> u32;
     fn CGWarpMouseCursorPosition(new_pos: core_graphics::geometry::CGPoint) -> u32;
     fn CGMainDisplayID() -> u32;
+    fn CGEventKeyboardGetUnicodeString(
+        event: *mut std::ffi::c_void,
+        max_len: std::ffi::c_ulong,
+        actual_len: *mut std::ffi::c_ulong,
+        buf: *mut u16,
+    );
 }
 
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
@@ -22,13 +30,23 @@ use std::sync::Mutex;
 use std::time::Instant;
 
 static IS_REMOTE: AtomicBool = AtomicBool::new(false);
-static PRESSED_BUTTONS: AtomicU8 = AtomicU8::new(0); // Bitmask: 1=Left, 2=Right, 4=Middle
+static PRESSED_BUTTONS: AtomicU8 = AtomicU8::new(0); // Bitmask by button number: 1=Left, 2=Right, 4=Middle, 8=Back, ...
 static LAST_SWITCH_TIME: Mutex<Option<Instant>> = Mutex::new(None);
 static VIRTUAL_CURSOR: Mutex<(f32, f32)> = Mutex::new((0.0, 0.0));
-static DISPLAY_CACHE: Mutex<Option<(f32, f32)>> = Mutex::new(None);
+static DISPLAY_CACHE: Mutex<Option<(f32, f32, f32, f32)>> = Mutex::new(None);
 static TOPOLOGY: Mutex<Option<Topology>> = Mutex::new(None);
-static ACTIVE_REMOTE_POS: Mutex<Option<ScreenPosition>> = Mutex::new(None);
+/// The `RemoteScreen::id` of the remote that has input.
+static ACTIVE_REMOTE: Mutex<Option<String>> = Mutex::new(None);
 static SCROLL_REVERSE: AtomicBool = AtomicBool::new(false);
+static MAINTAIN_ASPECT_RATIO: AtomicBool = AtomicBool::new(true);
+/// Toggled by the lock-to-screen hotkey and `set_screen_lock`; edges do not switch screens while on.
+static SCREEN_LOCK: ScreenLock = ScreenLock::new();
+static HOTKEYS: Mutex<Option<HotkeyMatcher>> = Mutex::new(None);
+static EDGE_GUARD: Mutex<Option<EdgeGuard>> = Mutex::new(None);
+/// Rebuilt from the topology on every config update, and whenever a peer reports its screen.
+static DESKTOP: Mutex<Option<VirtualDesktop>> = Mutex::new(None);
+/// The screens connected peers reported in their handshakes, by device ID.
+static PEER_SCREENS: Mutex<Option<HashMap<String, ScreenInfo>>> = Mutex::new(None);
 static DELTA_THRESHOLD: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(100); // Stores threshold as u32 bits
 
 pub struct MacosInputSource {
@@ -53,12 +71,7 @@ impl MacosInputSource {
             
             // 0. Warp to Center (Nuclear Option)
             // Prevent immediate edge triggering or Hot Corner issues.
-            let (max_width, max_height) = get_display_bounds();
-            let center = core_graphics::geometry::CGPoint { 
-                x: (max_width / 2.0) as f64, 
-                y: (max_height / 2.0) as f64 
-            };
-            let _ = CGWarpMouseCursorPosition(center);
+            let _ = CGWarpMouseCursorPosition(display_center());
 
             // 1. CoreGraphics Cursor Association (The "Freeze" API)
             // true = cursor moves with mouse (Local)
@@ -85,6 +98,12 @@ impl MacosInputSource {
     }
 
     fn update_topology(topology: Topology) {
+        if let Ok(mut cache) = DISPLAY_CACHE.lock() {
+            *cache = None;
+        }
+        if let Ok(mut desktop) = DESKTOP.lock() {
+            *desktop = Some(desktop_for(&topology));
+        }
         if let Ok(mut lock) = TOPOLOGY.lock() {
             *lock = Some(topology);
         }
@@ -100,36 +119,38 @@ impl Drop for MacosInputSource {
 
 
 // Optimization: Refresh display bounds only when needed, not on every mouse tick.
-fn get_display_bounds() -> (f32, f32) {
+/// Origin and size of the bounding box of all displays, in global points. Cursor positions are
+/// normalized against it.
+fn get_display_rect() -> (f32, f32, f32, f32) {
     if let Ok(guard) = DISPLAY_CACHE.lock() {
-        if let Some(bounds) = *guard {
-            return bounds;
+        if let Some(rect) = *guard {
+            return rect;
         }
     }
 
-    unsafe {
-        let mut max_width = 0.0;
-        let mut max_height = 0.0;
-        let mut display_count: u32 = 0;
-        let mut displays = [0u32; 16];
-        if core_graphics::display::CGGetActiveDisplayList(16, displays.as_mut_ptr(), &mut display_count) == 0 {
-            for i in 0..display_count {
-                let bounds = core_graphics::display::CGDisplayBounds(displays[i as usize]);
-                let right = bounds.origin.x + bounds.size.width;
-                let bottom = bounds.origin.y + bounds.size.height;
-                if right > max_width { max_width = right; }
-                if bottom > max_height { max_height = bottom; }
-            }
-        }
-        
-        if max_width > 1.0 && max_height > 1.0 {
+    match super::utils::desktop_bounds() {
+        Some((left, top, width, height)) if width > 1.0 && height > 1.0 => {
+            let rect = (left as f32, top as f32, width as f32, height as f32);
             if let Ok(mut guard) = DISPLAY_CACHE.lock() {
-                *guard = Some((max_width as f32, max_height as f32));
+                *guard = Some(rect);
             }
-            (max_width as f32, max_height as f32)
-        } else {
-            (1920.0, 1080.0) // Fallback
+            rect
         }
+        _ => (0.0, 0.0, 1920.0, 1080.0), // Fallback
+    }
+}
+
+fn get_display_bounds() -> (f32, f32) {
+    let (_, _, width, height) = get_display_rect();
+    (width, height)
+}
+
+/// The global point at the middle of all displays.
+fn display_center() -> core_graphics::geometry::CGPoint {
+    let (left, top, width, height) = get_display_rect();
+    core_graphics::geometry::CGPoint {
+        x: (left + width / 2.0) as f64,
+        y: (top + height / 2.0) as f64,
     }
 }
 
@@ -139,20 +160,204 @@ fn show_notification(_text: &str) {
     // "error 0" (process aborts). Leaving this as a no-op for stability.
 }
 
+/// The characters a key-down types on this machine's layout. `None` for shortcuts (Command or
+/// Control held) and for keys that type nothing printable.
+fn typed_text(event: &CGEvent) -> Option<String> {
+    use core_graphics::event::CGEventFlags;
+    use foreign_types::ForeignType;
+
+    if event.get_flags().intersects(CGEventFlags::CGEventFlagCommand | CGEventFlags::CGEventFlagControl) {
+        return None;
+    }
+    let mut buf = [0u16; 20];
+    let mut len: std::ffi::c_ulong = 0;
+    unsafe {
+        CGEventKeyboardGetUnicodeString(event.as_ptr() as *mut _, buf.len() as _, &mut len, buf.as_mut_ptr());
+    }
+    let text = String::from_utf16_lossy(&buf[..(len as usize).min(buf.len())]);
+    // Arrows and function keys report characters from the private use area (NSUpArrowFunctionKey...)
+    let printable = |c: char| !c.is_control() && !('\u{F700}'..='\u{F8FF}').contains(&c);
+    (!text.is_empty() && text.chars().all(printable)).then_some(text)
+}
+
+fn key_event(etype: CGEventType, event: &CGEvent) -> InputEvent {
+    let key_code = event.get_integer_value_field(9); // kCGKeyboardEventKeycode = 9
+
+    let is_down = if matches!(etype, CGEventType::FlagsChanged) {
+         // For FlagsChanged, we need to check the flags bitmask
+         let flags = event.get_flags();
+         let is_mod = match key_code {
+             54 | 55 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagCommand),
+             56 | 60 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagShift),
+             57 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagAlphaShift),
+             58 | 61 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagAlternate),
+             59 | 62 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagControl),
+             _ => false,
+         };
+         is_mod
+    } else {
+         matches!(etype, CGEventType::KeyDown)
+    };
+
+    let keycode = key_code as u16;
+    InputEvent::Keyboard {
+        usage: crate::keymap::macos_keycode_to_hid(keycode).unwrap_or(0),
+        is_down,
+        hint: Some(KeyHint::Macos { keycode }),
+    }
+}
+
+/// Feeds a hardware key event to the hotkey matcher. Events our own sink posted come from a
+/// private event source and are left out.
+fn check_hotkey(etype: CGEventType, event: &CGEvent) -> HotkeyOutcome {
+    // kCGEventSourceStateID = 45; 1 is kCGEventSourceStateHIDSystemState
+    if event.get_integer_value_field(45) != 1 {
+        return HotkeyOutcome::Pass;
+    }
+    match HOTKEYS.lock() {
+        Ok(mut hotkeys) => hotkeys.get_or_insert_with(|| HotkeyMatcher::new(&Default::default())).process(&key_event(etype, event)),
+        Err(_) => HotkeyOutcome::Pass,
+    }
+}
+
+/// Carries out a hotkey and returns the screen switch it caused, if any, or `None` if the
+/// hotkey had nothing to do and its key should reach the system as usual.
+fn run_hotkey(action: HotkeyAction) -> Option<Option<InputEvent>> {
+    match action {
+        HotkeyAction::ReturnToLocal => {
+            if !IS_REMOTE.load(Ordering::SeqCst) {
+                return None;
+            }
+            MacosInputSource::set_remote_impl(false);
+            show_notification("Returned to Local Control");
+            tracing::trace!("InputSource: Returned to Local Control (hotkey)");
+            Some(Some(InputEvent::ScreenSwitch(platform_passer_core::ScreenSide::Local)))
+        }
+        HotkeyAction::LockToScreen => {
+            SCREEN_LOCK.toggle();
+            Some(None)
+        }
+        HotkeyAction::SwitchToPeer(id) => {
+            let topology = TOPOLOGY.lock().ok()?;
+            if !topology.as_ref()?.remotes.iter().any(|r| r.id == id) {
+                tracing::warn!("InputSource: Hotkey names unknown peer {}", id);
+                return None;
+            }
+            drop(topology);
+            Some(enter_remote(id))
+        }
+        HotkeyAction::CyclePeers => {
+            let active = ACTIVE_REMOTE.lock().ok()?.clone();
+            let topology = TOPOLOGY.lock().ok()?;
+            let next = crate::hotkey::next_peer(&topology.as_ref()?.remotes, active.as_deref()).map(|r| r.id.clone());
+            drop(topology);
+            match next {
+                Some(id) => Some(enter_remote(id)),
+                None => run_hotkey(HotkeyAction::ReturnToLocal),
+            }
+        }
+    }
+}
+
+/// Hands input to the remote with id `id` with the cursor in the middle of its screen.
+fn enter_remote(id: String) -> Option<InputEvent> {
+    if let Ok(mut active) = ACTIVE_REMOTE.lock() { *active = Some(id); }
+    if let Ok(mut vc) = VIRTUAL_CURSOR.lock() { *vc = (0.5, 0.5); }
+    MacosInputSource::set_remote_impl(true);
+    Some(InputEvent::ScreenSwitch(platform_passer_core::ScreenSide::Remote))
+}
+
+/// A crossing found by the virtual desktop: the id of the remote entered (`None` for this
+/// machine), the edge left and the normalized entry point.
+struct Exit {
+    to: Option<String>,
+    edge: Edge,
+    x: f32,
+    y: f32,
+}
+
+/// Where the cursor at normalized `(x, y)` on the remote with id `from` (`None` for this
+/// machine) goes next, if it is against an edge with a screen beyond it.
+fn find_exit(from: Option<&str>, x: f32, y: f32) -> Option<Exit> {
+    let mut desktop = DESKTOP.lock().ok()?;
+    let desktop = desktop.get_or_insert_with(|| desktop_for(&Topology::default()));
+    let crossing = desktop.exit(from, x as f64, y as f64)?;
+    Some(Exit {
+        to: crossing.to.map(|remote| remote.id.clone()),
+        edge: crossing.edge,
+        x: crossing.x as f32,
+        y: crossing.y as f32,
+    })
+}
+
+/// The virtual desktop for `topology`, with the screens peers reported and this machine's
+/// screen as the displays attached now, rather than as configured. Without any remotes, one is
+/// assumed on the left (Backwards comp), the size of the only connected peer's screen, or else
+/// of this one.
+fn desktop_for(topology: &Topology) -> VirtualDesktop {
+    let peers = PEER_SCREENS.lock().ok().and_then(|peers| peers.clone()).unwrap_or_default();
+    let mut topology = topology.clone().with_peer_screens(&peers);
+    if let Some(info) = super::utils::get_screen_info() {
+        topology.local = info;
+    }
+    if topology.remotes.is_empty() {
+        let info = match (peers.len(), peers.values().next()) {
+            (1, Some(reported)) => reported.clone(),
+            _ => ScreenInfo { displays: Vec::new(), ..topology.local.clone() },
+        };
+        topology.remotes.push(RemoteScreen { id: String::new(), position: ScreenPosition::Left, info });
+    }
+    VirtualDesktop::new(&topology, MAINTAIN_ASPECT_RATIO.load(Ordering::SeqCst))
+}
+
+/// Normalized motion on the remote with id `on` for `(dx, dy)` points of motion here, as placed
+/// on the virtual desktop. Falls back to this screen's size.
+fn remote_motion(on: Option<&str>, dx: f32, dy: f32, width: f32, height: f32) -> (f32, f32) {
+    let motion = DESKTOP.lock().ok().and_then(|desktop| {
+        let (d, on) = desktop.as_ref().zip(on)?;
+        d.motion(on, dx as f64, dy as f64)
+    });
+    match motion {
+        Some((x, y)) => (x as f32, y as f32),
+        None => (dx / width, dy / height),
+    }
+}
+
+/// Feeds a cursor sample at normalized `(x, y)` on the remote with id `on` (`None` for this
+/// machine) to the edge guard, `edge` being the switching edge it is against, if any. Returns
+/// whether to switch.
+fn edge_allows(edge: Option<Edge>, on: Option<&str>, x: f32, y: f32, width: f32, height: f32) -> bool {
+    // A remote's edges are measured on its rectangle of the virtual desktop, not this screen
+    let (width, height) = on
+        .and_then(|id| DESKTOP.lock().ok()?.as_ref()?.rect_of(id))
+        .map_or((width as f64, height as f64), |rect| (rect.width, rect.height));
+    let (along, length) = match edge {
+        Some(Edge::Top) | Some(Edge::Bottom) => (x as f64, width),
+        _ => (y as f64, height),
+    };
+    let contact = EdgeContact {
+        edge,
+        along_px: along * length,
+        length_px: length,
+        buttons_held: PRESSED_BUTTONS.load(Ordering::SeqCst) != 0,
+    };
+    match EDGE_GUARD.lock() {
+        Ok(mut guard) => guard.get_or_insert_with(EdgeGuard::default).update(&contact, Instant::now()),
+        Err(_) => false,
+    }
+}
+
 fn handle_event(etype: CGEventType, event: &CGEvent) -> Option<InputEvent> {
-    let mut is_remote = IS_REMOTE.load(Ordering::SeqCst);
-    let (max_width, max_height) = get_display_bounds();
+    let is_remote = IS_REMOTE.load(Ordering::SeqCst);
+    let (origin_x, origin_y, max_width, max_height) = get_display_rect();
 
     match etype {
         CGEventType::MouseMoved | CGEventType::LeftMouseDragged | CGEventType::RightMouseDragged => {
             let point = event.location();
             
             // Normalize absolute position
-            let abs_x = point.x as f32 / max_width;
-            let abs_y = point.y as f32 / max_height;
-
-            // Decision variable: By default follow physical cursor
-            let mut check_x = abs_x;
+            let abs_x = (point.x as f32 - origin_x) / max_width;
+            let abs_y = (point.y as f32 - origin_y) / max_height;
 
             if is_remote {
                 let delta_x = event.get_double_value_field(4) as f32; // kCGMouseEventDeltaX = 4
@@ -164,11 +369,13 @@ fn handle_event(etype: CGEventType, event: &CGEvent) -> Option<InputEvent> {
                      ignore_delta = true;
                 }
 
+                let active = ACTIVE_REMOTE.lock().ok().and_then(|id| id.clone());
+                let (move_x, move_y) = remote_motion(active.as_deref(), delta_x, delta_y, max_width, max_height);
                 if let Ok(mut vc) = VIRTUAL_CURSOR.lock() {
                     if !ignore_delta {
                         // Update virtual coords (normalized)
-                        vc.0 += delta_x / max_width; 
-                        vc.1 += delta_y / max_height;
+                        vc.0 += move_x;
+                        vc.1 += move_y;
                         
                         // Clamp
                         if vc.0 < 0.0 { vc.0 = 0.0; }
@@ -176,157 +383,69 @@ fn handle_event(etype: CGEventType, event: &CGEvent) -> Option<InputEvent> {
                         if vc.1 < 0.0 { vc.1 = 0.0; }
                         if vc.1 > 1.0 { vc.1 = 1.0; }
                     }
-                    
-                    // CRITICAL: Always use Virtual Cursor position for edge checks when Remote.
-                    // This prevents physical cursor movement (due to warp or leaks) from triggering accidental exits.
-                    check_x = vc.0;
                 }
             }
 
-            // --- LIGIC: Windows (Left) - macOS (Right) ---
-
-            // Switch to Remote: Triggered when at macOS LEFT edge
-            // Switch to Remote: Triggered when at macOS LEFT edge
-            // Switch to Remote: Check configured edges
-            let mut triggered_remote = None;
-            if !is_remote {
-                 // Default to Left if no topology (Backwards comp)
-                 let mut checked = false;
-                 if let Ok(guard) = TOPOLOGY.lock() {
-                     if let Some(topo) = &*guard {
-                         if !topo.remotes.is_empty() {
-                            checked = true;
-                            for remote in &topo.remotes {
-                                let hit = match remote.position {
-                                    ScreenPosition::Left => abs_x <= 0.002,
-                                    ScreenPosition::Right => abs_x >= 0.998,
-                                    ScreenPosition::Top => abs_y <= 0.002,
-                                    ScreenPosition::Bottom => abs_y >= 0.998,
-                                };
-                                if hit {
-                                    triggered_remote = Some(remote.clone());
-                                    break;
-                                }
-                            }
-                         }
-                     }
-                 }
-                 
-                 // Fallback: Default Left Edge if config missing or remotes empty
-                 if !checked && abs_x <= 0.002 {
-                     // create dummy remote for fallback
-                     // This is tricky without a real object, but we just set IS_REMOTE.
-                     // We'll set ACTIVE_REMOTE_POS to Left.
-                     if let Ok(mut pos) = ACTIVE_REMOTE_POS.lock() { *pos = Some(ScreenPosition::Left); }
-                     IS_REMOTE.store(true, Ordering::SeqCst);
-                     is_remote = true;
-                     // Init VC at Right Edge (Assuming Left Remote)
-                     if let Ok(mut vc) = VIRTUAL_CURSOR.lock() { *vc = (0.950, abs_y); check_x = vc.0; }
-                     return Some(InputEvent::ScreenSwitch(platform_passer_core::ScreenSide::Remote));
-                 }
-            }
+            // CRITICAL: Always use Virtual Cursor position for edge checks when Remote.
+            // This prevents physical cursor movement (due to warp or leaks) from triggering accidental exits.
+            let (check_x, check_y) = if is_remote {
+                VIRTUAL_CURSOR.lock().map(|vc| *vc).unwrap_or((0.5, 0.5))
+            } else {
+                (abs_x, abs_y)
+            };
+            // No remote named yet: the stand-in `desktop_for` adds, which has an empty id
+            let active = is_remote.then(|| ACTIVE_REMOTE.lock().ok().and_then(|id| id.clone()).unwrap_or_default());
 
-            if let Some(remote) = triggered_remote {
-                IS_REMOTE.store(true, Ordering::SeqCst);
-                is_remote = true;
-                
-                // Store active position
-                if let Ok(mut pos) = ACTIVE_REMOTE_POS.lock() { *pos = Some(remote.position.clone()); }
-
-                // Determine entry point on REMOTE screen
-                // If we exit Local Left -> Enter Remote Right (x=0.95)
-                // If we exit Local Right -> Enter Remote Left (x=0.05)
-                // If we exit Local Top -> Enter Remote Bottom (y=0.95)
-                // If we exit Local Bottom -> Enter Remote Top (y=0.05)
-                let (entry_x, entry_y) = match remote.position {
-                    ScreenPosition::Left => (0.950, abs_y),
-                    ScreenPosition::Right => (0.050, abs_y),
-                    ScreenPosition::Top => (abs_x, 0.950),
-                    ScreenPosition::Bottom => (abs_x, 0.050),
-                };
+            let mut exit = if SCREEN_LOCK.is_locked() { None } else { find_exit(active.as_deref(), check_x, check_y) };
+            if !edge_allows(exit.as_ref().map(|e| e.edge), active.as_deref(), check_x, check_y, max_width, max_height) {
+                exit = None;
+            }
 
-                if let Ok(mut vc) = VIRTUAL_CURSOR.lock() {
-                    *vc = (entry_x, entry_y);
+            match exit {
+                Some(Exit { to: Some(id), x, y, .. }) => {
+                    // Into a remote, from this machine or from the remote beside it
+                    if let Ok(mut active) = ACTIVE_REMOTE.lock() { *active = Some(id); }
+                    if let Ok(mut vc) = VIRTUAL_CURSOR.lock() { *vc = (x, y); }
+                    IS_REMOTE.store(true, Ordering::SeqCst);
+                    return Some(InputEvent::ScreenSwitch(platform_passer_core::ScreenSide::Remote));
                 }
-                
-                return Some(InputEvent::ScreenSwitch(platform_passer_core::ScreenSide::Remote));
-            }
-            
-            // Return to Local
-            if is_remote {
-                let active_pos = if let Ok(guard) = ACTIVE_REMOTE_POS.lock() { guard.clone().unwrap_or(ScreenPosition::Left) } else { ScreenPosition::Left };
-                
-                let should_return = match active_pos {
-                    ScreenPosition::Left => check_x >= 0.998, // Remote is on Left, so we return when Remote Cursor hits Right
-                    ScreenPosition::Right => check_x <= 0.002, // Remote is on Right, return when Remote Cursor hits Left
-                    ScreenPosition::Top => { 
-                         // Check Y. We need check_y?
-                         // Current logic uses check_x for everything. We need to check Y for Top/Bottom!
-                         // But we calculated vc in handle_event start. We need access to vc.1.
-                         // check_x was set to vc.0.
-                         // Let's get y from VIRTUAL_CURSOR lock again or assume we need to change check var.
-                         // Hack: Read vc again.
-                         let vc_y = if let Ok(vc) = VIRTUAL_CURSOR.lock() { vc.1 } else { 0.5 };
-                         vc_y >= 0.998
-                    },
-                    ScreenPosition::Bottom => {
-                         let vc_y = if let Ok(vc) = VIRTUAL_CURSOR.lock() { vc.1 } else { 0.5 };
-                         vc_y <= 0.002
-                    },
-                };
-
-                if should_return {
-                    // Restore to appropriate edge
-                    let (bounds_w, bounds_h) = get_display_bounds();
-                    let (ret_x, ret_y) = match active_pos {
-                        ScreenPosition::Left => (10.0, if let Ok(vc) = VIRTUAL_CURSOR.lock() { vc.1 } else { 0.5 } * bounds_h),
-                        ScreenPosition::Right => (bounds_w - 10.0, if let Ok(vc) = VIRTUAL_CURSOR.lock() { vc.1 } else { 0.5 } * bounds_h),
-                        ScreenPosition::Top => (if let Ok(vc) = VIRTUAL_CURSOR.lock() { vc.0 } else { 0.5 } * bounds_w, 10.0),
-                        ScreenPosition::Bottom => (if let Ok(vc) = VIRTUAL_CURSOR.lock() { vc.0 } else { 0.5 } * bounds_w, bounds_h - 10.0),
+                Some(Exit { to: None, x, y, .. }) => {
+                    // Back to this machine, just inside the edge the cursor came through
+                    let edge_pos = core_graphics::geometry::CGPoint {
+                        x: (origin_x + x * max_width) as f64,
+                        y: (origin_y + y * max_height) as f64,
                     };
 
-                    let edge_pos = core_graphics::geometry::CGPoint { 
-                        x: ret_x as f64, 
-                        y: ret_y as f64
-                    };
+                    // CRITICAL: Call set_remote(false) FIRST to re-associate.
+                    // THEN warp. If we warp while disassociated, the re-association might snap back to the "Center Lock" hardware position.
+                    MacosInputSource::set_remote_impl(false);
 
-                // CRITICAL: Call set_remote(false) FIRST to re-associate.
-                // THEN warp. If we warp while disassociated, the re-association might snap back to the "Center Lock" hardware position.
-                MacosInputSource::set_remote_impl(false);
-                
-                unsafe {
-                    let _ = CGWarpMouseCursorPosition(edge_pos);
-                }
+                    unsafe {
+                        let _ = CGWarpMouseCursorPosition(edge_pos);
+                    }
 
-                is_remote = false;
-                
-                return Some(InputEvent::ScreenSwitch(platform_passer_core::ScreenSide::Local));
-            }
+                    return Some(InputEvent::ScreenSwitch(platform_passer_core::ScreenSide::Local));
+                }
+                None => {}
             }
 
             if !is_remote { return None; }
 
-            // Route correct coordinate
-            let mut final_x = abs_x;
-            let mut final_y = abs_y;
-            
-            if is_remote {
-                 if let Ok(vc) = VIRTUAL_CURSOR.lock() {
-                     final_x = vc.0;
-                     final_y = vc.1;
-                 }
-            }
-
-            Some(InputEvent::MouseMove { x: final_x, y: final_y })
+            Some(InputEvent::MouseMove { x: check_x, y: check_y })
         }
         CGEventType::LeftMouseDown | CGEventType::LeftMouseUp |
         CGEventType::RightMouseDown | CGEventType::RightMouseUp |
         CGEventType::OtherMouseDown | CGEventType::OtherMouseUp => {
-            let button_bit = match etype {
-                CGEventType::LeftMouseDown | CGEventType::LeftMouseUp => 1,
-                CGEventType::RightMouseDown | CGEventType::RightMouseUp => 2,
-                _ => 4,
+            let button = match etype {
+                CGEventType::LeftMouseDown | CGEventType::LeftMouseUp => platform_passer_core::MouseButton::Left,
+                CGEventType::RightMouseDown | CGEventType::RightMouseUp => platform_passer_core::MouseButton::Right,
+                _ => {
+                    // kCGMouseEventButtonNumber = 3: 2 is middle, 3 and 4 the thumb buttons
+                    let number = event.get_integer_value_field(3);
+                    platform_passer_core::MouseButton::from_index(number.clamp(2, u8::MAX as i64) as u8)
+                }
             };
+            let button_bit = 1u8.checked_shl(button.index() as u32).unwrap_or(0);
             let is_down = matches!(etype, CGEventType::LeftMouseDown | CGEventType::RightMouseDown | CGEventType::OtherMouseDown);
             
             if is_down {
@@ -336,61 +455,43 @@ fn handle_event(etype: CGEventType, event: &CGEvent) -> Option<InputEvent> {
             }
 
             if !is_remote { return None; }
-            let button = match button_bit {
-                1 => platform_passer_core::MouseButton::Left,
-                2 => platform_passer_core::MouseButton::Right,
-                _ => platform_passer_core::MouseButton::Middle,
-            };
             tracing::trace!("InputSource: Mouse Button {:?} {}", button, if is_down { "Down" } else { "Up" });
             Some(InputEvent::MouseButton { button, is_down })
         }
         CGEventType::KeyDown | CGEventType::KeyUp | CGEventType::FlagsChanged => {
-            let key_code = event.get_integer_value_field(9); // kCGKeyboardEventKeycode = 9
-            
-            // Check for hotkey to return to local (e.g., Command + Escape)
-            // Allow this even if remote (it's the escape hatch)
-            if is_remote && key_code == 53 { // Escape
-                 MacosInputSource::set_remote_impl(false);
-                 show_notification("Returned to Local Control (Escape)");
-                 tracing::trace!("InputSource: Returned to Local Control (Escape)");
-                 return Some(InputEvent::ScreenSwitch(platform_passer_core::ScreenSide::Local));
-            }
-
             if !is_remote { return None; }
-
-            let is_down = if matches!(etype, CGEventType::FlagsChanged) {
-                 // For FlagsChanged, we need to check the flags bitmask
-                 let flags = event.get_flags();
-                 let is_mod = match key_code {
-                     54 | 55 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagCommand),
-                     56 | 60 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagShift),
-                     57 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagAlphaShift),
-                     58 | 61 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagAlternate),
-                     59 | 62 => flags.contains(core_graphics::event::CGEventFlags::CGEventFlagControl),
-                     _ => false,
-                 };
-                 is_mod
-            } else {
-                 matches!(etype, CGEventType::KeyDown)
-            };
-
-            let win_vk = crate::keymap::macos_to_windows_vk(key_code as u32);
-            Some(InputEvent::Keyboard {
-                key_code: win_vk,
-                is_down,
-            })
+            Some(key_event(etype, event))
         }
         CGEventType::ScrollWheel => {
             if !is_remote { return None; }
-            // kCGScrollWheelEventDeltaAxis1 = 11 (Vertical, Y)
-            // kCGScrollWheelEventDeltaAxis2 = 12 (Horizontal, X)
-            let dy = event.get_integer_value_field(11); 
-            let dx = event.get_integer_value_field(12);
-            
+            // kCGScrollWheelEventIsContinuous = 88 (trackpad / Magic Mouse)
+            let continuous = event.get_integer_value_field(88) != 0;
+            let (dx, dy, unit) = if continuous {
+                // kCGScrollWheelEventPointDeltaAxis1/2 = 96/97 (pixels)
+                let dy = event.get_integer_value_field(96) as f32;
+                let dx = event.get_integer_value_field(97) as f32;
+                (dx, dy, ScrollUnit::Pixel)
+            } else {
+                // kCGScrollWheelEventFixedPtDeltaAxis1/2 = 93/94 (fractional lines)
+                let dy = event.get_double_value_field(93) as f32;
+                let dx = event.get_double_value_field(94) as f32;
+                (dx, dy, ScrollUnit::Line)
+            };
+
+            // kCGScrollWheelEventScrollPhase = 99, kCGScrollWheelEventMomentumPhase = 123
+            let phase = match (event.get_integer_value_field(99), event.get_integer_value_field(123)) {
+                (1, _) | (128, _) => ScrollPhase::Began,
+                (2, _) => ScrollPhase::Changed,
+                (4, _) | (8, _) => ScrollPhase::Ended,
+                (_, 3) => ScrollPhase::MomentumEnded,
+                (_, 0) => ScrollPhase::None,
+                _ => ScrollPhase::Momentum,
+            };
+
             let reverse = SCROLL_REVERSE.load(Ordering::SeqCst);
-            let dy_val = if reverse { -dy } else { dy };
+            let dy = if reverse { -dy } else { dy };
 
-            Some(InputEvent::Scroll { dx: dx as f32, dy: dy_val as f32 })
+            Some(InputEvent::SmoothScroll { dx, dy, unit, phase })
         }
         _ => None,
     }
@@ -460,8 +561,41 @@ impl InputSource for MacosInputSource {
                             None
                         }
                         _ => {
+                            if matches!(etype, CGEventType::KeyDown | CGEventType::KeyUp | CGEventType::FlagsChanged) {
+                                let swallow = match check_hotkey(etype, event) {
+                                    HotkeyOutcome::Pass => false,
+                                    HotkeyOutcome::Swallow => true,
+                                    HotkeyOutcome::Triggered(action) => match run_hotkey(action) {
+                                        Some(switch) => {
+                                            if let Some(switch) = switch {
+                                                callback_arc(switch);
+                                            }
+                                            true
+                                        }
+                                        None => {
+                                            if let (Ok(mut hotkeys), InputEvent::Keyboard { usage, .. }) = (HOTKEYS.lock(), key_event(etype, event)) {
+                                                if let Some(hotkeys) = hotkeys.as_mut() { hotkeys.forward(usage); }
+                                            }
+                                            false
+                                        }
+                                    },
+                                };
+                                if swallow {
+                                    // The chord key reaches neither this machine nor the peer
+                                    event.set_type(CGEventType::Null);
+                                    return Some(event.to_owned());
+                                }
+                            }
+
                             let was_remote_initially = IS_REMOTE.load(Ordering::SeqCst);
 
+                            // Text goes out right before its key-down; peers keep one or the other
+                            if was_remote_initially && matches!(etype, CGEventType::KeyDown) {
+                                if let Some(text) = typed_text(event) {
+                                    callback_arc(InputEvent::Text(text));
+                                }
+                            }
+
                             // Process event logic (extraction, sending to client)
                             let handled_ev = handle_event(etype, event);
                             if let Some(ev) = handled_ev {
@@ -484,13 +618,8 @@ impl InputSource for MacosInputSource {
                                     CGEventType::MouseMoved | CGEventType::LeftMouseDragged | CGEventType::RightMouseDragged => {
                                         // Movement: WARP-LOCK
                                         // CGAssociate is unreliable. We must physically hold the cursor in place.
-                                        let (max_width, max_height) = get_display_bounds();
-                                        let center = core_graphics::geometry::CGPoint { 
-                                            x: (max_width / 2.0) as f64, 
-                                            y: (max_height / 2.0) as f64 
-                                        };
                                         unsafe {
-                                            let _ = CGWarpMouseCursorPosition(center);
+                                            let _ = CGWarpMouseCursorPosition(display_center());
                                         }
 
                                         // Swallow the event (None) since we are warping.
@@ -587,7 +716,60 @@ impl InputSource for MacosInputSource {
         Ok(())
     }
 
+    fn set_screen_lock(&self, locked: bool) -> Result<()> {
+        SCREEN_LOCK.set(locked);
+        Ok(())
+    }
+
+    fn screen_locked(&self) -> bool {
+        SCREEN_LOCK.is_locked()
+    }
+
+    fn on_screen_lock_changed(&self, listener: Box<dyn Fn(bool) + Send + Sync>) {
+        SCREEN_LOCK.set_listener(listener);
+    }
+
+    fn set_peer_screen(&self, id: &str, screen: Option<ScreenInfo>) -> Result<()> {
+        if let Ok(mut peers) = PEER_SCREENS.lock() {
+            let peers = peers.get_or_insert_with(HashMap::new);
+            match screen {
+                Some(screen) => peers.insert(id.to_string(), screen),
+                None => peers.remove(id),
+            };
+        }
+        let topology = TOPOLOGY.lock().ok().and_then(|topology| topology.clone()).unwrap_or_default();
+        let desktop = desktop_for(&topology);
+        if let Ok(mut lock) = DESKTOP.lock() {
+            *lock = Some(desktop);
+        }
+        Ok(())
+    }
+
+    fn active_peer(&self) -> Option<String> {
+        // The stand-in remote of a topology without remotes has no id
+        let id = ACTIVE_REMOTE.lock().ok()?.clone()?;
+        (IS_REMOTE.load(Ordering::SeqCst) && !id.is_empty()).then_some(id)
+    }
+
+    fn modifier_state(&self) -> Option<ModifierState> {
+        // The hardware state: the tap swallows remote-bound keys before the session sees them
+        Some(super::utils::modifier_state(1))
+    }
+
     fn update_config(&self, config: AppConfig) -> Result<()> {
+        if let Ok(mut hotkeys) = HOTKEYS.lock() {
+            match hotkeys.as_mut() {
+                Some(matcher) => matcher.set_config(&config.hotkeys),
+                None => *hotkeys = Some(HotkeyMatcher::new(&config.hotkeys)),
+            }
+        }
+        if let Ok(mut edge_guard) = EDGE_GUARD.lock() {
+            match edge_guard.as_mut() {
+                Some(edge_guard) => edge_guard.set_config(config.input.edge_guards),
+                None => *edge_guard = Some(EdgeGuard::new(config.input.edge_guards)),
+            }
+        }
+        MAINTAIN_ASPECT_RATIO.store(config.input.maintain_aspect_ratio, Ordering::SeqCst);
         MacosInputSource::update_topology(config.topology);
         SCROLL_REVERSE.store(config.input.scroll_reverse, Ordering::SeqCst);
         
@@ -1,5 +1,7 @@
-use platform_passer_core::config::ScreenInfo;
-use core_graphics::display::{CGMainDisplayID, CGDisplayBounds, CGDisplayPixelsWide};
+use anyhow::{anyhow, Result};
+use platform_passer_core::config::{DisplayInfo, ScreenInfo};
+use platform_passer_core::ModifierState;
+use core_graphics::display::{CGGetActiveDisplayList, CGMainDisplayID, CGDisplayBounds, CGDisplayPixelsWide};
 
 /// Checks if the current process has accessibility permissions.
 pub fn is_accessibility_trusted() -> bool {
@@ -16,32 +18,130 @@ pub fn get_accessibility_guidance() -> &'static str {
     "Please enable Accessibility permissions for this app in 'System Settings > Privacy & Security > Accessibility'."
 }
 
-/// Fetches the main display's information (resolution and DPI).
+/// Fetches every active display's bounds and DPI. The screen spans their bounding box; its
+/// scale is the main display's.
 pub fn get_screen_info() -> Option<ScreenInfo> {
     unsafe {
-        let display_id = CGMainDisplayID();
-        let bounds = CGDisplayBounds(display_id);
-        
-        let width_points = bounds.size.width as u32;
-        let height_points = bounds.size.height as u32;
-        
-        // Physical pixels (Raw/Native resolution)
-        let width_pixels = CGDisplayPixelsWide(display_id) as u32;
-        
-        // Calculate backing scale factor (DPI)
-        let dpi_scale = if width_points > 0 {
-            width_pixels as f32 / width_points as f32
-        } else {
-            1.0
-        };
-        
-        // Use 1.0 minimum to avoid div by zero issues downstream
-        let final_scale = if dpi_scale < 1.0 { 1.0 } else { dpi_scale };
+        let main_display = CGMainDisplayID();
+        let mut display_count: u32 = 0;
+        let mut ids = [0u32; 16];
+        if CGGetActiveDisplayList(ids.len() as u32, ids.as_mut_ptr(), &mut display_count) != 0 || display_count == 0 {
+            ids[0] = main_display;
+            display_count = 1;
+        }
+
+        let displays: Vec<DisplayInfo> = ids[..display_count as usize]
+            .iter()
+            .map(|&display_id| {
+                let bounds = CGDisplayBounds(display_id);
+                DisplayInfo {
+                    x: bounds.origin.x as i32,
+                    y: bounds.origin.y as i32,
+                    width: bounds.size.width as u32, // We report logical points for cursor mapping
+                    height: bounds.size.height as u32,
+                    dpi_scale: backing_scale(display_id, bounds.size.width as u32),
+                    primary: display_id == main_display,
+                }
+            })
+            .collect();
+
+        let left = displays.iter().map(|d| d.x).min().unwrap_or(0);
+        let top = displays.iter().map(|d| d.y).min().unwrap_or(0);
+        let right = displays.iter().map(|d| d.x + d.width as i32).max().unwrap_or(0);
+        let bottom = displays.iter().map(|d| d.y + d.height as i32).max().unwrap_or(0);
+        let dpi_scale = displays.iter().find(|d| d.primary).map_or(1.0, |d| d.dpi_scale);
 
         Some(ScreenInfo {
-            width: width_points, // We report logical points for cursor mapping
-            height: height_points,
-            dpi_scale: final_scale,
+            width: (right - left).max(0) as u32,
+            height: (bottom - top).max(0) as u32,
+            dpi_scale,
+            displays,
         })
     }
 }
+
+/// Top-left corner and size of the bounding box of all displays, in global points: the area
+/// `get_screen_info` describes, which peers normalize cursor positions against.
+pub fn desktop_bounds() -> Option<(f64, f64, f64, f64)> {
+    let info = get_screen_info()?;
+    let left = info.displays.iter().map(|d| d.x).min().unwrap_or(0);
+    let top = info.displays.iter().map(|d| d.y).min().unwrap_or(0);
+    Some((left as f64, top as f64, info.width as f64, info.height as f64))
+}
+
+/// Physical pixels per logical point of a display.
+unsafe fn backing_scale(display_id: u32, width_points: u32) -> f32 {
+    // Physical pixels (Raw/Native resolution)
+    let width_pixels = CGDisplayPixelsWide(display_id) as u32;
+    let dpi_scale = if width_points > 0 {
+        width_pixels as f32 / width_points as f32
+    } else {
+        1.0
+    };
+    // Use 1.0 minimum to avoid div by zero issues downstream
+    dpi_scale.max(1.0)
+}
+
+/// Device-dependent `CGEventFlags` bits for each modifier, in `ModifierState::held` bit order.
+const DEVICE_MODIFIER_FLAGS: [u64; 8] = [
+    0x0001, // NX_DEVICELCTLKEYMASK
+    0x0002, // NX_DEVICELSHIFTKEYMASK
+    0x0020, // NX_DEVICELALTKEYMASK
+    0x0008, // NX_DEVICELCMDKEYMASK
+    0x2000, // NX_DEVICERCTLKEYMASK
+    0x0004, // NX_DEVICERSHIFTKEYMASK
+    0x0040, // NX_DEVICERALTKEYMASK
+    0x0010, // NX_DEVICERCMDKEYMASK
+];
+/// kCGEventFlagMaskAlphaShift
+const ALPHA_SHIFT_FLAG: u64 = 0x10000;
+/// kIOHIDCapsLockState
+const CAPS_LOCK_STATE: i32 = 1;
+/// kIOHIDParamConnectType
+const HID_PARAM_CONNECT_TYPE: u32 = 1;
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    static mach_task_self_: u32;
+    fn IOServiceMatching(name: *const std::ffi::c_char) -> *mut std::ffi::c_void;
+    fn IOServiceGetMatchingService(main_port: u32, matching: *mut std::ffi::c_void) -> u32;
+    fn IOServiceOpen(service: u32, owning_task: u32, connect_type: u32, connect: *mut u32) -> i32;
+    fn IOServiceClose(connect: u32) -> i32;
+    fn IOObjectRelease(object: u32) -> i32;
+    fn IOHIDSetModifierLockState(connect: u32, selector: i32, state: bool) -> i32;
+}
+
+/// Held modifiers and caps lock for an event source state: 0 is the combined session state,
+/// 1 the hardware state, which still sees keys a tap has swallowed. Macs have no num or scroll lock.
+pub fn modifier_state(state_id: i32) -> ModifierState {
+    let flags = unsafe { CGEventSourceFlagsState(state_id) };
+    let mut state = ModifierState { caps_lock: flags & ALPHA_SHIFT_FLAG != 0, ..ModifierState::default() };
+    for (usage, mask) in ModifierState::MODIFIER_USAGES.zip(DEVICE_MODIFIER_FLAGS) {
+        state.set_held(usage, flags & mask != 0);
+    }
+    state
+}
+
+/// Turns caps lock on or off. Posting the caps lock key does not toggle it on macOS, so this
+/// goes through the HID system instead.
+pub fn set_caps_lock(on: bool) -> Result<()> {
+    unsafe {
+        let service = IOServiceGetMatchingService(0, IOServiceMatching(b"IOHIDSystem\0".as_ptr().cast()));
+        if service == 0 {
+            return Err(anyhow!("IOHIDSystem service not found"));
+        }
+        let mut connect = 0;
+        let opened = IOServiceOpen(service, mach_task_self_, HID_PARAM_CONNECT_TYPE, &mut connect);
+        IOObjectRelease(service);
+        if opened != 0 {
+            return Err(anyhow!("Failed to open IOHIDSystem: {:#x}", opened));
+        }
+        let result = IOHIDSetModifierLockState(connect, CAPS_LOCK_STATE, on);
+        IOServiceClose(connect);
+        if result != 0 {
+            return Err(anyhow!("Failed to set caps lock: {:#x}", result));
+        }
+    }
+    Ok(())
+}
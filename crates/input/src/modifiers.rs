@@ -0,0 +1,41 @@
+//! Bringing a sink's modifier and lock-key state in line with a `ModifierState` snapshot.
+use platform_passer_core::{InputEvent, ModifierState, ScreenSide};
+
+/// HID usages of the lock keys.
+pub const CAPS_LOCK: u16 = 0x39;
+pub const NUM_LOCK: u16 = 0x53;
+pub const SCROLL_LOCK: u16 = 0x47;
+
+/// Whether a source event calls for a fresh snapshot to follow it: entering the remote screen,
+/// or releasing a lock key (by then the peer has acted on the key-down).
+pub fn snapshot_due(event: &InputEvent) -> bool {
+    match event {
+        InputEvent::ScreenSwitch(ScreenSide::Remote) => true,
+        InputEvent::Keyboard { usage, is_down: false, .. } => matches!(*usage, CAPS_LOCK | NUM_LOCK | SCROLL_LOCK),
+        _ => false,
+    }
+}
+
+/// Key events that take a system from `current` to `target`: modifiers are pressed or released,
+/// lock keys that differ are tapped once.
+pub fn reconcile(current: &ModifierState, target: &ModifierState) -> Vec<InputEvent> {
+    let key = |usage, is_down| InputEvent::Keyboard { usage, is_down, hint: None };
+    let mut events = Vec::new();
+    for usage in ModifierState::MODIFIER_USAGES {
+        let held = target.is_held(usage);
+        if current.is_held(usage) != held {
+            events.push(key(usage, held));
+        }
+    }
+    for (usage, now, wanted) in [
+        (CAPS_LOCK, current.caps_lock, target.caps_lock),
+        (NUM_LOCK, current.num_lock, target.num_lock),
+        (SCROLL_LOCK, current.scroll_lock, target.scroll_lock),
+    ] {
+        if now != wanted {
+            events.push(key(usage, true));
+            events.push(key(usage, false));
+        }
+    }
+    events
+}
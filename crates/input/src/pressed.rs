@@ -0,0 +1,71 @@
+//! Keys and buttons a sink has pressed on the local system and not yet released.
+use platform_passer_core::{InputEvent, KeyHint, MouseButton};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pressed {
+    Key { usage: u16, hint: Option<KeyHint> },
+    KeyVk(u32),
+    Button(MouseButton),
+}
+
+impl Pressed {
+    fn from_event(event: &InputEvent) -> Option<(Self, bool)> {
+        match *event {
+            InputEvent::Keyboard { usage, is_down, hint } => Some((Pressed::Key { usage, hint }, is_down)),
+            InputEvent::KeyboardVk { key_code, is_down } => Some((Pressed::KeyVk(key_code), is_down)),
+            InputEvent::MouseButton { button, is_down } => Some((Pressed::Button(button), is_down)),
+            _ => None,
+        }
+    }
+
+    /// Keys with a known usage match on it alone; the hint may differ between down and up.
+    fn same_as(&self, other: &Pressed) -> bool {
+        match (self, other) {
+            (Pressed::Key { usage: a, .. }, Pressed::Key { usage: b, .. }) if *a != 0 => a == b,
+            _ => self == other,
+        }
+    }
+
+    fn release(self) -> InputEvent {
+        match self {
+            Pressed::Key { usage, hint } => InputEvent::Keyboard { usage, is_down: false, hint },
+            Pressed::KeyVk(key_code) => InputEvent::KeyboardVk { key_code, is_down: false },
+            Pressed::Button(button) => InputEvent::MouseButton { button, is_down: false },
+        }
+    }
+}
+
+/// Records the key and button presses a sink injects, so exactly those can be released when
+/// the controlling peer goes away mid-chord.
+#[derive(Default)]
+pub struct PressedStateTracker {
+    /// In press order.
+    pressed: Vec<Pressed>,
+}
+
+impl PressedStateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notes a key or button event about to be injected. Other events are ignored.
+    pub fn record(&mut self, event: &InputEvent) {
+        let Some((entry, is_down)) = Pressed::from_event(event) else {
+            return;
+        };
+        // Repeats do not add a second entry, and an up clears the key however it went down
+        self.pressed.retain(|p| !p.same_as(&entry));
+        if is_down {
+            self.pressed.push(entry);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pressed.is_empty()
+    }
+
+    /// Forgets everything held and returns the events that release it, most recent press first.
+    pub fn release_all(&mut self) -> Vec<InputEvent> {
+        self.pressed.drain(..).rev().map(Pressed::release).collect()
+    }
+}
@@ -0,0 +1,60 @@
+//! Pinning the cursor to the current screen.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+type LockListener = Box<dyn Fn(bool) + Send + Sync>;
+
+/// Whether edges may switch screens, shared by a source's hooks, its hotkeys and the session.
+///
+/// While locked, edge-triggered `ScreenSwitch` is suppressed in both directions. Hotkeys and
+/// explicit switches still work.
+pub struct ScreenLock {
+    locked: AtomicBool,
+    listener: Mutex<Option<LockListener>>,
+}
+
+impl ScreenLock {
+    pub const fn new() -> Self {
+        Self { locked: AtomicBool::new(false), listener: Mutex::new(None) }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
+    }
+
+    /// Sets the lock, telling the listener if that changed it.
+    pub fn set(&self, locked: bool) {
+        if self.locked.swap(locked, Ordering::SeqCst) != locked {
+            self.notify(locked);
+        }
+    }
+
+    /// Flips the lock and returns the new state.
+    pub fn toggle(&self) -> bool {
+        let locked = !self.locked.fetch_xor(true, Ordering::SeqCst);
+        self.notify(locked);
+        locked
+    }
+
+    /// Called with the new state on every change, from whichever thread made it.
+    pub fn set_listener(&self, listener: LockListener) {
+        if let Ok(mut guard) = self.listener.lock() {
+            *guard = Some(listener);
+        }
+    }
+
+    fn notify(&self, locked: bool) {
+        tracing::info!("InputSource: Screen lock {}", if locked { "on" } else { "off" });
+        if let Ok(guard) = self.listener.lock() {
+            if let Some(listener) = &*guard {
+                listener(locked);
+            }
+        }
+    }
+}
+
+impl Default for ScreenLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,44 @@
+use platform_passer_core::{ScrollPhase, ScrollUnit};
+
+/// Turns fractional scroll deltas into whole steps for sinks that cannot post fractions.
+///
+/// The sink picks its resolution in steps per line: 1.0 for classic wheel notches,
+/// 120.0 for Windows `WHEEL_DELTA` units, `PIXELS_PER_LINE` for pixel-based posting.
+/// Whatever does not add up to a whole step is carried into the next event of the same
+/// gesture, so slow trackpad movement still scrolls instead of rounding to zero.
+pub struct ScrollAccumulator {
+    steps_per_line: f32,
+    rem_x: f32,
+    rem_y: f32,
+}
+
+impl ScrollAccumulator {
+    pub fn new(steps_per_line: f32) -> Self {
+        Self { steps_per_line, rem_x: 0.0, rem_y: 0.0 }
+    }
+
+    /// Adds a delta and returns the whole `(x, y)` steps now due.
+    pub fn accumulate(&mut self, dx: f32, dy: f32, unit: ScrollUnit, phase: ScrollPhase) -> (i32, i32) {
+        if phase == ScrollPhase::Began {
+            self.reset();
+        }
+        self.rem_x += unit.to_lines(dx) * self.steps_per_line;
+        self.rem_y += unit.to_lines(dy) * self.steps_per_line;
+
+        // Truncate toward zero so the remainder keeps the sign of the motion
+        let steps_x = self.rem_x.trunc();
+        let steps_y = self.rem_y.trunc();
+        self.rem_x -= steps_x;
+        self.rem_y -= steps_y;
+
+        if phase.is_end() {
+            self.reset();
+        }
+        (steps_x as i32, steps_y as i32)
+    }
+
+    pub fn reset(&mut self) {
+        self.rem_x = 0.0;
+        self.rem_y = 0.0;
+    }
+}
@@ -0,0 +1,134 @@
+//! Character-based typing: choosing text or physical keys per peer, and an ASCII fallback
+//! for sinks that cannot inject Unicode.
+use platform_passer_core::config::LayoutMode;
+use platform_passer_core::{Frame, InputEvent};
+
+/// HID usage of Left Shift, pressed around shifted characters.
+const LEFT_SHIFT: u16 = 0xE1;
+
+/// Picks between the `Text` events and the physical key events a source emits, for one peer.
+///
+/// Sources emit `InputEvent::Text` immediately before the key-down that produced it. In
+/// `Physical` mode the text is dropped. In `Characters` mode the text is kept and that key's
+/// down, repeats and up are dropped instead.
+pub struct LayoutFilter {
+    mode: LayoutMode,
+    text_pending: bool,
+    /// Keys whose events are being replaced by text, until they are released, even if the
+    /// mode changes in between: the peer never saw them go down.
+    suppressed: Vec<u16>,
+}
+
+impl LayoutFilter {
+    pub fn new(mode: LayoutMode) -> Self {
+        Self { mode, text_pending: false, suppressed: Vec::new() }
+    }
+
+    pub fn mode(&self) -> LayoutMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: LayoutMode) {
+        self.mode = mode;
+        self.text_pending = false;
+    }
+
+    /// Returns `frame` as it should be sent to this peer, or `None` to skip it.
+    pub fn filter(&mut self, frame: Frame) -> Option<Frame> {
+        let Frame::Input(input) = frame else {
+            return Some(frame);
+        };
+        let keep = match (&input.event, self.mode) {
+            (InputEvent::Text(_), LayoutMode::Physical) => false,
+            (InputEvent::Text(_), LayoutMode::Characters) => {
+                self.text_pending = true;
+                true
+            }
+            (InputEvent::Keyboard { usage, is_down: true, .. }, _) if self.text_pending => {
+                self.text_pending = false;
+                if !self.suppressed.contains(usage) {
+                    self.suppressed.push(*usage);
+                }
+                false
+            }
+            (InputEvent::Keyboard { usage, is_down, .. }, _) if self.suppressed.contains(usage) => {
+                if !is_down {
+                    self.suppressed.retain(|u| u != usage);
+                }
+                false
+            }
+            _ => true,
+        };
+
+        keep.then_some(Frame::Input(input))
+    }
+}
+
+/// Key events that type `text` on a US layout, for sinks without a Unicode injection path.
+///
+/// Characters outside printable ASCII, newline and tab are skipped.
+pub fn ascii_key_events(text: &str) -> Vec<InputEvent> {
+    let key = |usage, is_down| InputEvent::Keyboard { usage, is_down, hint: None };
+    let mut events = Vec::new();
+    for ch in text.chars() {
+        let Some((usage, shift)) = ascii_usage(ch) else {
+            continue;
+        };
+        if shift {
+            events.push(key(LEFT_SHIFT, true));
+        }
+        events.push(key(usage, true));
+        events.push(key(usage, false));
+        if shift {
+            events.push(key(LEFT_SHIFT, false));
+        }
+    }
+    events
+}
+
+/// HID usage of the US-layout key that types `ch`, and whether Shift is needed.
+pub fn ascii_usage(ch: char) -> Option<(u16, bool)> {
+    let key = match ch {
+        'a'..='z' => (0x04 + (ch as u16 - 'a' as u16), false),
+        'A'..='Z' => (0x04 + (ch as u16 - 'A' as u16), true),
+        '1'..='9' => (0x1E + (ch as u16 - '1' as u16), false),
+        '0' => (0x27, false),
+        '!' => (0x1E, true),
+        '@' => (0x1F, true),
+        '#' => (0x20, true),
+        '$' => (0x21, true),
+        '%' => (0x22, true),
+        '^' => (0x23, true),
+        '&' => (0x24, true),
+        '*' => (0x25, true),
+        '(' => (0x26, true),
+        ')' => (0x27, true),
+        '\n' => (0x28, false),
+        '\t' => (0x2B, false),
+        ' ' => (0x2C, false),
+        '-' => (0x2D, false),
+        '_' => (0x2D, true),
+        '=' => (0x2E, false),
+        '+' => (0x2E, true),
+        '[' => (0x2F, false),
+        '{' => (0x2F, true),
+        ']' => (0x30, false),
+        '}' => (0x30, true),
+        '\\' => (0x31, false),
+        '|' => (0x31, true),
+        ';' => (0x33, false),
+        ':' => (0x33, true),
+        '\'' => (0x34, false),
+        '"' => (0x34, true),
+        '`' => (0x35, false),
+        '~' => (0x35, true),
+        ',' => (0x36, false),
+        '<' => (0x36, true),
+        '.' => (0x37, false),
+        '>' => (0x37, true),
+        '/' => (0x38, false),
+        '?' => (0x38, true),
+        _ => return None,
+    };
+    Some(key)
+}
@@ -1,20 +1,42 @@
 use anyhow::Result;
-use platform_passer_core::InputEvent;
+use platform_passer_core::{InputEvent, ModifierState};
 
-use platform_passer_core::config::AppConfig;
+use platform_passer_core::config::{AppConfig, ScreenInfo};
 
 pub trait InputSource: Send + Sync {
     /// Start capturing input events. The callback is invoked for each event.
     fn start_capture(&self, callback: Box<dyn Fn(InputEvent) + Send + Sync>) -> Result<()>;
     fn stop_capture(&self) -> Result<()>;
     fn set_remote(&self, remote: bool) -> Result<()>;
-    fn add_remote(&self, _screen: platform_passer_core::config::RemoteScreen) -> Result<()> { Ok(()) }
     fn update_config(&self, _config: AppConfig) -> Result<()> { Ok(()) }
+    /// The screen peer `id` reported in its handshake, placed where its `RemoteScreen` says
+    /// instead of the configured size. `None` forgets it once the peer disconnects.
+    fn set_peer_screen(&self, _id: &str, _screen: Option<ScreenInfo>) -> Result<()> { Ok(()) }
+    /// Device ID of the peer captured input currently goes to, if it is a configured remote.
+    fn active_peer(&self) -> Option<String> { None }
+    /// Held modifiers and lock keys as the controlled peer should see them, if the platform can tell.
+    fn modifier_state(&self) -> Option<ModifierState> { None }
+    /// Pins the cursor to the current screen: edges stop switching screens in either direction.
+    fn set_screen_lock(&self, _locked: bool) -> Result<()> { Ok(()) }
+    fn screen_locked(&self) -> bool { false }
+    /// Registers a callback for lock changes, including those made by hotkey.
+    fn on_screen_lock_changed(&self, _listener: Box<dyn Fn(bool) + Send + Sync>) {}
 }
 
 pub trait InputSink {
     /// Inject a remote input event into the local system.
     fn inject_event(&self, event: InputEvent) -> Result<()>;
     fn update_config(&self, _config: AppConfig) -> Result<()> { Ok(()) }
+    /// Releases every key and button this sink pressed and has not released yet.
     fn reset_input(&self) -> Result<()> { Ok(()) }
+    /// Held modifiers and lock keys as the local system sees them, if the platform can tell.
+    fn local_modifier_state(&self) -> Option<ModifierState> { None }
+    /// Presses, releases and toggles keys until the local state matches `target`.
+    fn sync_modifiers(&self, target: &ModifierState) -> Result<()> {
+        let Some(current) = self.local_modifier_state() else { return Ok(()) };
+        for event in crate::modifiers::reconcile(&current, target) {
+            self.inject_event(event)?;
+        }
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
 pub mod sink;
 pub mod source;
+pub mod utils;
 
 pub use sink::*;
 pub use source::*;
+pub use utils::*;
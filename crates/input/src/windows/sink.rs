@@ -1,30 +1,64 @@
-use crate::InputSink;
+use crate::{InputSink, PressedStateTracker, ScrollAccumulator};
 use anyhow::Result;
-use platform_passer_core::{InputEvent, MouseButton};
+use platform_passer_core::{InputEvent, ModifierState, MouseButton, ScreenSide};
 use windows::Win32::UI::Input::KeyboardAndMouse::{
     SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, 
-    MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_MOVE, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+    MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_MOVE, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
     MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP,
-    MOUSEEVENTF_WHEEL, MOUSEEVENTF_HWHEEL,
+    MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP,
+    MOUSEEVENTF_WHEEL, MOUSEEVENTF_HWHEEL, MOUSE_EVENT_FLAGS,
     KEYEVENTF_KEYUP, VIRTUAL_KEY,
-    KEYEVENTF_EXTENDEDKEY, MapVirtualKeyW, MAPVK_VK_TO_VSC,
+    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE, MapVirtualKeyW, MAPVK_VK_TO_VSC,
 };
 use std::mem::size_of;
 use std::sync::Mutex;
 use platform_passer_core::config::AppConfig;
 
+// MOUSEINPUT.mouseData values for MOUSEEVENTF_XDOWN/XUP
+const XBUTTON1: u32 = 0x0001;
+const XBUTTON2: u32 = 0x0002;
+
+/// Wheel units per line (notch); SendInput accepts any multiple, so high-resolution deltas survive.
+const WHEEL_DELTA: f32 = 120.0;
+
 pub struct WindowsInputSink {
     last_pos: Mutex<(i32, i32)>,
+    scroll_accumulator: Mutex<ScrollAccumulator>,
+    pressed: Mutex<PressedStateTracker>,
 }
 
 impl WindowsInputSink {
     pub fn new() -> Self {
         Self {
             last_pos: Mutex::new((0, 0)),
+            scroll_accumulator: Mutex::new(ScrollAccumulator::new(WHEEL_DELTA)),
+            pressed: Mutex::new(PressedStateTracker::new()),
         }
     }
 }
 
+impl Drop for WindowsInputSink {
+    fn drop(&mut self) {
+        let _ = self.reset_input();
+    }
+}
+
+fn wheel_input(flags: MOUSE_EVENT_FLAGS, amount: i32) -> INPUT {
+    INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: windows::Win32::UI::Input::KeyboardAndMouse::MOUSEINPUT {
+                dx: 0,
+                dy: 0,
+                mouseData: amount as u32,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
 fn is_extended_key(vk: u32) -> bool {
     matches!(
         vk,
@@ -34,8 +68,57 @@ fn is_extended_key(vk: u32) -> bool {
     )
 }
 
+/// Sends one key. With `vk` 0 the scancode alone is injected, so the key lands on the same
+/// physical position under any layout.
+fn send_key(vk: u32, scancode: u16, is_down: bool) {
+    use windows::Win32::UI::Input::KeyboardAndMouse::KEYBD_EVENT_FLAGS;
+
+    let mut flags = if is_down { KEYBD_EVENT_FLAGS(0) } else { KEYEVENTF_KEYUP };
+    if scancode & 0xE000 == 0xE000 {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    if vk == 0 {
+        flags |= KEYEVENTF_SCANCODE;
+    }
+
+    let input = INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: windows::Win32::UI::Input::KeyboardAndMouse::KEYBDINPUT {
+                wVk: VIRTUAL_KEY(vk as u16),
+                wScan: scancode & 0xFF,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    unsafe { SendInput(&[input], size_of::<INPUT>() as i32) };
+}
+
+/// One UTF-16 unit typed through `KEYEVENTF_UNICODE`, independent of the keyboard layout.
+fn unicode_input(unit: u16, is_down: bool) -> INPUT {
+    let flags = if is_down { KEYEVENTF_UNICODE } else { KEYEVENTF_UNICODE | KEYEVENTF_KEYUP };
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: windows::Win32::UI::Input::KeyboardAndMouse::KEYBDINPUT {
+                wVk: VIRTUAL_KEY(0),
+                wScan: unit,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
 impl InputSink for WindowsInputSink {
     fn inject_event(&self, event: InputEvent) -> Result<()> {
+        if let Ok(mut pressed) = self.pressed.lock() {
+            pressed.record(&event);
+        }
+
         match event {
             InputEvent::MouseMove { x, y } => {
                 let dx = (x * 65535.0) as i32;
@@ -52,7 +135,8 @@ impl InputSink for WindowsInputSink {
                             dx,
                             dy,
                             mouseData: 0,
-                            dwFlags: MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_MOVE,
+                            // Over the whole virtual screen, not just the primary monitor
+                            dwFlags: MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK | MOUSEEVENTF_MOVE,
                             time: 0,
                             dwExtraInfo: 0,
                         },
@@ -61,10 +145,18 @@ impl InputSink for WindowsInputSink {
                 unsafe { SendInput(&[input], size_of::<INPUT>() as i32) };
             }
             InputEvent::MouseButton { button, is_down } => {
-                let flags = match button {
-                    MouseButton::Left => if is_down { MOUSEEVENTF_LEFTDOWN } else { MOUSEEVENTF_LEFTUP },
-                    MouseButton::Right => if is_down { MOUSEEVENTF_RIGHTDOWN } else { MOUSEEVENTF_RIGHTUP },
-                    MouseButton::Middle => if is_down { MOUSEEVENTF_MIDDLEDOWN } else { MOUSEEVENTF_MIDDLEUP },
+                let x_flags = if is_down { MOUSEEVENTF_XDOWN } else { MOUSEEVENTF_XUP };
+                let (flags, mouse_data) = match button {
+                    MouseButton::Left => (if is_down { MOUSEEVENTF_LEFTDOWN } else { MOUSEEVENTF_LEFTUP }, 0),
+                    MouseButton::Right => (if is_down { MOUSEEVENTF_RIGHTDOWN } else { MOUSEEVENTF_RIGHTUP }, 0),
+                    MouseButton::Middle => (if is_down { MOUSEEVENTF_MIDDLEDOWN } else { MOUSEEVENTF_MIDDLEUP }, 0),
+                    MouseButton::Back => (x_flags, XBUTTON1),
+                    MouseButton::Forward => (x_flags, XBUTTON2),
+                    MouseButton::Other(n) => {
+                        // SendInput can only synthesize the two X buttons
+                        tracing::trace!("WindowsInputSink: cannot inject mouse button {}", n);
+                        return Ok(());
+                    }
                 };
 
                 let (dx, dy) = if let Ok(pos) = self.last_pos.lock() {
@@ -79,8 +171,8 @@ impl InputSink for WindowsInputSink {
                         mi: windows::Win32::UI::Input::KeyboardAndMouse::MOUSEINPUT {
                             dx,
                             dy,
-                            mouseData: 0,
-                            dwFlags: flags | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_MOVE,
+                            mouseData: mouse_data,
+                            dwFlags: flags | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK | MOUSEEVENTF_MOVE,
                             time: 0,
                             dwExtraInfo: 0,
                         },
@@ -122,45 +214,79 @@ impl InputSink for WindowsInputSink {
                     unsafe { SendInput(&[input], size_of::<INPUT>() as i32) };
                 }
             }
-            InputEvent::Keyboard { key_code, is_down } => {
-                use windows::Win32::UI::Input::KeyboardAndMouse::KEYBD_EVENT_FLAGS;
-                
-                let mut flags = if is_down { KEYBD_EVENT_FLAGS(0) } else { KEYEVENTF_KEYUP };
-                
-                if is_extended_key(key_code) {
-                    flags |= KEYEVENTF_EXTENDEDKEY;
-                }
-
-                let scan_code = unsafe { MapVirtualKeyW(key_code, MAPVK_VK_TO_VSC) } as u16;
-
-                let input = INPUT {
-                    r#type: INPUT_KEYBOARD,
-                    Anonymous: INPUT_0 {
-                        ki: windows::Win32::UI::Input::KeyboardAndMouse::KEYBDINPUT {
-                            wVk: VIRTUAL_KEY(key_code as u16),
-                            wScan: scan_code,
-                            dwFlags: flags,
-                            time: 0,
-                            dwExtraInfo: 0,
-                        },
-                    },
+            InputEvent::SmoothScroll { dx, dy, unit, phase } => {
+                let (steps_x, steps_y) = match self.scroll_accumulator.lock() {
+                    Ok(mut acc) => acc.accumulate(dx, dy, unit, phase),
+                    Err(_) => return Ok(()),
                 };
-                unsafe { SendInput(&[input], size_of::<INPUT>() as i32) };
+                if steps_y != 0 {
+                    unsafe { SendInput(&[wheel_input(MOUSEEVENTF_WHEEL, steps_y)], size_of::<INPUT>() as i32) };
+                }
+                if steps_x != 0 {
+                    unsafe { SendInput(&[wheel_input(MOUSEEVENTF_HWHEEL, steps_x)], size_of::<INPUT>() as i32) };
+                }
             }
+            InputEvent::Text(text) => {
+                let inputs: Vec<INPUT> = text
+                    .encode_utf16()
+                    .flat_map(|unit| [unicode_input(unit, true), unicode_input(unit, false)])
+                    .collect();
+                if !inputs.is_empty() && unsafe { SendInput(&inputs, size_of::<INPUT>() as i32) } == 0 {
+                    tracing::debug!("InputSink: Unicode injection blocked, typing ASCII keys instead");
+                    for key in crate::text::ascii_key_events(&text) {
+                        self.inject_event(key)?;
+                    }
+                }
+            }
+            InputEvent::KeyboardVk { key_code, is_down } => {
+                let scancode = unsafe { MapVirtualKeyW(key_code, MAPVK_VK_TO_VSC) } as u16;
+                let scancode = if is_extended_key(key_code) { scancode | 0xE000 } else { scancode };
+                send_key(key_code, scancode, is_down);
+            }
+            InputEvent::Keyboard { usage, is_down, hint } => {
+                match crate::keymap::windows_key(usage, hint) {
+                    Some((vk, scancode)) => send_key(vk, scancode, is_down),
+                    None => tracing::trace!("InputSink: No Windows key for HID usage {:#04x}", usage),
+                }
+            }
+            InputEvent::ScreenSwitch(ScreenSide::Local) => return self.reset_input(),
             _ => {}
         }
         Ok(())
     }
 
     fn reset_input(&self) -> Result<()> {
-        // Reset logical state if needed?
-        // Mostly handled by OS, but could explicitly release held keys?
+        let held = match self.pressed.lock() {
+            Ok(mut pressed) => pressed.release_all(),
+            Err(_) => return Ok(()),
+        };
+        for event in held {
+            self.inject_event(event)?;
+        }
         Ok(())
     }
 
     fn update_config(&self, _config: AppConfig) -> Result<()> {
         Ok(())
     }
+
+    fn local_modifier_state(&self) -> Option<ModifierState> {
+        use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, GetKeyState};
+
+        let toggled = |vk: i32| unsafe { GetKeyState(vk) } & 0x01 != 0;
+        let mut state = ModifierState {
+            caps_lock: toggled(0x14),
+            num_lock: toggled(0x90),
+            scroll_lock: toggled(0x91),
+            ..ModifierState::default()
+        };
+        for usage in ModifierState::MODIFIER_USAGES {
+            if let Some(vk) = crate::keymap::hid_to_windows_vk(usage) {
+                state.set_held(usage, unsafe { GetAsyncKeyState(vk as i32) } < 0);
+            }
+        }
+        Some(state)
+    }
 }
 
 pub fn force_release_modifiers() {
@@ -1,21 +1,35 @@
-use crate::InputSource;
+use crate::{EdgeContact, EdgeGuard, HotkeyAction, HotkeyMatcher, HotkeyOutcome, InputSource, ScreenLock};
 use anyhow::Result;
-use platform_passer_core::{InputEvent, ScreenSide};
-use platform_passer_core::config::{AppConfig, ScreenPosition};
+use platform_passer_core::{InputEvent, KeyHint, ModifierState, ScreenSide, ScrollPhase, ScrollUnit};
+use platform_passer_core::config::{AppConfig, ScreenInfo};
+use platform_passer_core::topology::{Edge, VirtualDesktop};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use windows::Win32::Foundation::{LPARAM, WPARAM, LRESULT};
 use windows::Win32::UI::WindowsAndMessaging::{
     SetWindowsHookExA, UnhookWindowsHookEx, CallNextHookEx, GetMessageA,
     WH_KEYBOARD_LL, WH_MOUSE_LL, HHOOK, KBDLLHOOKSTRUCT, MSLLHOOKSTRUCT, WM_KEYDOWN, WM_SYSKEYDOWN,
     WM_MOUSEMOVE, GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
-    GetCursorPos, SetCursorPos,
+    GetCursorPos, SetCursorPos, GetForegroundWindow, GetWindowThreadProcessId,
 };
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, GetKeyState, GetKeyboardLayout, ToUnicodeEx};
 use std::thread;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
 static IS_REMOTE: AtomicBool = AtomicBool::new(false);
 static VIRTUAL_CURSOR_POS: Mutex<Option<(f32, f32)>> = Mutex::new(None);
-static ACTIVE_REMOTE_POS: Mutex<Option<ScreenPosition>> = Mutex::new(None);
+/// The `RemoteScreen::id` of the remote that has input.
+static ACTIVE_REMOTE: Mutex<Option<String>> = Mutex::new(None);
+/// Toggled by the lock-to-screen hotkey and `set_screen_lock`; edges do not switch screens while on.
+static SCREEN_LOCK: ScreenLock = ScreenLock::new();
+static HOTKEYS: Mutex<Option<HotkeyMatcher>> = Mutex::new(None);
+static EDGE_GUARD: Mutex<Option<EdgeGuard>> = Mutex::new(None);
+/// Rebuilt from the topology on every config update, and whenever a peer reports its screen.
+static DESKTOP: Mutex<Option<VirtualDesktop>> = Mutex::new(None);
+/// The screens connected peers reported in their handshakes, by device ID.
+static PEER_SCREENS: Mutex<Option<HashMap<String, ScreenInfo>>> = Mutex::new(None);
+/// Physical mouse buttons down, one bit each for left, right, middle, X1 and X2.
+static HELD_BUTTONS: AtomicU8 = AtomicU8::new(0);
 
 // Global callback storage
 type HookCallback = Box<dyn Fn(InputEvent) + Send + Sync>;
@@ -92,6 +106,9 @@ impl InputSource for WindowsInputSource {
     }
 
     fn set_remote(&self, remote: bool) -> Result<()> {
+        if remote && !IS_REMOTE.load(Ordering::SeqCst) {
+            unsafe { snapshot_key_state() };
+        }
         IS_REMOTE.store(remote, Ordering::SeqCst);
         
         if remote {
@@ -113,7 +130,7 @@ impl InputSource for WindowsInputSource {
             }
         } else {
             *VIRTUAL_CURSOR_POS.lock().unwrap() = None;
-            if let Ok(mut guard) = ACTIVE_REMOTE_POS.lock() { *guard = None; }
+            if let Ok(mut guard) = ACTIVE_REMOTE.lock() { *guard = None; }
             update_metrics();
             let metrics = CACHED_METRICS.lock().unwrap();
             if let Some(m) = &*metrics {
@@ -137,25 +154,234 @@ impl InputSource for WindowsInputSource {
     }
 
     fn update_config(&self, config: AppConfig) -> Result<()> {
+        if let Ok(mut hotkeys) = HOTKEYS.lock() {
+            match hotkeys.as_mut() {
+                Some(matcher) => matcher.set_config(&config.hotkeys),
+                None => *hotkeys = Some(HotkeyMatcher::new(&config.hotkeys)),
+            }
+        }
+        if let Ok(mut edge_guard) = EDGE_GUARD.lock() {
+            match edge_guard.as_mut() {
+                Some(edge_guard) => edge_guard.set_config(config.input.edge_guards),
+                None => *edge_guard = Some(EdgeGuard::new(config.input.edge_guards)),
+            }
+        }
+        rebuild_desktop(&config);
         let mut guard = GLOBAL_CONFIG.lock().unwrap();
         *guard = Some(config);
         update_metrics();
         Ok(())
     }
+
+    fn set_peer_screen(&self, id: &str, screen: Option<ScreenInfo>) -> Result<()> {
+        if let Ok(mut peers) = PEER_SCREENS.lock() {
+            let peers = peers.get_or_insert_with(HashMap::new);
+            match screen {
+                Some(screen) => peers.insert(id.to_string(), screen),
+                None => peers.remove(id),
+            };
+        }
+        if let Some(config) = GLOBAL_CONFIG.lock().ok().and_then(|config| config.clone()) {
+            rebuild_desktop(&config);
+        }
+        Ok(())
+    }
+
+    fn set_screen_lock(&self, locked: bool) -> Result<()> {
+        SCREEN_LOCK.set(locked);
+        Ok(())
+    }
+
+    fn screen_locked(&self) -> bool {
+        SCREEN_LOCK.is_locked()
+    }
+
+    fn on_screen_lock_changed(&self, listener: Box<dyn Fn(bool) + Send + Sync>) {
+        SCREEN_LOCK.set_listener(listener);
+    }
+
+    fn active_peer(&self) -> Option<String> {
+        let id = ACTIVE_REMOTE.lock().ok()?.clone()?;
+        IS_REMOTE.load(Ordering::SeqCst).then_some(id)
+    }
+
+    fn modifier_state(&self) -> Option<ModifierState> {
+        let state = KEY_STATE.lock().ok()?;
+        let mut snapshot = ModifierState {
+            caps_lock: state[0x14] & 0x01 != 0,
+            num_lock: state[0x90] & 0x01 != 0,
+            scroll_lock: state[0x91] & 0x01 != 0,
+            ..ModifierState::default()
+        };
+        for usage in ModifierState::MODIFIER_USAGES {
+            if let Some(vk) = crate::keymap::hid_to_windows_vk(usage) {
+                snapshot.set_held(usage, state[vk as usize] & 0x80 != 0);
+            }
+        }
+        Some(snapshot)
+    }
+}
+
+/// Keyboard state as the hook has seen it. Keys are swallowed while remote, so the system's
+/// own state does not follow them.
+static KEY_STATE: Mutex<[u8; 256]> = Mutex::new([0; 256]);
+
+/// VK_CAPITAL, VK_NUMLOCK and VK_SCROLL; bit 0 of their `KEY_STATE` entry is the toggle.
+const LOCK_KEYS: [usize; 3] = [0x14, 0x90, 0x91];
+
+/// Seeds `KEY_STATE` from the system when the hook starts swallowing keys; until then the
+/// system saw every key itself.
+unsafe fn snapshot_key_state() {
+    if let Ok(mut state) = KEY_STATE.lock() {
+        for (vk, entry) in state.iter_mut().enumerate() {
+            let down = if GetAsyncKeyState(vk as i32) < 0 { 0x80 } else { 0 };
+            let toggled = if LOCK_KEYS.contains(&vk) { (GetKeyState(vk as i32) & 0x01) as u8 } else { 0 };
+            *entry = down | toggled;
+        }
+    }
+}
+
+/// Records the key in `KEY_STATE` and returns the characters a key-down types on the
+/// foreground window's layout. `None` for shortcuts and keys that type nothing printable.
+unsafe fn track_key_and_translate(vk: u32, scancode: u32, is_down: bool) -> Option<String> {
+    const VK_SHIFT: usize = 0x10;
+    const VK_CONTROL: usize = 0x11;
+    const VK_MENU: usize = 0x12;
+
+    let mut state = KEY_STATE.lock().ok()?;
+    let key = (vk & 0xFF) as usize;
+    if is_down {
+        if LOCK_KEYS.contains(&key) && state[key] & 0x80 == 0 {
+            state[key] ^= 0x01;
+        }
+        state[key] |= 0x80;
+    } else {
+        state[key] &= !0x80;
+    }
+    // The hook reports left/right codes; ToUnicodeEx reads the generic ones
+    for (generic, left, right) in [(VK_SHIFT, 0xA0, 0xA1), (VK_CONTROL, 0xA2, 0xA3), (VK_MENU, 0xA4, 0xA5)] {
+        state[generic] = (state[left] | state[right]) & 0x80;
+    }
+    if !is_down {
+        return None;
+    }
+
+    let ctrl = state[VK_CONTROL] & 0x80 != 0;
+    let alt = state[VK_MENU] & 0x80 != 0;
+    let win = (state[0x5B] | state[0x5C]) & 0x80 != 0;
+    // Control+Alt is AltGr; either one alone makes a shortcut
+    if win || ctrl != alt {
+        return None;
+    }
+
+    let layout = GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), None));
+    let mut buf = [0u16; 8];
+    // 0x4: leave the system's dead-key state alone, the key never reaches it
+    let len = ToUnicodeEx(vk, scancode, &state, &mut buf, 0x4, layout);
+    if len <= 0 {
+        return None;
+    }
+    let text = String::from_utf16_lossy(&buf[..len as usize]);
+    (!text.chars().any(char::is_control)).then_some(text)
+}
+
+fn emit(event: InputEvent) {
+    if let Ok(guard) = GLOBAL_CALLBACK.try_lock() {
+        if let Some(cb) = &*guard { cb(event); }
+    }
+}
+
+/// Carries out a hotkey and returns the screen switch it caused, if any, or `None` if the
+/// hotkey had nothing to do and its key should reach the system as usual.
+fn run_hotkey(action: HotkeyAction) -> Option<Option<InputEvent>> {
+    match action {
+        HotkeyAction::ReturnToLocal => {
+            if !IS_REMOTE.load(Ordering::SeqCst) {
+                return None;
+            }
+            let _ = WindowsInputSource.set_remote(false);
+            Some(Some(InputEvent::ScreenSwitch(ScreenSide::Local)))
+        }
+        HotkeyAction::LockToScreen => {
+            SCREEN_LOCK.toggle();
+            Some(None)
+        }
+        HotkeyAction::SwitchToPeer(id) => {
+            let config = GLOBAL_CONFIG.lock().ok()?;
+            if !config.as_ref()?.topology.remotes.iter().any(|r| r.id == id) {
+                tracing::warn!("InputSource: Hotkey names unknown peer {}", id);
+                return None;
+            }
+            drop(config);
+            Some(enter_remote(id))
+        }
+        HotkeyAction::CyclePeers => {
+            let active = ACTIVE_REMOTE.lock().ok()?.clone();
+            let config = GLOBAL_CONFIG.lock().ok()?;
+            let next = crate::hotkey::next_peer(&config.as_ref()?.topology.remotes, active.as_deref()).map(|r| r.id.clone());
+            drop(config);
+            match next {
+                Some(id) => Some(enter_remote(id)),
+                None => run_hotkey(HotkeyAction::ReturnToLocal),
+            }
+        }
+    }
+}
+
+fn enter_remote(id: String) -> Option<InputEvent> {
+    if let Ok(mut guard) = ACTIVE_REMOTE.lock() {
+        *guard = Some(id);
+    }
+    let _ = WindowsInputSource.set_remote(true);
+    Some(InputEvent::ScreenSwitch(ScreenSide::Remote))
 }
 
 unsafe extern "system" fn keyboard_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     if code >= 0 {
-        let is_remote = IS_REMOTE.load(Ordering::Relaxed);
-        if is_remote {
-            let kbd = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
-            let is_down = wparam.0 as u32 == WM_KEYDOWN || wparam.0 as u32 == WM_SYSKEYDOWN;
-            let event = InputEvent::Keyboard { key_code: kbd.vkCode, is_down };
-            if let Ok(guard) = GLOBAL_CALLBACK.try_lock() {
-                if let Some(cb) = &*guard {
-                    cb(event);
-                }
+        let kbd = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        let is_down = wparam.0 as u32 == WM_KEYDOWN || wparam.0 as u32 == WM_SYSKEYDOWN;
+        // LLKHF_EXTENDED marks the 0xE0-prefixed half of the scancode set
+        let scancode = kbd.scanCode as u16 | if kbd.flags.0 & 0x01 != 0 { 0xE000 } else { 0 };
+        let event = InputEvent::Keyboard {
+            usage: crate::keymap::windows_to_hid(kbd.vkCode, scancode).unwrap_or(0),
+            is_down,
+            hint: Some(KeyHint::Windows { vk: kbd.vkCode, scancode }),
+        };
+
+        // LLKHF_INJECTED: keys our own sink typed never trigger hotkeys
+        if kbd.flags.0 & 0x10 == 0 {
+            let outcome = match HOTKEYS.try_lock() {
+                Ok(mut hotkeys) => hotkeys.get_or_insert_with(|| HotkeyMatcher::new(&Default::default())).process(&event),
+                Err(_) => HotkeyOutcome::Pass,
+            };
+            match outcome {
+                HotkeyOutcome::Pass => {}
+                HotkeyOutcome::Swallow => return LRESULT(1),
+                HotkeyOutcome::Triggered(action) => match run_hotkey(action) {
+                    Some(switch) => {
+                        if let Some(switch) = switch {
+                            emit(switch);
+                        }
+                        return LRESULT(1);
+                    }
+                    None => {
+                        if let InputEvent::Keyboard { usage, .. } = event {
+                            if let Ok(mut hotkeys) = HOTKEYS.try_lock() {
+                                if let Some(hotkeys) = hotkeys.as_mut() { hotkeys.forward(usage); }
+                            }
+                        }
+                    }
+                },
             }
+        }
+
+        if IS_REMOTE.load(Ordering::Relaxed) {
+            let text = track_key_and_translate(kbd.vkCode, kbd.scanCode, is_down);
+            // Text goes out right before its key-down; peers keep one or the other
+            if let Some(text) = text {
+                emit(InputEvent::Text(text));
+            }
+            emit(event);
             return LRESULT(1); // Swallow
         }
     }
@@ -177,6 +403,7 @@ unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM)
 
     let is_remote = IS_REMOTE.load(Ordering::Relaxed);
     let msg = wparam.0 as u32;
+    track_button(msg, ms.mouseData);
 
     // 2. FAST PATH: If not remote and not move, let it pass immediately
     if !is_remote && msg != WM_MOUSEMOVE {
@@ -200,46 +427,48 @@ unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM)
                 if dx != 0 || dy != 0 {
                     if let Ok(mut guard) = VIRTUAL_CURSOR_POS.try_lock() {
                         if let Some((vx, vy)) = *guard {
-                            let new_vx = (vx + (dx as f32 / m.width as f32)).max(0.0).min(1.0);
-                            let new_vy = (vy + (dy as f32 / m.height as f32)).max(0.0).min(1.0);
+                            let active = ACTIVE_REMOTE.try_lock().ok().and_then(|id| id.clone());
+                            let (mx, my) = remote_motion(active.as_deref(), dx as f32, dy as f32, m);
+                            let new_vx = (vx + mx).max(0.0).min(1.0);
+                            let new_vy = (vy + my).max(0.0).min(1.0);
                             *guard = Some((new_vx, new_vy));
                             
-                            // Return to Local Logic (Based on Virtual Cursor)
-                            let mut should_return = false;
-                            if let Ok(pos_guard) = ACTIVE_REMOTE_POS.try_lock() {
-                                if let Some(pos) = &*pos_guard {
-                                    should_return = match pos {
-                                        ScreenPosition::Right => new_vx <= 0.001,
-                                        ScreenPosition::Left => new_vx >= 0.999,
-                                        ScreenPosition::Top => new_vy >= 0.999,
-                                        ScreenPosition::Bottom => new_vy <= 0.001,
-                                    };
-                                }
+                            // Leaving the remote screen, judged by the virtual cursor
+                            let mut exit = match &active {
+                                Some(id) if !SCREEN_LOCK.is_locked() => find_exit(Some(id), new_vx, new_vy),
+                                _ => None,
+                            };
+                            if !edge_allows(exit.as_ref().map(|e| e.edge), active.as_deref(), new_vx, new_vy, m) {
+                                exit = None;
                             }
 
-                            if should_return {
-                                IS_REMOTE.store(false, Ordering::SeqCst);
-                                swallow = false;
-                                *guard = None;
-                                if let Ok(mut pos_guard) = ACTIVE_REMOTE_POS.try_lock() { *pos_guard = None; }
-                                event = Some(InputEvent::ScreenSwitch(ScreenSide::Local));
-                                // Center the physical cursor on the original edge to avoid immediate re-trigger
-                                let target_x = match ACTIVE_REMOTE_POS.lock().unwrap().clone() {
-                                    Some(ScreenPosition::Right) => m.left + m.width - 50,
-                                    Some(ScreenPosition::Left) => m.left + 50,
-                                    _ => ms.pt.x,
-                                };
-                                let _ = SetCursorPos(target_x, ms.pt.y);
-                            } else {
-                                // Rate limit Move
-                                use std::time::Instant;
-                                static mut LAST_SEND: Option<Instant> = None;
-                                let now = Instant::now();
-                                if LAST_SEND.map_or(true, |l| now.duration_since(l).as_millis() >= 8) {
-                                    LAST_SEND = Some(now);
-                                    event = Some(InputEvent::MouseMove { x: new_vx, y: new_vy });
+                            match exit {
+                                Some(Exit { to: None, x, y, .. }) => {
+                                    IS_REMOTE.store(false, Ordering::SeqCst);
+                                    swallow = false;
+                                    *guard = None;
+                                    if let Ok(mut id_guard) = ACTIVE_REMOTE.try_lock() { *id_guard = None; }
+                                    event = Some(InputEvent::ScreenSwitch(ScreenSide::Local));
+                                    // Land just inside this screen, across from where the cursor left the remote
+                                    let _ = SetCursorPos(m.left + (x * m.width as f32) as i32, m.top + (y * m.height as f32) as i32);
+                                }
+                                Some(Exit { to: Some(id), x, y, .. }) => {
+                                    *guard = Some((x, y));
+                                    if let Ok(mut id_guard) = ACTIVE_REMOTE.try_lock() { *id_guard = Some(id); }
+                                    event = Some(InputEvent::ScreenSwitch(ScreenSide::Remote));
+                                    let _ = SetCursorPos(center_x, center_y);
+                                }
+                                None => {
+                                    // Rate limit Move
+                                    use std::time::Instant;
+                                    static mut LAST_SEND: Option<Instant> = None;
+                                    let now = Instant::now();
+                                    if LAST_SEND.map_or(true, |l| now.duration_since(l).as_millis() >= 8) {
+                                        LAST_SEND = Some(now);
+                                        event = Some(InputEvent::MouseMove { x: new_vx, y: new_vy });
+                                    }
+                                    let _ = SetCursorPos(center_x, center_y);
                                 }
-                                let _ = SetCursorPos(center_x, center_y);
                             }
                         }
                     }
@@ -249,8 +478,14 @@ unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM)
                     WM_LBUTTONDOWN | WM_LBUTTONUP => Some(InputEvent::MouseButton { button: platform_passer_core::MouseButton::Left, is_down: msg == WM_LBUTTONDOWN }),
                     WM_RBUTTONDOWN | WM_RBUTTONUP => Some(InputEvent::MouseButton { button: platform_passer_core::MouseButton::Right, is_down: msg == WM_RBUTTONDOWN }),
                     WM_MBUTTONDOWN | WM_MBUTTONUP => Some(InputEvent::MouseButton { button: platform_passer_core::MouseButton::Middle, is_down: msg == WM_MBUTTONDOWN }),
-                    0x020A => Some(InputEvent::Scroll { dx: 0.0, dy: (ms.mouseData >> 16) as i16 as f32 / 120.0 }),
-                    0x020E => Some(InputEvent::Scroll { dx: (ms.mouseData >> 16) as i16 as f32 / 120.0, dy: 0.0 }),
+                    WM_XBUTTONDOWN | WM_XBUTTONUP => {
+                        // HIWORD(mouseData): 1 = XBUTTON1, 2 = XBUTTON2
+                        let button = platform_passer_core::MouseButton::from_index(2 + (ms.mouseData >> 16) as u8);
+                        Some(InputEvent::MouseButton { button, is_down: msg == WM_XBUTTONDOWN })
+                    }
+                    // Fractions of a notch come from high-resolution wheels; sinks carry them over
+                    0x020A => Some(InputEvent::SmoothScroll { dx: 0.0, dy: (ms.mouseData >> 16) as i16 as f32 / 120.0, unit: ScrollUnit::Line, phase: ScrollPhase::None }),
+                    0x020E => Some(InputEvent::SmoothScroll { dx: (ms.mouseData >> 16) as i16 as f32 / 120.0, dy: 0.0, unit: ScrollUnit::Line, phase: ScrollPhase::None }),
                     _ => None
                 };
             }
@@ -260,33 +495,20 @@ unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM)
         if let Some(m) = metrics {
             let abs_x = (ms.pt.x - m.left) as f32 / m.width as f32;
             let abs_y = (ms.pt.y - m.top) as f32 / m.height as f32;
-            let mut triggered_remote = None;
-
-            if let Ok(config_opt) = GLOBAL_CONFIG.try_lock() {
-                if let Some(config) = &*config_opt {
-                    for remote in &config.topology.remotes {
-                        let hit = match remote.position {
-                            ScreenPosition::Right => abs_x >= 0.999,
-                            ScreenPosition::Left => abs_x <= 0.001,
-                            ScreenPosition::Top => abs_y <= 0.001,
-                            ScreenPosition::Bottom => abs_y >= 0.999,
-                        };
-                        if hit {
-                            triggered_remote = Some(remote.position.clone());
-                            break;
-                        }
-                    }
-                }
+            let mut exit = if SCREEN_LOCK.is_locked() { None } else { find_exit(None, abs_x, abs_y) };
+            if !edge_allows(exit.as_ref().map(|e| e.edge), None, abs_x, abs_y, m) {
+                exit = None;
             }
 
-            if let Some(pos) = triggered_remote {
+            if let Some(Exit { to: Some(id), x, y, .. }) = exit {
+                snapshot_key_state();
                 IS_REMOTE.store(true, Ordering::SeqCst);
                 swallow = true;
                 if let Ok(mut v_guard) = VIRTUAL_CURSOR_POS.try_lock() {
-                    *v_guard = Some((abs_x, abs_y));
+                    *v_guard = Some((x, y));
                 }
-                if let Ok(mut pos_guard) = ACTIVE_REMOTE_POS.try_lock() {
-                    *pos_guard = Some(pos);
+                if let Ok(mut id_guard) = ACTIVE_REMOTE.try_lock() {
+                    *id_guard = Some(id);
                 }
                 let center_x = m.left + m.width / 2;
                 let center_y = m.top + m.height / 2;
@@ -297,17 +519,103 @@ unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM)
     }
 
     if let Some(ev) = event {
-        if let Ok(guard) = GLOBAL_CALLBACK.try_lock() {
-            if let Some(cb) = &*guard { cb(ev); }
-        }
+        emit(ev);
     }
 
     if swallow { LRESULT(1) } else { CallNextHookEx(MOUSE_HOOK, code, wparam, lparam) }
 }
 
+/// A crossing found by the virtual desktop: the id of the remote entered (`None` for this
+/// machine), the edge left and the normalized entry point.
+struct Exit {
+    to: Option<String>,
+    edge: Edge,
+    x: f32,
+    y: f32,
+}
+
+/// Lays out the virtual desktop for `config`, with the screens peers reported and the monitors
+/// attached here now, whatever the config says about either.
+fn rebuild_desktop(config: &AppConfig) {
+    let peers = PEER_SCREENS.lock().ok().and_then(|peers| peers.clone()).unwrap_or_default();
+    let mut topology = config.topology.clone().with_peer_screens(&peers);
+    if let Some(info) = super::utils::get_screen_info() {
+        topology.local = info;
+    }
+    if let Ok(mut desktop) = DESKTOP.lock() {
+        *desktop = Some(VirtualDesktop::new(&topology, config.input.maintain_aspect_ratio));
+    }
+}
+
+/// Where the cursor at normalized `(x, y)` on the remote with id `from` (`None` for this
+/// machine) goes next, if it is against an edge with a screen beyond it.
+fn find_exit(from: Option<&str>, x: f32, y: f32) -> Option<Exit> {
+    let desktop = DESKTOP.try_lock().ok()?;
+    let desktop = desktop.as_ref()?;
+    let crossing = desktop.exit(from, x as f64, y as f64)?;
+    Some(Exit {
+        to: crossing.to.map(|remote| remote.id.clone()),
+        edge: crossing.edge,
+        x: crossing.x as f32,
+        y: crossing.y as f32,
+    })
+}
+
+/// Normalized motion on the remote with id `on` for `(dx, dy)` pixels of motion here, as placed
+/// on the virtual desktop. Falls back to this screen's size.
+fn remote_motion(on: Option<&str>, dx: f32, dy: f32, m: &Metrics) -> (f32, f32) {
+    let desktop = DESKTOP.try_lock().ok();
+    let motion = desktop.as_ref().and_then(|d| d.as_ref()).zip(on).and_then(|(d, on)| d.motion(on, dx as f64, dy as f64));
+    match motion {
+        Some((x, y)) => (x as f32, y as f32),
+        None => (dx / m.width as f32, dy / m.height as f32),
+    }
+}
+
+/// Feeds a cursor sample at normalized `(x, y)` on the remote with id `on` (`None` for this
+/// machine) to the edge guard, `edge` being the switching edge it is against, if any. Returns
+/// whether to switch.
+fn edge_allows(edge: Option<Edge>, on: Option<&str>, x: f32, y: f32, m: &Metrics) -> bool {
+    // A remote's edges are measured on its rectangle of the virtual desktop, not this screen
+    let (width, height) = on
+        .and_then(|id| DESKTOP.try_lock().ok()?.as_ref()?.rect_of(id))
+        .map_or((m.width as f64, m.height as f64), |rect| (rect.width, rect.height));
+    let (along, length) = match edge {
+        Some(Edge::Top) | Some(Edge::Bottom) => (x as f64, width),
+        _ => (y as f64, height),
+    };
+    let contact = EdgeContact {
+        edge,
+        along_px: along * length,
+        length_px: length,
+        buttons_held: HELD_BUTTONS.load(Ordering::Relaxed) != 0,
+    };
+    match EDGE_GUARD.try_lock() {
+        Ok(mut guard) => guard.get_or_insert_with(EdgeGuard::default).update(&contact, std::time::Instant::now()),
+        Err(_) => false,
+    }
+}
+
+fn track_button(msg: u32, mouse_data: u32) {
+    let bit = match msg {
+        WM_LBUTTONDOWN | WM_LBUTTONUP => 0x01,
+        WM_RBUTTONDOWN | WM_RBUTTONUP => 0x02,
+        WM_MBUTTONDOWN | WM_MBUTTONUP => 0x04,
+        WM_XBUTTONDOWN | WM_XBUTTONUP => if (mouse_data >> 16) == 1 { 0x08 } else { 0x10 },
+        _ => return,
+    };
+    if matches!(msg, WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN) {
+        HELD_BUTTONS.fetch_or(bit, Ordering::Relaxed);
+    } else {
+        HELD_BUTTONS.fetch_and(!bit, Ordering::Relaxed);
+    }
+}
+
 const WM_LBUTTONDOWN: u32 = 0x0201;
 const WM_LBUTTONUP: u32 = 0x0202;
 const WM_RBUTTONDOWN: u32 = 0x0204;
 const WM_RBUTTONUP: u32 = 0x0205;
 const WM_MBUTTONDOWN: u32 = 0x0207;
 const WM_MBUTTONUP: u32 = 0x0208;
+const WM_XBUTTONDOWN: u32 = 0x020B;
+const WM_XBUTTONUP: u32 = 0x020C;
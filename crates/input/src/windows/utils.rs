@@ -0,0 +1,46 @@
+use platform_passer_core::config::{DisplayInfo, ScreenInfo};
+use windows::Win32::Foundation::{BOOL, LPARAM, RECT};
+use windows::Win32::Graphics::Gdi::{EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO};
+
+const MONITORINFOF_PRIMARY: u32 = 0x1;
+
+/// Fetches every monitor's bounds in virtual-screen pixels. The screen spans their bounding
+/// box, the same area the hooks normalize cursor positions against. Scales stay 1.0: hook
+/// coordinates are already pixels.
+pub fn get_screen_info() -> Option<ScreenInfo> {
+    let mut displays: Vec<DisplayInfo> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(HDC::default(), None, Some(collect_monitor), LPARAM(&mut displays as *mut _ as isize));
+    }
+    if displays.is_empty() {
+        return None;
+    }
+
+    let left = displays.iter().map(|d| d.x).min().unwrap_or(0);
+    let top = displays.iter().map(|d| d.y).min().unwrap_or(0);
+    let right = displays.iter().map(|d| d.x + d.width as i32).max().unwrap_or(0);
+    let bottom = displays.iter().map(|d| d.y + d.height as i32).max().unwrap_or(0);
+    Some(ScreenInfo {
+        width: (right - left).max(0) as u32,
+        height: (bottom - top).max(0) as u32,
+        dpi_scale: 1.0,
+        displays,
+    })
+}
+
+unsafe extern "system" fn collect_monitor(monitor: HMONITOR, _hdc: HDC, _clip: *mut RECT, data: LPARAM) -> BOOL {
+    let displays = &mut *(data.0 as *mut Vec<DisplayInfo>);
+    let mut info = MONITORINFO { cbSize: std::mem::size_of::<MONITORINFO>() as u32, ..Default::default() };
+    if GetMonitorInfoW(monitor, &mut info).as_bool() {
+        let bounds = info.rcMonitor;
+        displays.push(DisplayInfo {
+            x: bounds.left,
+            y: bounds.top,
+            width: (bounds.right - bounds.left).max(0) as u32,
+            height: (bounds.bottom - bounds.top).max(0) as u32,
+            dpi_scale: 1.0,
+            primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+        });
+    }
+    BOOL(1)
+}
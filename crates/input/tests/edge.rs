@@ -0,0 +1,83 @@
+//! Edge-switch guards: dwell, double tap, dead corners and held buttons.
+use platform_passer_core::config::EdgeGuardConfig;
+use platform_passer_core::topology::Edge;
+use platform_passer_input::{EdgeContact, EdgeGuard};
+use std::time::{Duration, Instant};
+
+fn at(edge: Option<Edge>, along_px: f64) -> EdgeContact {
+    EdgeContact { edge, along_px, length_px: 1080.0, buttons_held: false }
+}
+
+fn right() -> EdgeContact {
+    at(Some(Edge::Right), 540.0)
+}
+
+fn away() -> EdgeContact {
+    at(None, 540.0)
+}
+
+fn ms(start: Instant, n: u64) -> Instant {
+    start + Duration::from_millis(n)
+}
+
+#[test]
+fn switches_on_first_touch_by_default() {
+    let mut guard = EdgeGuard::default();
+    assert!(!guard.update(&away(), Instant::now()));
+    assert!(guard.update(&right(), Instant::now()));
+}
+
+#[test]
+fn dwell_needs_continuous_contact() {
+    let t = Instant::now();
+    let mut guard = EdgeGuard::new(EdgeGuardConfig { dwell_ms: 200, ..Default::default() });
+    assert!(!guard.update(&right(), t));
+    assert!(!guard.update(&right(), ms(t, 150)));
+    // Leaving restarts the clock
+    assert!(!guard.update(&away(), ms(t, 160)));
+    assert!(!guard.update(&right(), ms(t, 170)));
+    assert!(!guard.update(&right(), ms(t, 300)));
+    assert!(guard.update(&right(), ms(t, 370)));
+}
+
+#[test]
+fn double_tap_needs_a_second_touch_within_the_window() {
+    let t = Instant::now();
+    let mut guard = EdgeGuard::new(EdgeGuardConfig { double_tap: true, double_tap_window_ms: 400, ..Default::default() });
+    assert!(!guard.update(&right(), t));
+    assert!(!guard.update(&right(), ms(t, 50)));
+    assert!(!guard.update(&away(), ms(t, 100)));
+    // Too late: this touch becomes the first tap
+    assert!(!guard.update(&right(), ms(t, 600)));
+    assert!(!guard.update(&away(), ms(t, 650)));
+    assert!(guard.update(&right(), ms(t, 800)));
+}
+
+#[test]
+fn double_tap_must_hit_the_same_edge() {
+    let t = Instant::now();
+    let mut guard = EdgeGuard::new(EdgeGuardConfig { double_tap: true, ..Default::default() });
+    assert!(!guard.update(&right(), t));
+    assert!(!guard.update(&away(), ms(t, 50)));
+    assert!(!guard.update(&at(Some(Edge::Top), 540.0), ms(t, 100)));
+}
+
+#[test]
+fn dead_corners_never_switch() {
+    let t = Instant::now();
+    let mut guard = EdgeGuard::new(EdgeGuardConfig { corner_dead_zone_px: 40, ..Default::default() });
+    assert!(!guard.update(&at(Some(Edge::Right), 10.0), t));
+    assert!(!guard.update(&at(Some(Edge::Right), 1060.0), t));
+    assert!(guard.update(&at(Some(Edge::Right), 40.0), t));
+}
+
+#[test]
+fn held_buttons_block_only_when_configured() {
+    let t = Instant::now();
+    let dragging = EdgeContact { buttons_held: true, ..right() };
+    assert!(EdgeGuard::default().update(&dragging, t));
+
+    let mut guard = EdgeGuard::new(EdgeGuardConfig { block_while_button_held: true, ..Default::default() });
+    assert!(!guard.update(&dragging, t));
+    assert!(guard.update(&right(), ms(t, 10)));
+}
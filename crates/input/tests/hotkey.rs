@@ -0,0 +1,100 @@
+//! The hotkey matcher every input source shares.
+use platform_passer_core::config::{HotkeyConfig, PeerHotkey, RemoteScreen, ScreenInfo, ScreenPosition};
+use platform_passer_core::hotkey::modifiers;
+use platform_passer_core::{Chord, InputEvent};
+use platform_passer_input::hotkey::next_peer;
+use platform_passer_input::{HotkeyAction, HotkeyMatcher, HotkeyOutcome};
+
+const ESCAPE: u16 = 0x29;
+const LEFT_CTRL: u16 = 0xE0;
+const LEFT_ALT: u16 = 0xE2;
+const RIGHT_ALT: u16 = 0xE6;
+
+fn key(usage: u16, is_down: bool) -> InputEvent {
+    InputEvent::Keyboard { usage, is_down, hint: None }
+}
+
+fn feed(matcher: &mut HotkeyMatcher, events: &[InputEvent]) -> Vec<HotkeyOutcome> {
+    events.iter().map(|e| matcher.process(e)).collect()
+}
+
+#[test]
+fn plain_escape_is_forwarded() {
+    let mut matcher = HotkeyMatcher::new(&HotkeyConfig::default());
+    assert_eq!(feed(&mut matcher, &[key(ESCAPE, true), key(ESCAPE, false)]), [HotkeyOutcome::Pass, HotkeyOutcome::Pass]);
+}
+
+#[test]
+fn chord_triggers_once_and_swallows_its_key_until_released() {
+    let mut matcher = HotkeyMatcher::new(&HotkeyConfig::default());
+    let outcomes = feed(
+        &mut matcher,
+        &[key(LEFT_CTRL, true), key(RIGHT_ALT, true), key(ESCAPE, true), key(ESCAPE, true), key(ESCAPE, false), key(RIGHT_ALT, false)],
+    );
+    assert_eq!(
+        outcomes,
+        [
+            HotkeyOutcome::Pass,
+            HotkeyOutcome::Pass,
+            HotkeyOutcome::Triggered(HotkeyAction::ReturnToLocal),
+            HotkeyOutcome::Swallow,
+            HotkeyOutcome::Swallow,
+            HotkeyOutcome::Pass,
+        ]
+    );
+}
+
+#[test]
+fn a_forwarded_chord_key_is_released_as_usual() {
+    let mut matcher = HotkeyMatcher::new(&HotkeyConfig::default());
+    let outcomes = feed(&mut matcher, &[key(LEFT_CTRL, true), key(RIGHT_ALT, true), key(ESCAPE, true)]);
+    assert_eq!(outcomes[2], HotkeyOutcome::Triggered(HotkeyAction::ReturnToLocal));
+    matcher.forward(ESCAPE);
+    assert_eq!(matcher.process(&key(ESCAPE, false)), HotkeyOutcome::Pass);
+}
+
+#[test]
+fn extra_modifiers_do_not_match() {
+    let mut matcher = HotkeyMatcher::new(&HotkeyConfig::default());
+    let outcomes = feed(&mut matcher, &[key(LEFT_CTRL, true), key(LEFT_ALT, true), key(0xE1, true), key(ESCAPE, true)]);
+    assert_eq!(outcomes[3], HotkeyOutcome::Pass);
+}
+
+#[test]
+fn released_modifiers_stop_counting() {
+    let mut matcher = HotkeyMatcher::new(&HotkeyConfig::default());
+    feed(&mut matcher, &[key(LEFT_CTRL, true), key(LEFT_ALT, true), key(LEFT_ALT, false)]);
+    assert_eq!(matcher.modifiers(), modifiers::CTRL);
+    assert_eq!(matcher.process(&key(ESCAPE, true)), HotkeyOutcome::Pass);
+}
+
+#[test]
+fn peer_chords_carry_the_peer_id() {
+    let config = HotkeyConfig {
+        return_to_local: None,
+        cycle_peers: Some(Chord::new(modifiers::CTRL, 0x2B)),
+        switch_to_peer: vec![PeerHotkey { peer: "laptop".into(), chord: Chord::new(modifiers::META, 0x1E) }],
+        ..HotkeyConfig::default()
+    };
+    let mut matcher = HotkeyMatcher::new(&config);
+    let outcomes = feed(&mut matcher, &[key(0xE3, true), key(0x1E, true), key(0x1E, false), key(0xE3, false)]);
+    assert_eq!(outcomes[1], HotkeyOutcome::Triggered(HotkeyAction::SwitchToPeer("laptop".into())));
+    let outcomes = feed(&mut matcher, &[key(0xE4, true), key(0x2B, true)]);
+    assert_eq!(outcomes[1], HotkeyOutcome::Triggered(HotkeyAction::CyclePeers));
+    // Unbound by this config
+    let outcomes = feed(&mut matcher, &[key(0xE4, false), key(LEFT_CTRL, true), key(LEFT_ALT, true), key(ESCAPE, true)]);
+    assert_eq!(outcomes[3], HotkeyOutcome::Pass);
+}
+
+#[test]
+fn cycling_walks_the_topology_then_returns_home() {
+    let remote = |id: &str, position| RemoteScreen { id: id.into(), position, info: ScreenInfo::default() };
+    // Peers that share a side are still visited one by one
+    let remotes = [remote("a", ScreenPosition::Right), remote("b", ScreenPosition::Right), remote("c", ScreenPosition::Left)];
+    assert_eq!(next_peer(&remotes, None).map(|r| r.id.as_str()), Some("a"));
+    assert_eq!(next_peer(&remotes, Some("a")).map(|r| r.id.as_str()), Some("b"));
+    assert_eq!(next_peer(&remotes, Some("b")).map(|r| r.id.as_str()), Some("c"));
+    assert!(next_peer(&remotes, Some("c")).is_none());
+    assert!(next_peer(&remotes, Some("gone")).is_none());
+    assert!(next_peer(&[], None).is_none());
+}
@@ -0,0 +1,74 @@
+//! Round trips through the HID key tables.
+use platform_passer_core::KeyHint;
+use platform_passer_input::keymap::*;
+
+/// Converting a usage out and back must land on the same usage, unless the platform code is
+/// shared with an earlier key, in which case that key must convert to the same code.
+fn assert_round_trip(name: &str, to: fn(u16) -> Option<u32>, from: fn(u32) -> Option<u16>) {
+    for usage in usages() {
+        let Some(code) = to(usage) else { continue };
+        let back = from(code).unwrap_or_else(|| panic!("{}: {:#x} from usage {:#04x} has no usage", name, code, usage));
+        if back != usage {
+            assert_eq!(to(back), Some(code), "{}: usage {:#04x} came back as {:#04x}", name, usage, back);
+        }
+    }
+}
+
+#[test]
+fn windows_vk_round_trips() {
+    assert_round_trip("vk", hid_to_windows_vk, windows_vk_to_hid);
+}
+
+#[test]
+fn windows_scancode_round_trips() {
+    assert_round_trip(
+        "scancode",
+        |u| hid_to_windows_scancode(u).map(u32::from),
+        |c| windows_scancode_to_hid(c as u16),
+    );
+}
+
+#[test]
+fn macos_keycode_round_trips() {
+    assert_round_trip("macos", |u| hid_to_macos_keycode(u).map(u32::from), |c| macos_keycode_to_hid(c as u16));
+}
+
+#[test]
+fn evdev_round_trips() {
+    assert_round_trip("evdev", |u| hid_to_evdev(u).map(u32::from), |c| evdev_to_hid(c as u16));
+}
+
+#[test]
+fn usages_are_unique() {
+    let mut seen: Vec<u16> = usages().collect();
+    let total = seen.len();
+    seen.sort_unstable();
+    seen.dedup();
+    assert_eq!(seen.len(), total);
+}
+
+#[test]
+fn known_keys_match_across_platforms() {
+    // Physical Q: the same key whatever the layout types
+    assert_eq!(windows_scancode_to_hid(0x10), Some(0x14));
+    assert_eq!(macos_keycode_to_hid(12), Some(0x14));
+    assert_eq!(evdev_to_hid(16), Some(0x14));
+
+    // Extended scancodes tell Right Control and keypad Enter apart
+    assert_eq!(windows_scancode_to_hid(0xE01D), Some(0xE4));
+    assert_eq!(windows_scancode_to_hid(0x001D), Some(0xE0));
+    assert_eq!(windows_to_hid(0x0D, 0xE01C), Some(0x58));
+    assert_eq!(windows_vk_to_hid(0x0D), Some(0x28));
+
+    // Generic modifier codes map to the left-hand key
+    assert_eq!(windows_vk_to_hid(0x10), Some(0xE1));
+}
+
+#[test]
+fn same_platform_hints_win_over_the_table() {
+    assert_eq!(macos_key(0, Some(KeyHint::Macos { keycode: 0x3F })), Some(0x3F));
+    assert_eq!(macos_key(0x14, Some(KeyHint::Windows { vk: 0x41, scancode: 0x10 })), Some(12));
+    assert_eq!(windows_key(0x14, Some(KeyHint::Windows { vk: 0x41, scancode: 0x10 })), Some((0x41, 0x10)));
+    assert_eq!(windows_key(0x14, Some(KeyHint::Macos { keycode: 12 })), Some((0, 0x10)));
+    assert_eq!(windows_key(0, None), None);
+}
@@ -0,0 +1,65 @@
+//! Modifier snapshots: when they are sent and how a sink catches up to one.
+use platform_passer_core::{InputEvent, ModifierState, ScreenSide};
+use platform_passer_input::modifiers::{reconcile, snapshot_due, CAPS_LOCK, NUM_LOCK};
+
+fn key(usage: u16, is_down: bool) -> InputEvent {
+    InputEvent::Keyboard { usage, is_down, hint: None }
+}
+
+/// `(usage, is_down)` of each key event.
+fn keys(events: Vec<InputEvent>) -> Vec<(u16, bool)> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            InputEvent::Keyboard { usage, is_down, .. } => (usage, is_down),
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect()
+}
+
+fn held(usages: &[u16]) -> ModifierState {
+    let mut state = ModifierState::default();
+    for &usage in usages {
+        state.set_held(usage, true);
+    }
+    state
+}
+
+#[test]
+fn held_bits_follow_hid_modifier_order() {
+    let state = held(&[0xE0, 0xE7]);
+    assert_eq!(state.held, 0b1000_0001);
+    assert!(state.is_held(0xE0));
+    assert!(!state.is_held(0xE1));
+    // Not a modifier usage
+    assert!(!state.is_held(0x04));
+}
+
+#[test]
+fn snapshots_follow_screen_entry_and_lock_key_release() {
+    assert!(snapshot_due(&InputEvent::ScreenSwitch(ScreenSide::Remote)));
+    assert!(!snapshot_due(&InputEvent::ScreenSwitch(ScreenSide::Local)));
+    assert!(snapshot_due(&key(CAPS_LOCK, false)));
+    assert!(!snapshot_due(&key(CAPS_LOCK, true)));
+    assert!(!snapshot_due(&key(0xE1, false)));
+}
+
+#[test]
+fn reconcile_presses_and_releases_differing_modifiers() {
+    let events = reconcile(&held(&[0xE0, 0xE1]), &held(&[0xE1, 0xE2]));
+    assert_eq!(keys(events), [(0xE0, false), (0xE2, true)]);
+}
+
+#[test]
+fn reconcile_taps_each_differing_lock_once() {
+    let current = ModifierState { caps_lock: true, num_lock: false, ..ModifierState::default() };
+    let target = ModifierState { caps_lock: false, num_lock: true, ..ModifierState::default() };
+    let events = reconcile(&current, &target);
+    assert_eq!(keys(events), [(CAPS_LOCK, true), (CAPS_LOCK, false), (NUM_LOCK, true), (NUM_LOCK, false)]);
+}
+
+#[test]
+fn matching_state_needs_no_events() {
+    let state = ModifierState { held: 0x11, scroll_lock: true, ..ModifierState::default() };
+    assert!(reconcile(&state, &state).is_empty());
+}
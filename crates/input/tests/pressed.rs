@@ -0,0 +1,66 @@
+//! Which keys and buttons a sink releases when the controlling peer goes away.
+use platform_passer_core::{InputEvent, KeyHint, MouseButton};
+use platform_passer_input::PressedStateTracker;
+
+fn key(usage: u16, is_down: bool) -> InputEvent {
+    InputEvent::Keyboard { usage, is_down, hint: None }
+}
+
+fn describe(events: Vec<InputEvent>) -> Vec<String> {
+    events.iter().map(|e| format!("{:?}", e)).collect()
+}
+
+#[test]
+fn releases_exactly_what_is_still_held_most_recent_first() {
+    let mut tracker = PressedStateTracker::new();
+    for event in [
+        key(0xE0, true),
+        key(0x06, true),
+        InputEvent::MouseButton { button: MouseButton::Left, is_down: true },
+        key(0x06, false),
+        InputEvent::KeyboardVk { key_code: 0x5B, is_down: true },
+    ] {
+        tracker.record(&event);
+    }
+
+    let released = tracker.release_all();
+    assert_eq!(
+        describe(released),
+        describe(vec![
+            InputEvent::KeyboardVk { key_code: 0x5B, is_down: false },
+            InputEvent::MouseButton { button: MouseButton::Left, is_down: false },
+            key(0xE0, false),
+        ])
+    );
+    assert!(tracker.is_empty());
+    assert!(tracker.release_all().is_empty());
+}
+
+#[test]
+fn repeats_are_released_once() {
+    let mut tracker = PressedStateTracker::new();
+    for _ in 0..3 {
+        tracker.record(&key(0x04, true));
+    }
+    assert_eq!(tracker.release_all().len(), 1);
+}
+
+#[test]
+fn release_keeps_the_hint_the_key_went_down_with() {
+    let hint = Some(KeyHint::Windows { vk: 0xBA, scancode: 0x27 });
+    let mut tracker = PressedStateTracker::new();
+    tracker.record(&InputEvent::Keyboard { usage: 0, is_down: true, hint });
+    tracker.record(&InputEvent::Keyboard { usage: 0, is_down: true, hint: Some(KeyHint::Macos { keycode: 41 }) });
+    tracker.record(&InputEvent::Keyboard { usage: 0, is_down: false, hint: Some(KeyHint::Macos { keycode: 41 }) });
+
+    let released = tracker.release_all();
+    assert_eq!(describe(released), describe(vec![InputEvent::Keyboard { usage: 0, is_down: false, hint }]));
+}
+
+#[test]
+fn non_key_events_are_not_tracked() {
+    let mut tracker = PressedStateTracker::new();
+    tracker.record(&InputEvent::MouseMove { x: 0.5, y: 0.5 });
+    tracker.record(&InputEvent::Text("a".into()));
+    assert!(tracker.is_empty());
+}
@@ -0,0 +1,30 @@
+//! The screen lock shared by a source's hooks, hotkeys and session.
+use platform_passer_input::ScreenLock;
+use std::sync::{Arc, Mutex};
+
+fn recording(lock: &ScreenLock) -> Arc<Mutex<Vec<bool>>> {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    lock.set_listener(Box::new(move |locked| sink.lock().unwrap().push(locked)));
+    seen
+}
+
+#[test]
+fn starts_unlocked_and_toggles() {
+    let lock = ScreenLock::new();
+    assert!(!lock.is_locked());
+    assert!(lock.toggle());
+    assert!(lock.is_locked());
+    assert!(!lock.toggle());
+}
+
+#[test]
+fn listener_hears_every_change_once() {
+    let lock = ScreenLock::new();
+    let seen = recording(&lock);
+    lock.set(true);
+    lock.set(true);
+    lock.toggle();
+    lock.set(false);
+    assert_eq!(*seen.lock().unwrap(), [true, false]);
+}
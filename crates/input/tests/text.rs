@@ -0,0 +1,96 @@
+//! Per-peer choice between typed text and physical keys, and the ASCII fallback.
+use platform_passer_core::config::{InputConfig, LayoutMode};
+use platform_passer_core::{Frame, InputEvent, InputFrame, InputSequencer};
+use platform_passer_input::text::ascii_key_events;
+use platform_passer_input::LayoutFilter;
+
+fn key(usage: u16, is_down: bool) -> InputEvent {
+    InputEvent::Keyboard { usage, is_down, hint: None }
+}
+
+/// What a source emits for typing "ä" with the key at HID usage 0x34, then an arrow key.
+fn typing() -> Vec<Frame> {
+    [InputEvent::Text("ä".into()), key(0x34, true), key(0x34, true), key(0x34, false), key(0x50, true), key(0x50, false)]
+        .into_iter()
+        .map(|event| match event {
+            // Text is not stamped
+            InputEvent::Text(_) => Frame::Input(event.into()),
+            _ => Frame::Input(InputFrame::captured(event)),
+        })
+        .collect()
+}
+
+/// What reaches the peer, numbered after the filter as sessions do.
+fn run(filter: &mut LayoutFilter, frames: Vec<Frame>) -> Vec<InputFrame> {
+    let mut sequencer = InputSequencer::new();
+    frames
+        .into_iter()
+        .filter_map(|f| filter.filter(f))
+        .map(|mut f| {
+            sequencer.number(&mut f);
+            f
+        })
+        .map(|f| match f {
+            Frame::Input(input) => input,
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn physical_mode_drops_text() {
+    let sent = run(&mut LayoutFilter::new(LayoutMode::Physical), typing());
+    assert_eq!(sent.len(), 5);
+    assert!(sent.iter().all(|f| matches!(f.event, InputEvent::Keyboard { .. })));
+    let seqs: Vec<u64> = sent.iter().map(|f| f.stamp.unwrap().seq).collect();
+    assert_eq!(seqs, [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn characters_mode_replaces_the_key_with_its_text() {
+    let sent = run(&mut LayoutFilter::new(LayoutMode::Characters), typing());
+    assert_eq!(sent.len(), 3);
+    assert!(matches!(&sent[0].event, InputEvent::Text(t) if t == "ä"));
+    assert!(matches!(sent[1].event, InputEvent::Keyboard { usage: 0x50, is_down: true, .. }));
+    assert!(matches!(sent[2].event, InputEvent::Keyboard { usage: 0x50, is_down: false, .. }));
+
+    // Numbered after the three key frames were dropped, so they leave no gap
+    let seqs: Vec<u64> = sent[1..].iter().map(|f| f.stamp.unwrap().seq).collect();
+    assert_eq!(seqs, [0, 1]);
+}
+
+#[test]
+fn key_held_across_a_mode_change_stays_replaced_until_released() {
+    let mut filter = LayoutFilter::new(LayoutMode::Characters);
+    let frames = typing();
+    let (before, after) = frames.split_at(2);
+    assert_eq!(run(&mut filter, before.to_vec()).len(), 1);
+
+    // The peer never saw the key go down, so neither the repeat nor the release is sent
+    filter.set_mode(LayoutMode::Physical);
+    let sent = run(&mut filter, after.to_vec());
+    assert_eq!(sent.len(), 2);
+    assert!(sent.iter().all(|f| matches!(f.event, InputEvent::Keyboard { usage: 0x50, .. })));
+}
+
+#[test]
+fn peers_can_override_the_layout_mode() {
+    let mut config = InputConfig { layout_mode: LayoutMode::Characters, ..InputConfig::default() };
+    config.peer_layout_modes.insert("windows-server".into(), LayoutMode::Physical);
+
+    assert_eq!(config.layout_mode_for("macos-client"), LayoutMode::Characters);
+    assert_eq!(config.layout_mode_for("windows-server"), LayoutMode::Physical);
+}
+
+#[test]
+fn ascii_fallback_presses_shift_for_shifted_characters() {
+    let events = ascii_key_events("a!é");
+    let keys: Vec<(u16, bool)> = events
+        .iter()
+        .map(|e| match e {
+            InputEvent::Keyboard { usage, is_down, .. } => (*usage, *is_down),
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(keys, [(0x04, true), (0x04, false), (0xE1, true), (0x1E, true), (0x1E, false), (0xE1, false)]);
+}
@@ -1,10 +1,11 @@
 use crate::events::{SessionEvent, LogLevel};
 use crate::commands::SessionCommand;
-use crate::log_error;
+use crate::{log_error, log_debug, log_info, log_warn};
 use anyhow::Result;
-use platform_passer_core::{Frame, ClipboardEvent, Handshake, Heartbeat};
-use platform_passer_transport::connect_ws;
-use platform_passer_input::{InputSink, DefaultInputSink, InputSource, DefaultInputSource};
+use platform_passer_core::{Frame, ClipboardEvent, Handshake, Heartbeat, CapabilitySet, FrameLimits, FrameCodec, InputConfig, InputFrame, InputSequencer, NetworkConfig, SecurityConfig, unix_time_us};
+use platform_passer_transport::tls::Trust;
+use platform_passer_transport::{connect_ws, connect_wss, FingerprintMismatch, KnownPeers, TlsMismatch};
+use platform_passer_input::{InputSink, DefaultInputSink, InputSource, DefaultInputSource, LayoutFilter, modifiers};
 use platform_passer_clipboard::{ClipboardProvider, DefaultClipboard};
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -12,12 +13,16 @@ use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc::{self, Sender, Receiver};
 use std::time::Duration;
 use crate::clipboard_utils::{LocalClipboardContent, calculate_hash};
-use tokio_tungstenite::tungstenite::Message;
+use crate::input_stats::InputLatencyTracker;
+use crate::latency::RttTracker;
+use crate::pairing::{self, AuthLink, DeviceTrust, Refused};
+use crate::policy::Denials;
+use crate::transfer::IncomingFiles;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
 use futures_util::{StreamExt, SinkExt};
 use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
 use platform_passer_core::{FileManifest, FileMeta, TransferPurpose};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 enum SessionInternalMsg {
     SendClipboardFiles { batch_id: u64, files: Vec<PathBuf> },
@@ -136,26 +141,55 @@ pub async fn run_client_session(
     // Capture input
     let input_tx = local_tx.clone();
     let input_log = event_tx.clone();
+    let state_source = Arc::downgrade(&source);
     if let Err(e) = source.start_capture(Box::new(move |event| {
+        let snapshot_due = modifiers::snapshot_due(&event);
+        // Numbered per connection, as it is sent
+        let frame = Frame::Input(InputFrame::captured(event));
         // Use try_send to avoid blocking the input hook thread
-        if input_tx.try_send(Frame::Input(event)).is_err() {
+        if input_tx.try_send(frame).is_err() {
             // Log once in a while or just ignore overflow for MouseMove
         }
+        if snapshot_due {
+            if let Some(state) = state_source.upgrade().and_then(|s| s.modifier_state()) {
+                let _ = input_tx.try_send(Frame::ModifierState(state));
+            }
+        }
     })) {
         log_error!(&input_log, "Failed to start input capture: {}", e);
     }
     
     let _ = source.set_remote(false);
 
+    // The lock lives in the platform hooks and would otherwise outlast a previous session
+    let _ = source.set_screen_lock(false);
+    let lock_tx = event_tx.clone();
+    source.on_screen_lock_changed(Box::new(move |locked| {
+        let _ = lock_tx.try_send(SessionEvent::ScreenLock { locked });
+    }));
+
+    let mut frame_limits = FrameLimits::default();
+    let mut network = NetworkConfig::default();
+    let mut input_config = InputConfig::default();
+    let mut security = SecurityConfig::default();
+    let mut device_name = platform_passer_core::config::host_name().to_string();
     let mut backoff = Duration::from_secs(1);
-    let max_backoff = Duration::from_secs(30);
+    let mut known_peers = KnownPeers::load(platform_passer_core::config::config_dir().join("known_peers"))?;
+    let trust = DeviceTrust::load()?;
+    let mut deferred = VecDeque::new();
 
     // Initial Connecting state
     let _ = event_tx.send(SessionEvent::Connecting(server_addr.to_string())).await;
 
     loop {
         // Enforce timeout on connection attempt
-        let connect_fut = tokio::time::timeout(Duration::from_secs(5), connect_ws(server_addr));
+        let connect_fut = tokio::time::timeout(Duration::from_secs(5), async {
+            if network.tls {
+                connect_wss(server_addr, &frame_limits).await
+            } else {
+                connect_ws(server_addr, &frame_limits).await
+            }
+        });
         let stream_result = tokio::select! {
             res = connect_fut => {
                 match res {
@@ -163,64 +197,178 @@ pub async fn run_client_session(
                     Err(_) => Err(anyhow::anyhow!("Connection timed out")),
                 }
             },
-            Some(cmd) = cmd_rx.recv() => {
-                if matches!(cmd, SessionCommand::Disconnect) { return Ok(()); }
+            Some(cmd) = next_command(&mut deferred, &mut cmd_rx) => {
+                match cmd {
+                    SessionCommand::Disconnect => return Ok(()),
+                    SessionCommand::UpdateConfig(config) => {
+                        frame_limits = config.frame_limits;
+                        network = config.network;
+                        input_config = config.input.clone();
+                        security = config.security.clone();
+                        device_name = config.name_or_host();
+                        let _ = sink.update_config((*config).clone());
+                        let _ = source.update_config(*config);
+                    }
+                    SessionCommand::SetScreenLock(locked) => {
+                        let _ = source.set_screen_lock(locked);
+                    }
+                    SessionCommand::SendFile(_) | SessionCommand::ConfirmPairing { .. } => {}
+                }
                 continue; 
             }
         };
 
         match stream_result {
             Ok(ws_stream) => {
-                backoff = Duration::from_secs(1);
                 let _ = event_tx.send(SessionEvent::Connected(server_addr.to_string())).await;
 
+                let presented = ws_stream.get_ref().peer_fingerprint();
                 let (mut ws_sink, mut ws_stream) = ws_stream.split();
+                let mut codec = FrameCodec::new(frame_limits);
                 let clip = DefaultClipboard::new();
                 
                 // Handshake
                 let screen_info = {
-                    #[cfg(target_os = "macos")] { platform_passer_input::get_screen_info() }
-                    #[cfg(not(target_os = "macos"))] { None }
+                    #[cfg(any(target_os = "macos", target_os = "windows"))] { platform_passer_input::get_screen_info() }
+                    #[cfg(not(any(target_os = "macos", target_os = "windows")))] { None }
                 };
 
-                let handshake = Frame::Handshake(Handshake {
-                    version: 1,
-                    client_id: format!("{}-client", std::env::consts::OS),
-                    capabilities: vec!["input".to_string(), "clipboard".to_string()],
-                    screen_info,
-                });
+                let local_handshake = Handshake::new(trust.id(), CapabilitySet::all(), screen_info).with_device_name(device_name.clone());
 
-                if let Err(e) = ws_sink.send(Message::Binary(bincode::serialize(&handshake)?)).await {
+                if let Err(e) = ws_sink.send(Message::Binary(codec.encode_message(&Frame::Handshake(local_handshake.clone()))?)).await {
                     log_error!(&event_tx, "Handshake send failed: {}", e);
+                    back_off(&event_tx, server_addr, &mut backoff).await;
                     continue;
                 }
 
-                let mut active_files: HashMap<u32, File> = HashMap::new();
+                // Wait for the server's handshake (or rejection) before exchanging anything else
+                let reply = tokio::time::timeout(Duration::from_secs(5), ws_stream.next()).await;
+                let server_handshake = match reply {
+                    Ok(Some(Ok(Message::Binary(bytes)))) => match codec.decode_message(&bytes) {
+                        Ok(Frame::Handshake(h)) => h,
+                        Ok(Frame::HandshakeReject(reason)) => {
+                            let _ = event_tx.send(SessionEvent::Error(format!("Server rejected handshake: {}", reason))).await;
+                            return Err(anyhow::anyhow!("Handshake rejected: {}", reason));
+                        }
+                        _ => {
+                            log_error!(&event_tx, "Invalid handshake reply from server");
+                            back_off(&event_tx, server_addr, &mut backoff).await;
+                            continue;
+                        }
+                    },
+                    _ => {
+                        log_error!(&event_tx, "No handshake reply from server");
+                        back_off(&event_tx, server_addr, &mut backoff).await;
+                        continue;
+                    }
+                };
+
+                let protocol_version = match local_handshake.negotiate_version(&server_handshake) {
+                    Ok(v) => v,
+                    Err(reason) => {
+                        let _ = event_tx.send(SessionEvent::Error(format!("Incompatible server ({}): {}", server_handshake.client_id, reason))).await;
+                        return Err(anyhow::anyhow!("Handshake failed: {}", reason));
+                    }
+                };
+                let capabilities = local_handshake.capabilities.intersection(&server_handshake.capabilities);
+
+                // Device authentication: input flows neither way until the two devices are paired
+                let admitted = {
+                    let mut link = AuthLink { sink: &mut ws_sink, stream: &mut ws_stream, codec: &codec, version: protocol_version };
+                    let secret = security.pre_shared_key();
+                    let admission = async {
+                        let device = pairing::authenticate_server(&mut link, &trust, &server_handshake).await?;
+                        if let Some(presented) = &presented {
+                            check_pin(&mut known_peers, server_addr, &device.id, presented)?;
+                        }
+                        pairing::await_admission(&mut link, &trust, &device, server_addr, &event_tx, secret.as_deref()).await?;
+                        Ok::<_, anyhow::Error>(device)
+                    };
+                    tokio::pin!(admission);
+                    // The user confirms a pairing from here; other commands wait for the session
+                    loop {
+                        tokio::select! {
+                            admitted = &mut admission => break admitted,
+                            Some(cmd) = cmd_rx.recv() => match cmd {
+                                SessionCommand::ConfirmPairing { addr, code } => match trust.confirm(&addr, code.as_deref()) {
+                                    Some(false) if code.is_some() => log_warn!(&event_tx, "Wrong pairing code for {}", addr),
+                                    None => log_warn!(&event_tx, "No pairing with {} is waiting to be confirmed", addr),
+                                    _ => {}
+                                },
+                                SessionCommand::Disconnect => return Ok(()),
+                                cmd => deferred.push_back(cmd),
+                            },
+                        }
+                    }
+                };
+                let server = match admitted {
+                    Ok(device) => device,
+                    Err(e) if e.downcast_ref::<Refused>().is_some() || e.downcast_ref::<FingerprintMismatch>().is_some() => {
+                        // Declined pairing, a key mismatch or a changed certificate; retrying would only fail again
+                        let _ = event_tx.send(SessionEvent::Error(format!("Not connected to {}: {}", server_handshake.client_id, e))).await;
+                        return Err(e);
+                    }
+                    Err(e) => {
+                        log_error!(&event_tx, "Device authentication with {} failed: {}", server_handshake.client_id, e);
+                        back_off(&event_tx, server_addr, &mut backoff).await;
+                        continue;
+                    }
+                };
+                backoff = Duration::from_secs(1);
+                log_info!(&event_tx, "Connected to {} [{}]", server.name, server.id);
+                if let Some(info) = server_handshake.screen_info.clone() {
+                    let _ = source.set_peer_screen(&server.id, Some(info));
+                }
+                let _ = event_tx.send(SessionEvent::ProtocolNegotiated { peer: server.id.clone(), name: server.name.clone(), version: protocol_version, capabilities }).await;
+
+                let mut input_stats = InputLatencyTracker::new();
+                let mut sequencer = InputSequencer::new();
+                let mut rtt = RttTracker::new();
+                let mut active_files = IncomingFiles::new();
                 let mut pending_sends: HashMap<u32, PathBuf> = HashMap::new();
                 let mut incoming_batches: HashMap<u64, (usize, Vec<PathBuf>)> = HashMap::new();
                 let mut active_downloads: HashMap<u32, (u64, PathBuf)> = HashMap::new();
                 let mut file_id_counter = 0u32;
                 
-                let (hb_stop_tx, mut hb_stop_rx) = mpsc::channel::<()>(1);
-                let hb_local_tx = local_tx.clone();
-                tokio::spawn(async move {
-                    loop {
-                        tokio::select! {
-                            _ = tokio::time::sleep(Duration::from_secs(5)) => {
-                                let hb = Frame::Heartbeat(Heartbeat {
-                                    timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(),
-                                });
-                                if hb_local_tx.send(hb).await.is_err() { break; }
-                            }
-                            _ = hb_stop_rx.recv() => { break; }
-                        }
-                    }
-                });
+                let mut layout = LayoutFilter::new(input_config.layout_mode_for(&server.id));
+                let mut policy = security.policy_for(&server.id);
+                let mut denials = Denials::new(server.name.clone());
+                let mut hb_interval = tokio::time::interval(network.heartbeat_interval());
+                let mut unanswered_heartbeats = 0u32;
+                let mut last_rx = tokio::time::Instant::now();
 
                 loop {
                     tokio::select! {
+                        _ = hb_interval.tick() => {
+                            if unanswered_heartbeats >= network.missed_heartbeats.max(1) {
+                                log_error!(&event_tx, "Server missed {} heartbeats.", unanswered_heartbeats);
+                                break;
+                            }
+                            unanswered_heartbeats += 1;
+                            if let Err(e) = ws_sink.send(Message::Binary(codec.encode_message(&Frame::Heartbeat(Heartbeat::ping()))?)).await {
+                                log_error!(&event_tx, "Send failed: {}", e);
+                                break;
+                            }
+                        }
                         Some(frame) = local_rx.recv() => {
-                            let bytes = bincode::serialize(&frame)?;
+                            if !capabilities.allows(&frame) {
+                                continue;
+                            }
+                            if let Err(denial) = policy.check_outgoing(&frame) {
+                                denials.record(&event_tx, denial).await;
+                                if let Frame::FileTransferRequest(req) = &frame {
+                                    pending_sends.remove(&req.id);
+                                }
+                                continue;
+                            }
+                            let Some(frame) = layout.filter(frame) else {
+                                continue;
+                            };
+                            let Some(mut frame) = frame.for_version(protocol_version) else {
+                                continue;
+                            };
+                            sequencer.number(&mut frame);
+                            let bytes = codec.encode_message(&frame)?;
                             if let Err(e) = ws_sink.send(Message::Binary(bytes)).await {
                                 log_error!(&event_tx, "Send failed: {}", e);
                                 break;
@@ -246,109 +394,158 @@ pub async fn run_client_session(
                                 }
                             }
                         }
-                        msg_opt = tokio::time::timeout(Duration::from_secs(15), ws_stream.next()) => {
+                        msg_opt = tokio::time::timeout_at(last_rx + network.read_timeout(), ws_stream.next()) => {
+                            if let Ok(Some(Ok(_))) = &msg_opt {
+                                last_rx = tokio::time::Instant::now();
+                            }
                             match msg_opt {
                                 Ok(Some(Ok(Message::Binary(bytes)))) => {
-                                    if let Ok(frame) = bincode::deserialize::<Frame>(&bytes) {
-                                        match frame {
-                                            Frame::Input(event) => {
-                                                match event {
-                                                    platform_passer_core::InputEvent::ScreenSwitch(_) => {
-                                                        // When Client receives focus, ensure it stays in Local mode (not swallowing)
-                                                        let _ = sink.reset_input(); 
-                                                        let _ = source.set_remote(false);
-                                                    }
-                                                    _ => { let _ = sink.inject_event(event); }
-                                                }
-                                            }
-                                            Frame::Clipboard(ClipboardEvent::Text(text)) => {
-                                                if let Ok(mut lock) = last_remote_clip.lock() {
-                                                    *lock = Some(LocalClipboardContent::Text(text.clone()));
+                                    let frame = match codec.decode_message(&bytes) {
+                                        Ok(frame) => frame,
+                                        Err(e) if e.is_recoverable() => {
+                                            log_debug!(&event_tx, "Skipping frame: {}", e);
+                                            continue;
+                                        }
+                                        Err(e) => {
+                                            let _ = event_tx.send(SessionEvent::ProtocolViolation { peer: server.id.clone(), reason: e.to_string() }).await;
+                                            break;
+                                        }
+                                    };
+                                    if !capabilities.allows(&frame) {
+                                        log_debug!(&event_tx, "Ignoring frame outside negotiated capabilities: {:?}", frame.capability());
+                                        continue;
+                                    }
+                                    if let Err(denial) = policy.check_incoming(&frame) {
+                                        denials.record(&event_tx, denial).await;
+                                        // Declined, so the server does not wait on it
+                                        if let Frame::FileTransferRequest(req) = frame {
+                                            let _ = ws_sink.send(Message::Binary(codec.encode_message(&Frame::FileTransferResponse(platform_passer_core::FileTransferResponse { id: req.id, accepted: false }))?)).await;
+                                        }
+                                        continue;
+                                    }
+                                    match frame {
+                                        Frame::Input(InputFrame { event, stamp }) => {
+                                            if let Some(stamp) = stamp {
+                                                input_stats.record(&stamp, unix_time_us());
+                                                if let Some(report) = input_stats.take_report_if_due() {
+                                                    let _ = event_tx.send(SessionEvent::InputLatency { peer: server.id.clone(), report }).await;
                                                 }
-                                                let _ = clip.set_text(text);
                                             }
-                                            Frame::Clipboard(ClipboardEvent::Image { data }) => {
-                                                let hash = calculate_hash(&data);
-                                                if let Ok(mut lock) = last_remote_clip.lock() {
-                                                    *lock = Some(LocalClipboardContent::Image(hash));
+                                            match event {
+                                                platform_passer_core::InputEvent::ScreenSwitch(_) => {
+                                                    // When Client receives focus, ensure it stays in Local mode (not swallowing)
+                                                    let _ = sink.reset_input(); 
+                                                    let _ = source.set_remote(false);
                                                 }
-                                                let _ = clip.set_image(data);
+                                                _ => { let _ = sink.inject_event(event); }
                                             }
-                                            Frame::Clipboard(ClipboardEvent::Files { manifest }) => {
-                                                incoming_batches.insert(manifest.batch_id, (manifest.files.len(), Vec::new()));
+                                        }
+                                        Frame::ModifierState(state) => {
+                                            let _ = sink.sync_modifiers(&state);
+                                        }
+                                        Frame::Clipboard(ClipboardEvent::Text(text)) => {
+                                            if let Ok(mut lock) = last_remote_clip.lock() {
+                                                *lock = Some(LocalClipboardContent::Text(text.clone()));
                                             }
-                                            Frame::Notification { title, message } => {
-                                                let _ = event_tx.send(SessionEvent::Log { level: LogLevel::Info, message: format!("Remote Notification: {} - {}", title, message) }).await;
+                                            let _ = clip.set_text(text);
+                                        }
+                                        Frame::Clipboard(ClipboardEvent::Image { data }) => {
+                                            let hash = calculate_hash(&data);
+                                            if let Ok(mut lock) = last_remote_clip.lock() {
+                                                *lock = Some(LocalClipboardContent::Image(hash));
                                             }
-                                            Frame::FileTransferRequest(req) => {
-                                                let (should_dload, save_dir, batch_id_opt) = match req.purpose {
-                                                    TransferPurpose::Manual => (true, std::path::PathBuf::from("downloads"), None),
-                                                    TransferPurpose::ClipboardSync { batch_id } => {
-                                                        (true, std::env::temp_dir().join(format!("platform_passer_clip_{}", batch_id)), Some(batch_id))
+                                            let _ = clip.set_image(data);
+                                        }
+                                        Frame::Clipboard(ClipboardEvent::Files { manifest }) => {
+                                            incoming_batches.insert(manifest.batch_id, (manifest.files.len(), Vec::new()));
+                                        }
+                                        Frame::Notification { title, message } => {
+                                            let _ = event_tx.send(SessionEvent::Log { level: LogLevel::Info, message: format!("Remote Notification: {} - {}", title, message) }).await;
+                                        }
+                                        Frame::FileTransferRequest(req) => {
+                                            let (should_dload, save_dir, batch_id_opt) = match req.purpose {
+                                                TransferPurpose::Manual => (true, std::path::PathBuf::from("downloads"), None),
+                                                TransferPurpose::ClipboardSync { batch_id } => {
+                                                    (true, std::env::temp_dir().join(format!("platform_passer_clip_{}", batch_id)), Some(batch_id))
+                                                }
+                                            };
+                                            if should_dload {
+                                                let _ = tokio::fs::create_dir_all(&save_dir).await;
+                                                let file_path = save_dir.join(&req.filename);
+                                                match File::create(&file_path).await {
+                                                    Ok(file) => {
+                                                        active_files.start(req.id, file, file_path.clone(), req.file_size, policy.max_transfer_bytes);
+                                                        if let Some(bid) = batch_id_opt { active_downloads.insert(req.id, (bid, file_path)); }
+                                                        let _ = ws_sink.send(Message::Binary(codec.encode_message(&Frame::FileTransferResponse(platform_passer_core::FileTransferResponse { id: req.id, accepted: true }))?)).await;
                                                     }
-                                                };
-                                                if should_dload {
-                                                    let _ = tokio::fs::create_dir_all(&save_dir).await;
-                                                    let file_path = save_dir.join(&req.filename);
-                                                    match File::create(&file_path).await {
-                                                        Ok(file) => {
-                                                            active_files.insert(req.id, file);
-                                                            if let Some(bid) = batch_id_opt { active_downloads.insert(req.id, (bid, file_path)); }
-                                                            let _ = ws_sink.send(Message::Binary(bincode::serialize(&Frame::FileTransferResponse(platform_passer_core::FileTransferResponse { id: req.id, accepted: true }))?)).await;
-                                                        }
-                                                        Err(_) => {
-                                                            let _ = ws_sink.send(Message::Binary(bincode::serialize(&Frame::FileTransferResponse(platform_passer_core::FileTransferResponse { id: req.id, accepted: false }))?)).await;
-                                                        }
+                                                    Err(_) => {
+                                                        let _ = ws_sink.send(Message::Binary(codec.encode_message(&Frame::FileTransferResponse(platform_passer_core::FileTransferResponse { id: req.id, accepted: false }))?)).await;
                                                     }
                                                 }
                                             }
-                                            Frame::FileData { id, chunk } => {
-                                                if let Some(file) = active_files.get_mut(&id) { let _ = file.write_all(&chunk).await; }
+                                        }
+                                        Frame::FileData { id, chunk } => {
+                                            if let Err(overrun) = active_files.write(id, &chunk).await {
+                                                log_warn!(&event_tx, "Aborted {:?} from {}: {}", overrun.path, server.name, overrun);
+                                                let _ = event_tx.send(SessionEvent::TransferFailed { id, reason: overrun.to_string() }).await;
+                                                // A clipboard batch missing a file can never complete
+                                                if let Some((batch_id, _)) = active_downloads.remove(&id) { incoming_batches.remove(&batch_id); }
                                             }
-                                            Frame::FileEnd { id } => {
-                                                if let Some(mut file) = active_files.remove(&id) {
-                                                    let _ = file.flush().await;
-                                                    if let Some((batch_id, path)) = active_downloads.remove(&id) {
-                                                        if let Some((remaining, paths)) = incoming_batches.get_mut(&batch_id) {
-                                                            paths.push(path);
-                                                            *remaining -= 1;
-                                                            if *remaining == 0 {
-                                                                let final_paths: Vec<String> = paths.iter().map(|p| p.to_string_lossy().to_string()).collect();
-                                                                let mut hasher = std::collections::hash_map::DefaultHasher::new();
-                                                                use std::hash::Hash; use std::hash::Hasher;
-                                                                final_paths.hash(&mut hasher);
-                                                                if let Ok(mut lock) = last_remote_clip.lock() { *lock = Some(LocalClipboardContent::Files(hasher.finish())); }
-                                                                let _ = clip.set_files(final_paths);
-                                                                incoming_batches.remove(&batch_id);
-                                                            }
+                                        }
+                                        Frame::FileEnd { id } => {
+                                            let finished = active_files.finish(id).await;
+                                            if finished {
+                                                if let Some((batch_id, path)) = active_downloads.remove(&id) {
+                                                    if let Some((remaining, paths)) = incoming_batches.get_mut(&batch_id) {
+                                                        paths.push(path);
+                                                        *remaining -= 1;
+                                                        if *remaining == 0 {
+                                                            let final_paths: Vec<String> = paths.iter().map(|p| p.to_string_lossy().to_string()).collect();
+                                                            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                                                            use std::hash::Hash; use std::hash::Hasher;
+                                                            final_paths.hash(&mut hasher);
+                                                            if let Ok(mut lock) = last_remote_clip.lock() { *lock = Some(LocalClipboardContent::Files(hasher.finish())); }
+                                                            let _ = clip.set_files(final_paths);
+                                                            incoming_batches.remove(&batch_id);
                                                         }
                                                     }
                                                 }
                                             }
-                                            Frame::FileTransferResponse(resp) => {
-                                                if resp.accepted {
-                                                    if let Some(path) = pending_sends.remove(&resp.id) {
-                                                        let local_tx_file = local_tx.clone();
-                                                        let file_id = resp.id;
-                                                        tokio::spawn(async move {
-                                                            if let Ok(mut file) = tokio::fs::File::open(&path).await {
-                                                                let mut buffer = vec![0u8; 65536];
-                                                                while let Ok(n) = tokio::io::AsyncReadExt::read(&mut file, &mut buffer).await {
-                                                                    if n == 0 { break; }
-                                                                    let _ = local_tx_file.send(Frame::FileData { id: file_id, chunk: buffer[..n].to_vec() }).await;
-                                                                }
-                                                                let _ = local_tx_file.send(Frame::FileEnd { id: file_id }).await;
+                                        }
+                                        Frame::FileTransferResponse(resp) => {
+                                            if resp.accepted {
+                                                if let Some(path) = pending_sends.remove(&resp.id) {
+                                                    let local_tx_file = local_tx.clone();
+                                                    let file_id = resp.id;
+                                                    tokio::spawn(async move {
+                                                        if let Ok(mut file) = tokio::fs::File::open(&path).await {
+                                                            let mut buffer = vec![0u8; 65536];
+                                                            while let Ok(n) = tokio::io::AsyncReadExt::read(&mut file, &mut buffer).await {
+                                                                if n == 0 { break; }
+                                                                let _ = local_tx_file.send(Frame::FileData { id: file_id, chunk: buffer[..n].to_vec() }).await;
                                                             }
-                                                        });
-                                                    }
-                                                } else { pending_sends.remove(&resp.id); }
+                                                            let _ = local_tx_file.send(Frame::FileEnd { id: file_id }).await;
+                                                        }
+                                                    });
+                                                }
+                                            } else if let Some(path) = pending_sends.remove(&resp.id) {
+                                                let _ = event_tx.send(SessionEvent::TransferFailed { id: resp.id, reason: format!("{} declined {:?}", server.name, path) }).await;
                                             }
-                                            Frame::Heartbeat(hb) => { let _ = ws_sink.send(Message::Binary(bincode::serialize(&Frame::Heartbeat(hb))?)).await; }
-                                            _ => {}
                                         }
+                                        Frame::Heartbeat(hb) if hb.reply => {
+                                            unanswered_heartbeats = 0;
+                                            let sample = rtt.record(hb.timestamp, unix_time_us());
+                                            let _ = event_tx.send(SessionEvent::Latency { peer: server.id.clone(), rtt_ms: sample.rtt_ms, jitter_ms: sample.jitter_ms, quality: sample.quality }).await;
+                                        }
+                                        Frame::Heartbeat(hb) => { let _ = ws_sink.send(Message::Binary(codec.encode_message(&Frame::Heartbeat(hb.to_reply()))?)).await; }
+                                        _ => {}
                                     }
                                 }
                                 Ok(Some(Ok(Message::Close(_)))) | Ok(None) => break,
+                                Ok(Some(Err(WsError::Capacity(e)))) => {
+                                    let _ = event_tx.send(SessionEvent::ProtocolViolation { peer: server.id.clone(), reason: e.to_string() }).await;
+                                    break;
+                                }
                                 Ok(Some(Err(e))) => {
                                     log_error!(&event_tx, "WebSocket Error: {}", e);
                                     break;
@@ -360,7 +557,7 @@ pub async fn run_client_session(
                                 _ => {}
                             }
                         }
-                        Some(cmd) = cmd_rx.recv() => {
+                        Some(cmd) = next_command(&mut deferred, &mut cmd_rx) => {
                             match cmd {
                                 SessionCommand::SendFile(path) => {
                                     if path.exists() {
@@ -377,29 +574,73 @@ pub async fn run_client_session(
                                     }
                                 },
                                 SessionCommand::Disconnect => {
-                                    let _ = hb_stop_tx.send(()).await;
                                     let _ = ws_sink.close().await;
+                                    let _ = sink.reset_input();
                                     return Ok(());
                                 },
                                 SessionCommand::UpdateConfig(config) => {
-                                    let _ = sink.update_config(config.clone());
-                                    let _ = source.update_config(config);
+                                    frame_limits = config.frame_limits;
+                                    codec.set_limits(frame_limits);
+                                    network = config.network;
+                                    hb_interval = tokio::time::interval(network.heartbeat_interval());
+                                    input_config = config.input.clone();
+                                    security = config.security.clone();
+                                    policy = security.policy_for(&server.id);
+                                    device_name = config.name_or_host();
+                                    layout.set_mode(input_config.layout_mode_for(&server.id));
+                                    let _ = sink.update_config((*config).clone());
+                                    let _ = source.update_config(*config);
                                 },
+                                SessionCommand::SetScreenLock(locked) => {
+                                    let _ = source.set_screen_lock(locked);
+                                },
+                                SessionCommand::ConfirmPairing { .. } => {}
                             }
                         }
                     }
                 }
                 let _ = source.set_remote(false);
+                let _ = source.set_peer_screen(&server.id, None);
                 let _ = sink.reset_input();
-                let _ = hb_stop_tx.send(()).await;
                 // Don't send Disconnected here, we will Reconnect
                 // let _ = event_tx.send(SessionEvent::Disconnected).await;
             }
-            Err(_) => { 
-                let _ = event_tx.send(SessionEvent::Reconnecting(server_addr.to_string())).await;
-                tokio::time::sleep(backoff).await; 
-                backoff = std::cmp::min(backoff * 2, max_backoff); 
+            Err(e) if e.downcast_ref::<TlsMismatch>().is_some() => {
+                // Retrying would only fail again until one end changes its config
+                let _ = event_tx.send(SessionEvent::Error(e.to_string())).await;
+                return Err(e);
             }
+            Err(_) => back_off(&event_tx, server_addr, &mut backoff).await,
         }
     }
 }
+
+/// Checks the certificate the server presented against the one pinned for its device ID,
+/// pinning it on first contact. A pin made by address before pins were kept by device ID moves
+/// to the device that now answers there.
+fn check_pin(known_peers: &mut KnownPeers, server_addr: SocketAddr, device: &str, presented: &str) -> Result<()> {
+    if known_peers.rename(&server_addr.to_string(), device)? {
+        tracing::info!("Moved the certificate pin of {} to device {}", server_addr, device);
+    }
+    match known_peers.verify(device, presented)? {
+        Trust::Pinned => tracing::warn!("First TLS connection to {}: pinned certificate {}", device, presented),
+        Trust::Known => tracing::debug!("TLS certificate of {} matches its pin", device),
+    }
+    Ok(())
+}
+
+/// Reports the retry and waits `backoff` before it, doubling the wait for the next one up to
+/// 30 seconds. The wait resets once the server has let this device in.
+async fn back_off(event_tx: &Sender<SessionEvent>, server_addr: SocketAddr, backoff: &mut Duration) {
+    let _ = event_tx.send(SessionEvent::Reconnecting(server_addr.to_string())).await;
+    tokio::time::sleep(*backoff).await;
+    *backoff = std::cmp::min(*backoff * 2, Duration::from_secs(30));
+}
+
+/// The next command, starting with those that arrived while the client was pairing.
+async fn next_command(deferred: &mut VecDeque<SessionCommand>, cmd_rx: &mut Receiver<SessionCommand>) -> Option<SessionCommand> {
+    match deferred.pop_front() {
+        Some(cmd) => Some(cmd),
+        None => cmd_rx.recv().await,
+    }
+}
@@ -4,6 +4,11 @@ use std::path::PathBuf;
 #[derive(Debug, Clone)]
 pub enum SessionCommand {
     SendFile(PathBuf),
-    UpdateConfig(AppConfig),
+    UpdateConfig(Box<AppConfig>),
+    /// Pins the cursor to the current screen, or releases it.
+    SetScreenLock(bool),
+    /// Answers a `SessionEvent::PairingRequest` or `PairingCode` for `addr` with the code the
+    /// user entered, or declines it with `None`.
+    ConfirmPairing { addr: String, code: Option<String> },
     Disconnect,
 }
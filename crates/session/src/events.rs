@@ -1,3 +1,7 @@
+use platform_passer_core::CapabilitySet;
+use crate::input_stats::InputLatencyReport;
+use crate::latency::LinkQuality;
+
 #[derive(Debug, Clone, Copy, serde::Serialize)]
 pub enum LogLevel {
     Trace,
@@ -14,6 +18,27 @@ pub enum SessionEvent {
     Connecting(String), // Target Address
     Reconnecting(String), // Target Address
     Connected(String), // Remote Address
+    /// A peer proved its device key and was let in. `peer` is its device ID, which names it in
+    /// every other event too, and `name` what its user calls it.
+    ProtocolNegotiated { peer: String, name: String, version: u32, capabilities: CapabilitySet },
+    /// Heartbeat round trip to `peer`, averaged over the last few samples.
+    Latency { peer: String, rtt_ms: f64, jitter_ms: f64, quality: LinkQuality },
+    /// Input latency and loss observed from `peer` over the last reporting window.
+    InputLatency { peer: String, report: InputLatencyReport },
     Disconnected,
+    /// The cursor was pinned to, or released from, the current screen.
+    ScreenLock { locked: bool },
+    /// A device that is not paired yet wants to connect from `addr`. Answer with
+    /// `SessionCommand::ConfirmPairing` and the code it shows.
+    PairingRequest { peer: String, name: String, addr: String },
+    /// `peer` at `addr` does not know this device yet: show `code` so its user can enter it
+    /// there. Once it has accepted, answer with `SessionCommand::ConfirmPairing` and the code.
+    PairingCode { peer: String, name: String, addr: String, code: String },
+    /// `peer` is now trusted, and will be let in without a code from now on.
+    Paired { peer: String, name: String },
+    /// The peer sent malformed or oversized data and was disconnected.
+    ProtocolViolation { peer: String, reason: String },
+    /// File transfer `id` will not complete: the peer declined it, or sent more than it may.
+    TransferFailed { id: u32, reason: String },
     Error(String),
 }
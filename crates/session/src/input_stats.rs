@@ -0,0 +1,136 @@
+//! Per-peer input latency, loss and ordering statistics, built from `InputStamp`s.
+//!
+//! Latency is the receive time minus the sender's capture time, so it is only as accurate as
+//! the clock agreement between the two machines. Negative values (receiver clock behind) count as 0.
+use platform_passer_core::InputStamp;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// Upper bounds (inclusive) of the histogram buckets, in milliseconds. A final bucket counts the rest.
+pub const LATENCY_BUCKETS_MS: [u64; 8] = [1, 2, 5, 10, 20, 50, 100, 250];
+
+/// How often a report is emitted while input is flowing.
+pub const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many skipped sequence numbers are remembered, so a late arrival can be told from a
+/// duplicate. Older gaps stay counted as lost.
+const MAX_TRACKED_GAPS: usize = 1024;
+
+/// Input statistics for one reporting window.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct InputLatencyReport {
+    pub samples: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// `buckets[i]` counts samples up to `LATENCY_BUCKETS_MS[i]`; the last entry counts the rest.
+    pub buckets: Vec<u64>,
+    /// Sequence numbers skipped and not seen (yet) in this window.
+    pub lost: u64,
+    /// Frames that arrived after a later sequence number.
+    pub out_of_order: u64,
+}
+
+impl InputLatencyReport {
+    /// Share of samples known to be at or under `ms`, at bucket resolution.
+    pub fn fraction_within_ms(&self, ms: u64) -> f64 {
+        if self.samples == 0 {
+            return 1.0;
+        }
+        let within: u64 = LATENCY_BUCKETS_MS
+            .iter()
+            .zip(&self.buckets)
+            .filter(|(bound, _)| **bound <= ms)
+            .map(|(_, count)| count)
+            .sum();
+        within as f64 / self.samples as f64
+    }
+}
+
+pub struct InputLatencyTracker {
+    next_seq: Option<u64>,
+    /// Skipped sequence numbers that may still arrive.
+    missing: BTreeSet<u64>,
+    buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    samples: u64,
+    total_us: u64,
+    max_us: u64,
+    lost: u64,
+    out_of_order: u64,
+    window_start: Instant,
+}
+
+impl Default for InputLatencyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputLatencyTracker {
+    pub fn new() -> Self {
+        Self {
+            next_seq: None,
+            missing: BTreeSet::new(),
+            buckets: [0; LATENCY_BUCKETS_MS.len() + 1],
+            samples: 0,
+            total_us: 0,
+            max_us: 0,
+            lost: 0,
+            out_of_order: 0,
+            window_start: Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, stamp: &InputStamp, received_at_us: u64) {
+        match self.next_seq {
+            Some(expected) if stamp.seq < expected => {
+                // Counted as lost when the gap was seen; it arrived after all. Anything else
+                // already arrived, and a duplicate is neither lost nor out of order.
+                if self.missing.remove(&stamp.seq) {
+                    self.out_of_order += 1;
+                    self.lost = self.lost.saturating_sub(1);
+                }
+            }
+            Some(expected) => {
+                self.lost += stamp.seq - expected;
+                self.missing.extend(stamp.seq.saturating_sub(MAX_TRACKED_GAPS as u64).max(expected)..stamp.seq);
+                while self.missing.len() > MAX_TRACKED_GAPS {
+                    self.missing.pop_first();
+                }
+                self.next_seq = Some(stamp.seq + 1);
+            }
+            None => self.next_seq = Some(stamp.seq + 1),
+        }
+
+        let latency_us = received_at_us.saturating_sub(stamp.captured_at_us);
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound_ms| latency_us <= bound_ms * 1000)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        self.samples += 1;
+        self.total_us += latency_us;
+        self.max_us = self.max_us.max(latency_us);
+    }
+
+    /// The statistics of the current window so far.
+    pub fn report(&self) -> InputLatencyReport {
+        InputLatencyReport {
+            samples: self.samples,
+            mean_ms: if self.samples == 0 { 0.0 } else { self.total_us as f64 / self.samples as f64 / 1000.0 },
+            max_ms: self.max_us as f64 / 1000.0,
+            buckets: self.buckets.to_vec(),
+            lost: self.lost,
+            out_of_order: self.out_of_order,
+        }
+    }
+
+    /// Returns the current window and starts a new one once `REPORT_INTERVAL` has passed.
+    pub fn take_report_if_due(&mut self) -> Option<InputLatencyReport> {
+        if self.samples == 0 || self.window_start.elapsed() < REPORT_INTERVAL {
+            return None;
+        }
+        let report = self.report();
+        *self = Self { next_seq: self.next_seq, missing: std::mem::take(&mut self.missing), ..Self::new() };
+        Some(report)
+    }
+}
@@ -0,0 +1,79 @@
+//! Round-trip time measured from heartbeat echoes.
+use std::collections::VecDeque;
+
+/// Number of recent samples the moving average and jitter are taken over.
+pub const RTT_WINDOW: usize = 8;
+
+/// Link quality bands from the PRD: green under 20 ms, yellow up to 50 ms, red above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum LinkQuality {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl LinkQuality {
+    pub fn from_rtt_ms(rtt_ms: f64) -> Self {
+        if rtt_ms < 20.0 {
+            LinkQuality::Green
+        } else if rtt_ms <= 50.0 {
+            LinkQuality::Yellow
+        } else {
+            LinkQuality::Red
+        }
+    }
+}
+
+impl std::fmt::Display for LinkQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LinkQuality::Green => "green",
+            LinkQuality::Yellow => "yellow",
+            LinkQuality::Red => "red",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RttSample {
+    /// Moving average over the last `RTT_WINDOW` round trips.
+    pub rtt_ms: f64,
+    /// Mean absolute difference between consecutive round trips in the window.
+    pub jitter_ms: f64,
+    pub quality: LinkQuality,
+}
+
+#[derive(Default)]
+pub struct RttTracker {
+    samples: VecDeque<f64>,
+}
+
+impl RttTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the echo of a heartbeat sent at `sent_at_us`, both on our own clock.
+    pub fn record(&mut self, sent_at_us: u64, received_at_us: u64) -> RttSample {
+        let rtt_ms = received_at_us.saturating_sub(sent_at_us) as f64 / 1000.0;
+        if self.samples.len() == RTT_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(rtt_ms);
+
+        let avg = self.samples.iter().sum::<f64>() / self.samples.len() as f64;
+        let jitter = if self.samples.len() < 2 {
+            0.0
+        } else {
+            let diffs: f64 = self
+                .samples
+                .iter()
+                .zip(self.samples.iter().skip(1))
+                .map(|(a, b)| (b - a).abs())
+                .sum();
+            diffs / (self.samples.len() - 1) as f64
+        };
+        RttSample { rtt_ms: avg, jitter_ms: jitter, quality: LinkQuality::from_rtt_ms(avg) }
+    }
+}
@@ -2,10 +2,18 @@ pub mod events;
 pub mod commands;
 pub mod logging;
 pub mod clipboard_utils;
+pub mod input_stats;
+pub mod latency;
+pub mod pairing;
+pub mod policy;
+pub mod routing;
+pub mod transfer;
 pub mod client;
 pub mod server;
 
 pub use events::{SessionEvent, LogLevel};
 pub use commands::SessionCommand;
+pub use input_stats::InputLatencyReport;
+pub use latency::LinkQuality;
 pub use client::run_client_session;
 pub use server::run_server_session;
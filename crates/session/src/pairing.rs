@@ -0,0 +1,357 @@
+//! Device authentication right after the handshake, and pairing of devices that do not trust
+//! each other yet, or proof of a pre-shared key instead. Both session loops run this before they
+//! exchange any other frame.
+use crate::events::SessionEvent;
+use crate::{log_info, log_warn};
+use anyhow::{anyhow, bail, Result};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use platform_passer_core::{DeviceAuth, Frame, FrameCodec, Handshake, RejectReason, DEVICE_ID_VERSION, PRE_SHARED_KEY_VERSION};
+use platform_passer_transport::device::{commitment, device_id, nonce};
+use platform_passer_transport::{DeviceKey, Exchange, FailedAttempts, PeerStream, Role, TrustedPeers};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// How long the server waits for its user to enter a pairing code.
+pub const PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long each step of the exchange may take.
+const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+type WsSink = SplitSink<WebSocketStream<PeerStream>, Message>;
+type WsSource = SplitStream<WebSocketStream<PeerStream>>;
+
+/// The code a waiting peer shows, and where to send the user's answer.
+type PendingPairing = (String, oneshot::Sender<bool>);
+
+/// The session cannot go ahead, and trying again will not change that.
+#[derive(Debug)]
+pub(crate) struct Refused(pub RejectReason);
+
+impl fmt::Display for Refused {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Refused {}
+
+/// This machine's device key and the devices it has paired with, shared by every connection.
+#[derive(Clone)]
+pub(crate) struct DeviceTrust {
+    key: Arc<DeviceKey>,
+    peers: Arc<Mutex<TrustedPeers>>,
+    /// Peers waiting for the user to enter or confirm their code, by address.
+    pending: Arc<Mutex<HashMap<String, PendingPairing>>>,
+    /// Server side: addresses that recently failed the pre-shared key check.
+    failures: Arc<Mutex<FailedAttempts>>,
+}
+
+impl DeviceTrust {
+    /// Loads the device key and trusted peers from the config directory.
+    pub fn load() -> Result<Self> {
+        let dir = platform_passer_core::config::config_dir();
+        Ok(Self {
+            key: Arc::new(DeviceKey::load_or_generate(&dir.join("device.key"))?),
+            peers: Arc::new(Mutex::new(TrustedPeers::load(dir.join("trusted_peers"))?)),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            failures: Arc::new(Mutex::new(FailedAttempts::default())),
+        })
+    }
+
+    /// This machine's device ID, sent in its handshake.
+    pub fn id(&self) -> String {
+        self.key.id()
+    }
+
+    /// Counts a failed attempt from `ip`. Returns how long it is now locked out for, if this
+    /// failure locked it out.
+    fn record_failure(&self, ip: IpAddr) -> Option<Duration> {
+        let mut failures = self.failures.lock().ok()?;
+        failures.record(ip, Instant::now()).then(|| failures.lockout())
+    }
+
+    /// How much longer connections from `ip` are refused after failing authentication.
+    pub fn locked_out(&self, ip: IpAddr) -> Option<Duration> {
+        self.failures.lock().ok()?.locked_out(ip, Instant::now())
+    }
+
+    fn is_trusted(&self, public_key: &[u8]) -> bool {
+        self.peers.lock().map(|peers| peers.is_trusted(public_key)).unwrap_or(false)
+    }
+
+    fn trust(&self, public_key: &[u8], name: &str) -> Result<()> {
+        self.peers.lock().map_err(|_| anyhow!("Trusted peer store poisoned"))?.trust(public_key, name)
+    }
+
+    /// Answers the pairing with `addr` with the code the user entered, or `None` to decline.
+    /// Returns whether it was accepted, or `None` if no device at `addr` is waiting.
+    pub fn confirm(&self, addr: &str, code: Option<&str>) -> Option<bool> {
+        let (expected, answer) = self.pending.lock().ok()?.remove(addr)?;
+        let accepted = code.map(str::trim) == Some(expected.as_str());
+        let _ = answer.send(accepted);
+        Some(accepted)
+    }
+}
+
+/// A peer that has proven it holds `public_key`, and the code for pairing with it.
+pub(crate) struct AuthenticatedPeer {
+    /// The device ID of `public_key`, which the peer goes by from here on.
+    pub id: String,
+    /// The name from its handshake, or its ID if it sent none.
+    pub name: String,
+    pub public_key: Vec<u8>,
+    pub code: String,
+    exchange: Exchange,
+}
+
+/// The two halves of a connection still in device authentication.
+pub(crate) struct AuthLink<'a> {
+    pub sink: &'a mut WsSink,
+    pub stream: &'a mut WsSource,
+    pub codec: &'a FrameCodec,
+    /// The negotiated protocol version.
+    pub version: u32,
+}
+
+impl AuthLink<'_> {
+    async fn send(&mut self, auth: DeviceAuth) -> Result<()> {
+        self.send_frame(&Frame::DeviceAuth(auth)).await
+    }
+
+    async fn send_frame(&mut self, frame: &Frame) -> Result<()> {
+        let frame = frame.clone().for_version(self.version).ok_or_else(|| anyhow!("Peer protocol {} is too old", self.version))?;
+        self.sink.send(Message::Binary(self.codec.encode_message(&frame)?)).await?;
+        Ok(())
+    }
+
+    /// Tells the peer why the session cannot go ahead, and hangs up.
+    async fn refuse(&mut self, reason: RejectReason) -> Result<()> {
+        let _ = self.send_frame(&Frame::HandshakeReject(reason.clone())).await;
+        let _ = self.sink.close().await;
+        Err(Refused(reason).into())
+    }
+
+    /// The peer that proved `public_key`, once it is clear the key is the one its `handshake`
+    /// named. Peers before `DEVICE_ID_VERSION` named none.
+    fn authenticated(&self, handshake: &Handshake, public_key: Vec<u8>, exchange: Exchange) -> Result<AuthenticatedPeer> {
+        let id = device_id(&public_key);
+        if self.version >= DEVICE_ID_VERSION && handshake.client_id != id {
+            bail!("Peer claimed to be device {} but proved the key of {}", handshake.client_id, id);
+        }
+        let name = if handshake.device_name.is_empty() { id.clone() } else { handshake.device_name.clone() };
+        Ok(AuthenticatedPeer { id, name, public_key, code: exchange.pairing_code(), exchange })
+    }
+
+    async fn recv(&mut self, timeout: Duration) -> Result<DeviceAuth> {
+        loop {
+            let msg = tokio::time::timeout(timeout, self.stream.next())
+                .await
+                .map_err(|_| anyhow!("Timed out during device authentication"))?;
+            match msg {
+                Some(Ok(Message::Binary(bytes))) => match self.codec.decode_message(&bytes)? {
+                    Frame::DeviceAuth(auth) => return Ok(auth),
+                    Frame::HandshakeReject(reason) => return Err(Refused(reason).into()),
+                    other => bail!("Unexpected frame type {} during device authentication", other.tag()),
+                },
+                Some(Ok(Message::Close(_))) | None => bail!("Connection closed during device authentication"),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Client side: commits to a nonce, learns the server's key, and trades proofs with it.
+/// The key must be the one the server's `handshake` named.
+pub(crate) async fn authenticate_server(link: &mut AuthLink<'_>, trust: &DeviceTrust, handshake: &Handshake) -> Result<AuthenticatedPeer> {
+    let public_key = trust.key.public_key();
+    let own_nonce = nonce()?;
+    link.send(DeviceAuth::Commit { public_key: public_key.clone(), commitment: commitment(&own_nonce) }).await?;
+    let DeviceAuth::Hello { public_key: server_key, nonce: server_nonce } = link.recv(STEP_TIMEOUT).await? else {
+        bail!("Expected the server's hello");
+    };
+    let exchange = Exchange::new(&public_key, &own_nonce, &server_key, &server_nonce)?;
+    link.send(DeviceAuth::Reveal { nonce: own_nonce }).await?;
+    link.send(DeviceAuth::Proof(exchange.prove(&trust.key, Role::Client))).await?;
+    let DeviceAuth::Proof(signature) = link.recv(STEP_TIMEOUT).await? else {
+        bail!("Expected the server's proof");
+    };
+    exchange.verify(&server_key, Role::Server, &signature).map_err(|_| anyhow!("The server's device proof is invalid"))?;
+    link.authenticated(handshake, server_key, exchange)
+}
+
+/// Server side: answers the client's commitment, checks its nonce against it, and trades proofs.
+/// The key must be the one the client's `handshake` named.
+pub(crate) async fn authenticate_client(link: &mut AuthLink<'_>, trust: &DeviceTrust, handshake: &Handshake) -> Result<AuthenticatedPeer> {
+    let DeviceAuth::Commit { public_key: client_key, commitment: committed } = link.recv(STEP_TIMEOUT).await? else {
+        bail!("Expected the client's commitment");
+    };
+    let public_key = trust.key.public_key();
+    let own_nonce = nonce()?;
+    link.send(DeviceAuth::Hello { public_key: public_key.clone(), nonce: own_nonce.clone() }).await?;
+    let DeviceAuth::Reveal { nonce: client_nonce } = link.recv(STEP_TIMEOUT).await? else {
+        bail!("Expected the client's nonce");
+    };
+    if commitment(&client_nonce) != committed {
+        bail!("The client's nonce does not match its commitment");
+    }
+    let exchange = Exchange::new(&client_key, &client_nonce, &public_key, &own_nonce)?;
+    let DeviceAuth::Proof(signature) = link.recv(STEP_TIMEOUT).await? else {
+        bail!("Expected the client's proof");
+    };
+    exchange.verify(&client_key, Role::Client, &signature).map_err(|_| anyhow!("The client's device proof is invalid"))?;
+    let client = link.authenticated(handshake, client_key, exchange)?;
+    link.send(DeviceAuth::Proof(client.exchange.prove(&trust.key, Role::Server))).await?;
+    Ok(client)
+}
+
+/// Server side: lets `client` in if both devices trust each other, and otherwise waits for the
+/// user to enter the code it shows. Refuses the client if they decline or the wait runs out.
+/// With a pre-shared `secret`, the client must prove it instead, and is let in if it does.
+pub(crate) async fn admit(
+    link: &mut AuthLink<'_>,
+    trust: &DeviceTrust,
+    client: &AuthenticatedPeer,
+    addr: SocketAddr,
+    event_tx: &Sender<SessionEvent>,
+    secret: Option<&str>,
+) -> Result<()> {
+    let name = &client.name;
+    let answer = link.recv(STEP_TIMEOUT).await?;
+    if let Some(secret) = secret {
+        let proven = match &answer {
+            DeviceAuth::SecretProof(proof) => client.exchange.verify_secret(secret.as_bytes(), Role::Client, proof).is_ok(),
+            _ => false,
+        };
+        if !proven {
+            log_warn!(event_tx, "{} ({}) failed the pre-shared key check", name, addr);
+            if let Some(lockout) = trust.record_failure(addr.ip()) {
+                log_warn!(event_tx, "Too many failed attempts from {}; refusing it for {}s", addr.ip(), lockout.as_secs());
+            }
+            return link.refuse(RejectReason::SecretMismatch).await;
+        }
+        link.send(DeviceAuth::SecretProof(client.exchange.prove_secret(secret.as_bytes(), Role::Server))).await?;
+        log_info!(event_tx, "{} ({}) proved the pre-shared key", name, addr);
+        return link.send(DeviceAuth::Accepted).await;
+    }
+    if matches!(answer, DeviceAuth::SecretProof(_)) {
+        log_warn!(event_tx, "{} ({}) offers a pre-shared key, but none is set here", name, addr);
+        return link.refuse(RejectReason::SecretMismatch).await;
+    }
+
+    let trusts_us = answer == DeviceAuth::Accepted;
+    if !(trusts_us && trust.is_trusted(&client.public_key)) {
+        link.send(DeviceAuth::PairingRequired).await?;
+        let (answer_tx, answer_rx) = oneshot::channel();
+        if let Ok(mut pending) = trust.pending.lock() {
+            pending.insert(addr.to_string(), (client.code.clone(), answer_tx));
+        }
+        log_info!(event_tx, "{} [{}] ({}) wants to pair. Enter the code it shows to accept it.", name, client.id, addr);
+        let _ = event_tx.send(SessionEvent::PairingRequest { peer: client.id.clone(), name: name.clone(), addr: addr.to_string() }).await;
+
+        let accepted = tokio::select! {
+            answer = tokio::time::timeout(PAIRING_TIMEOUT, answer_rx) => matches!(answer, Ok(Ok(true))),
+            // The client sends nothing while it waits, so anything here means it gave up
+            _ = link.stream.next() => false,
+        };
+        if let Ok(mut pending) = trust.pending.lock() {
+            pending.remove(&addr.to_string());
+        }
+        if !accepted {
+            log_warn!(event_tx, "Not pairing with {} ({})", name, addr);
+            return link.refuse(RejectReason::NotPaired).await;
+        }
+        if let Err(e) = trust.trust(&client.public_key, name) {
+            log_warn!(event_tx, "Paired with {}, but failed to save it: {}", name, e);
+        }
+        log_info!(event_tx, "Paired with {} [{}] ({})", name, client.id, addr);
+        let _ = event_tx.send(SessionEvent::Paired { peer: client.id.clone(), name: name.clone() }).await;
+    }
+    link.send(DeviceAuth::Accepted).await
+}
+
+/// Client side: tells the server whether it trusts it and waits to be let in, showing the
+/// pairing code if the devices have not paired yet. A new server is trusted only once it has
+/// accepted the code and the user has confirmed it here too. With a pre-shared `secret`, the two
+/// prove it to each other instead.
+pub(crate) async fn await_admission(
+    link: &mut AuthLink<'_>,
+    trust: &DeviceTrust,
+    server: &AuthenticatedPeer,
+    addr: SocketAddr,
+    event_tx: &Sender<SessionEvent>,
+    secret: Option<&str>,
+) -> Result<()> {
+    let name = &server.name;
+    if let Some(secret) = secret {
+        if link.version < PRE_SHARED_KEY_VERSION {
+            log_warn!(event_tx, "{} speaks protocol {}, too old for a pre-shared key", name, link.version);
+            return Err(Refused(RejectReason::SecretMismatch).into());
+        }
+        link.send(DeviceAuth::SecretProof(server.exchange.prove_secret(secret.as_bytes(), Role::Client))).await?;
+        let DeviceAuth::SecretProof(proof) = link.recv(STEP_TIMEOUT).await? else {
+            bail!("Expected the server's pre-shared key proof");
+        };
+        if server.exchange.verify_secret(secret.as_bytes(), Role::Server, &proof).is_err() {
+            log_warn!(event_tx, "{} does not know the pre-shared key", name);
+            return Err(Refused(RejectReason::SecretMismatch).into());
+        }
+        return match link.recv(STEP_TIMEOUT).await? {
+            DeviceAuth::Accepted => Ok(()),
+            other => bail!("Unexpected {:?} from the server", other),
+        };
+    }
+
+    let trusted = trust.is_trusted(&server.public_key);
+    link.send(if trusted { DeviceAuth::Accepted } else { DeviceAuth::PairingRequired }).await?;
+    match link.recv(STEP_TIMEOUT).await? {
+        DeviceAuth::Accepted if trusted => return Ok(()),
+        DeviceAuth::PairingRequired => {}
+        other => bail!("Unexpected {:?} from the server", other),
+    }
+
+    let (answer_tx, answer_rx) = oneshot::channel();
+    if let Ok(mut pending) = trust.pending.lock() {
+        pending.insert(addr.to_string(), (server.code.clone(), answer_tx));
+    }
+    log_info!(event_tx, "Pairing with {}: enter {} there, then again here once it accepts this device.", name, server.code);
+    let _ = event_tx.send(SessionEvent::PairingCode { peer: server.id.clone(), name: name.clone(), addr: addr.to_string(), code: server.code.clone() }).await;
+
+    // Anyone in the middle can answer `Accepted`, but only the real server takes the code the
+    // user sees here, so wait for the user to confirm that it did
+    let server_accepted = async {
+        match link.recv(PAIRING_TIMEOUT + STEP_TIMEOUT).await? {
+            DeviceAuth::Accepted => Ok(()),
+            other => bail!("Unexpected {:?} from the server", other),
+        }
+    };
+    let user_confirmed = async {
+        match tokio::time::timeout(PAIRING_TIMEOUT, answer_rx).await {
+            Ok(Ok(true)) => Ok(()),
+            _ => Err(anyhow::Error::from(Refused(RejectReason::NotPaired))),
+        }
+    };
+    let paired = tokio::try_join!(server_accepted, user_confirmed);
+    if let Ok(mut pending) = trust.pending.lock() {
+        pending.remove(&addr.to_string());
+    }
+    if let Err(e) = paired {
+        log_warn!(event_tx, "Not pairing with {}: {}", name, e);
+        let _ = link.sink.close().await;
+        return Err(e);
+    }
+    if let Err(e) = trust.trust(&server.public_key, name) {
+        log_warn!(event_tx, "Paired with {}, but failed to save it: {}", name, e);
+    }
+    log_info!(event_tx, "Paired with {} [{}]", name, server.id);
+    let _ = event_tx.send(SessionEvent::Paired { peer: server.id.clone(), name: name.clone() }).await;
+    Ok(())
+}
@@ -0,0 +1,28 @@
+//! Logging what a peer's `PeerPolicy` refuses.
+use crate::events::SessionEvent;
+use crate::log_warn;
+use platform_passer_core::PolicyDenial;
+use std::collections::HashSet;
+use tokio::sync::mpsc::Sender;
+
+/// The denials seen on one connection. Each kind is logged once at warn level, and repeats only
+/// to tracing, so that a peer denied input does not flood the log with every mouse move.
+pub(crate) struct Denials {
+    peer: String,
+    seen: HashSet<PolicyDenial>,
+}
+
+impl Denials {
+    /// `peer` names the peer in the log.
+    pub fn new(peer: String) -> Self {
+        Self { peer, seen: HashSet::new() }
+    }
+
+    pub async fn record(&mut self, event_tx: &Sender<SessionEvent>, denial: PolicyDenial) {
+        if self.seen.insert(denial) {
+            log_warn!(event_tx, "Policy for {} denies {}", self.peer, denial);
+        } else {
+            tracing::debug!("Policy for {} denies {}", self.peer, denial);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Which connected client captured input goes to. The server captures once for all of them;
+//! each connection keeps the input meant for its peer and the screen switches that concern it.
+use platform_passer_core::{Frame, InputEvent, ScreenSide};
+
+/// A frame on its way to the connected clients, with the one it is meant for by device ID.
+/// `None` sends it to every client.
+#[derive(Debug, Clone)]
+pub struct Outbound {
+    pub to: Option<String>,
+    pub frame: Frame,
+}
+
+impl From<Frame> for Outbound {
+    fn from(frame: Frame) -> Self {
+        Self { to: None, frame }
+    }
+}
+
+/// Picks out the outbound frames one connection's peer gets.
+#[derive(Debug)]
+pub struct InputRoute {
+    peer: String,
+    /// Whether this peer is the one being controlled.
+    engaged: bool,
+}
+
+impl InputRoute {
+    pub fn new(peer: impl Into<String>) -> Self {
+        Self { peer: peer.into(), engaged: false }
+    }
+
+    /// Whether this peer is the one being controlled.
+    pub fn engaged(&self) -> bool {
+        self.engaged
+    }
+
+    /// The frame this peer gets for `outbound`, if any. When control moves straight on to
+    /// another peer, the one that had it is sent back to the server's screen instead.
+    pub fn filter(&mut self, outbound: Outbound) -> Option<Frame> {
+        let Outbound { to, frame } = outbound;
+        let mine = to.as_deref().is_none_or(|to| to == self.peer);
+        let Frame::Input(mut input) = frame else {
+            return mine.then_some(frame);
+        };
+        match input.event {
+            InputEvent::ScreenSwitch(ScreenSide::Remote) if mine => self.engaged = true,
+            InputEvent::ScreenSwitch(_) if self.engaged => {
+                self.engaged = false;
+                input.event = InputEvent::ScreenSwitch(ScreenSide::Local);
+            }
+            _ if !mine => return None,
+            _ => {}
+        }
+        Some(Frame::Input(input))
+    }
+}
@@ -1,20 +1,26 @@
 use crate::events::{SessionEvent, LogLevel};
 use crate::{log_info, log_error, log_debug, log_warn};
 use anyhow::Result;
-use platform_passer_core::{Frame, ClipboardEvent, Handshake};
-use platform_passer_transport::{make_ws_listener};
-use platform_passer_input::{InputSource, DefaultInputSource};
+use platform_passer_core::{Frame, ClipboardEvent, Handshake, Heartbeat, CapabilitySet, FrameLimits, FrameCodec, InputConfig, InputFrame, InputSequencer, NetworkConfig, SecurityConfig, unix_time_us};
+use platform_passer_transport::{make_ws_listener, accept_ws, accept_wss, PeerStream, TlsIdentity};
+use platform_passer_input::{InputSource, DefaultInputSource, LayoutFilter, modifiers};
 use platform_passer_clipboard::{ClipboardProvider, DefaultClipboard};
 use std::net::SocketAddr;
 use tokio::sync::mpsc::{Sender, Receiver};
-use tokio_tungstenite::{accept_async, tungstenite::Message as WsMessage};
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message as WsMessage};
 use crate::commands::SessionCommand;
 use std::sync::{Arc, Mutex};
 use crate::clipboard_utils::{LocalClipboardContent, calculate_hash};
+use crate::input_stats::InputLatencyTracker;
+use crate::latency::RttTracker;
+use crate::pairing::{self, AuthLink, DeviceTrust};
+use crate::policy::Denials;
+use crate::routing::{InputRoute, Outbound};
+use crate::transfer::IncomingFiles;
 use futures_util::{StreamExt, SinkExt};
 use std::collections::HashMap;
 use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
 use std::path::PathBuf;
 use platform_passer_core::{FileManifest, FileMeta, TransferPurpose};
 
@@ -26,17 +32,34 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
     log_info!(&event_tx, "Starting WebSocket server session on {}", bind_addr);
     
     // 1. Setup Shared Outbound channel for all events (Input, Clipboard)
-    let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel::<Frame>(100);
+    let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel::<Outbound>(100);
     let (internal_tx, mut internal_rx) = tokio::sync::mpsc::channel::<SessionInternalMsg>(100);
     
     // 2. Setup Input Source (Server captures local input)
     let source = Arc::new(DefaultInputSource::new());
     let broadcast_tx_captured = broadcast_tx.clone();
     
+    let state_source = Arc::downgrade(&source);
     source.start_capture(Box::new(move |event| {
-        let _ = broadcast_tx_captured.send(Frame::Input(event));
+        let snapshot_due = modifiers::snapshot_due(&event);
+        let source = state_source.upgrade();
+        let to = source.as_ref().and_then(|s| s.active_peer());
+        // Numbered per client, once it is known which frames each one gets
+        let _ = broadcast_tx_captured.send(Outbound { to: to.clone(), frame: Frame::Input(InputFrame::captured(event)) });
+        if snapshot_due {
+            if let Some(state) = source.and_then(|s| s.modifier_state()) {
+                let _ = broadcast_tx_captured.send(Outbound { to, frame: Frame::ModifierState(state) });
+            }
+        }
     }))?;
 
+    // The lock lives in the platform hooks and would otherwise outlast a previous session
+    let _ = source.set_screen_lock(false);
+    let lock_tx = event_tx.clone();
+    source.on_screen_lock_changed(Box::new(move |locked| {
+        let _ = lock_tx.try_send(SessionEvent::ScreenLock { locked });
+    }));
+
     // 3. Setup Clipboard Listener
     let clip_tx = broadcast_tx.clone();
     let _clip_log = event_tx.clone();
@@ -87,7 +110,7 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
                          let _ = clip_tx.send(Frame::Notification { 
                              title: "Clipboard Sync Skipped".to_string(), 
                              message: "files > 10MB".to_string() 
-                         });
+                         }.into());
                      } else {
                          let batch_id = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos() as u64;
                          
@@ -96,7 +119,7 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
                              total_size,
                              batch_id,
                          };
-                          let _ = clip_tx.send(Frame::Clipboard(ClipboardEvent::Files { manifest }));
+                          let _ = clip_tx.send(Frame::Clipboard(ClipboardEvent::Files { manifest }).into());
                           
                           let _ = internal_tx_clip.try_send(SessionInternalMsg::SendClipboardFiles { 
                               batch_id, 
@@ -121,7 +144,7 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
                 } else { true };
 
                 if should_send {
-                     let _ = clip_tx.send(Frame::Clipboard(ClipboardEvent::Text(text)));
+                     let _ = clip_tx.send(Frame::Clipboard(ClipboardEvent::Text(text)).into());
                 }
                 return;
             }
@@ -138,25 +161,46 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
             } else { true };
             
             if should_send {
-                 let _ = clip_tx.send(Frame::Clipboard(ClipboardEvent::Image { data: img_data }));
+                 let _ = clip_tx.send(Frame::Clipboard(ClipboardEvent::Image { data: img_data }).into());
             }
         }
      })) {
         log_error!(&event_tx, "Failed to start clipboard listener: {}", e);
     }
 
-    // 4. Setup WebSocket Listener
+    // 4. TLS certificate, generated on first run; clients pin it by this fingerprint
+    let identity = TlsIdentity::load_or_generate(&platform_passer_core::config::config_dir().join("tls"));
+    let tls_acceptor = match identity.and_then(|identity| Ok((identity.fingerprint(), identity.acceptor()?))) {
+        Ok((fingerprint, acceptor)) => {
+            log_info!(&event_tx, "TLS certificate fingerprint: {}", fingerprint);
+            Some(acceptor)
+        }
+        Err(e) => {
+            log_error!(&event_tx, "TLS unavailable: {}", e);
+            None
+        }
+    };
+
+    // 5. Device key: clients are let in only once paired with it
+    let device_trust = DeviceTrust::load()?;
+
+    // 6. Setup WebSocket Listener
     let listener = make_ws_listener(bind_addr).await?;
     log_info!(&event_tx, "WebSocket Server listening on {}", bind_addr);
     let _ = event_tx.send(SessionEvent::Waiting(bind_addr.to_string())).await;
 
-    // 5. Main Server Loop (Commands + Accept)
+    // 7. Main Server Loop (Commands + Accept)
     let cmd_broadcast_tx = broadcast_tx.clone();
     let cmd_event_tx = event_tx.clone();
     let pending_sends: Arc<Mutex<HashMap<u32, PathBuf>>> = Arc::new(Mutex::new(HashMap::new()));
     let pending_sends_clone = pending_sends.clone();
     let mut file_id_counter = 0u32;
     let source_cmd = source.clone();
+    let mut frame_limits = FrameLimits::default();
+    let (network_tx, _) = watch::channel(NetworkConfig::default());
+    let (input_config_tx, _) = watch::channel(InputConfig::default());
+    let (security_tx, _) = watch::channel(SecurityConfig::default());
+    let mut device_name = platform_passer_core::config::host_name().to_string();
     
     let mut session_tasks = Vec::new();
 
@@ -182,15 +226,33 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
                                 file_size,
                                 purpose: TransferPurpose::Manual,
                             });
-                            let _ = cmd_broadcast_tx.send(req);
+                            let _ = cmd_broadcast_tx.send(req.into());
                         }
                     }
                     Some(SessionCommand::UpdateConfig(config)) => {
+                        // Applies to connections accepted from now on
+                        frame_limits = config.frame_limits;
+                        security_tx.send_replace(config.security.clone());
+                        device_name = config.name_or_host();
+                        // Applies to open connections immediately
+                        network_tx.send_replace(config.network);
+                        input_config_tx.send_replace(config.input.clone());
                         // Update source config (Server as sender)
-                        if let Err(e) = source_cmd.update_config(config) {
+                        if let Err(e) = source_cmd.update_config(*config) {
                             log_error!(&cmd_event_tx, "Failed to update server source config: {}", e);
                         }
                     }
+                    Some(SessionCommand::SetScreenLock(locked)) => {
+                        let _ = source_cmd.set_screen_lock(locked);
+                    }
+                    Some(SessionCommand::ConfirmPairing { addr, code }) => {
+                        match device_trust.confirm(&addr, code.as_deref()) {
+                            Some(true) => {}
+                            Some(false) if code.is_some() => log_warn!(&cmd_event_tx, "Wrong pairing code for {}", addr),
+                            Some(false) => {}
+                            None => log_warn!(&cmd_event_tx, "No device at {} is waiting to pair", addr),
+                        }
+                    }
                     Some(SessionCommand::Disconnect) => {
                         log_info!(&cmd_event_tx, "Server disconnect command received. Shutting down.");
                         break;
@@ -222,7 +284,7 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
                                     file_size,
                                     purpose: TransferPurpose::ClipboardSync { batch_id },
                                 });
-                                let _ = cmd_broadcast_tx.send(req);
+                                let _ = cmd_broadcast_tx.send(req.into());
                             }
                         }
                     }
@@ -231,27 +293,40 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
             // Handle New Connections
             accept_res = listener.accept() => {
                 match accept_res {
+                    Ok((_, addr)) if device_trust.locked_out(addr.ip()).is_some() => {
+                        log_debug!(&event_tx, "Refusing {} after repeated failed authentication", addr);
+                    }
                     Ok((stream, addr)) => {
                          let log_tx_spawn = event_tx.clone();
-                        let broadcast_rx = broadcast_tx.subscribe();
                         let broadcast_tx_session = broadcast_tx.clone();
                         let last_remote_clip_conn = last_remote_clip.clone();
                         let pending_sends_session = pending_sends.clone();
                         let source_clone = source.clone();
+                        let limits = frame_limits;
+                        let secret = security_tx.borrow().pre_shared_key();
+                        let security_rx = security_tx.subscribe();
+                        let name = device_name.clone();
+                        let network_rx = network_tx.subscribe();
+                        let input_config_rx = input_config_tx.subscribe();
+                        let tls = network_tx.borrow().tls;
+                        let acceptor = tls_acceptor.clone();
+                        let trust = device_trust.clone();
                 
                         let handle = tokio::spawn(async move {
-                            match accept_async(stream).await {
+                            let upgraded = match (tls, &acceptor) {
+                                (false, _) => accept_ws(stream, &limits).await,
+                                (true, Some(acceptor)) => accept_wss(stream, &limits, acceptor).await,
+                                (true, None) => Err(anyhow::anyhow!("TLS is enabled but no certificate is available")),
+                            };
+                            match upgraded {
                                 Ok(ws_stream) => {
-                                    log_info!(&log_tx_spawn, "WebSocket handshake successful with {}", addr);
+                                    let kind = if ws_stream.get_ref().is_encrypted() { "Secure WebSocket" } else { "WebSocket" };
+                                    log_info!(&log_tx_spawn, "{} handshake successful with {}", kind, addr);
                                     let _ = log_tx_spawn.send(SessionEvent::Connecting(addr.to_string())).await;
-                                    
-                                    if let Err(e) = ws_stream.get_ref().set_nodelay(true) {
-                                        log_warn!(&log_tx_spawn, "Failed to set TCP_NODELAY on server: {}", e);
-                                    }
                 
                                     let _ = log_tx_spawn.send(SessionEvent::Connected(addr.to_string())).await;
                                     
-                                    if let Err(e) = handle_protocol_session(ws_stream, broadcast_rx, log_tx_spawn.clone(), source_clone, last_remote_clip_conn, pending_sends_session, broadcast_tx_session).await {
+                                    if let Err(e) = handle_protocol_session(PeerConnection { ws: ws_stream, addr, codec: FrameCodec::new(limits), network_rx, input_config_rx, security_rx, secret, name }, log_tx_spawn.clone(), source_clone, last_remote_clip_conn, pending_sends_session, broadcast_tx_session, trust).await {
                                         log_error!(&log_tx_spawn, "Protocol error with {}: {}", addr, e);
                                     }
                                 }
@@ -278,67 +353,155 @@ pub async fn run_server_session(bind_addr: SocketAddr, mut cmd_rx: Receiver<Sess
     Ok(())
 }
 
+/// An accepted connection and its per-connection settings.
+struct PeerConnection {
+    ws: tokio_tungstenite::WebSocketStream<PeerStream>,
+    addr: SocketAddr,
+    /// Frame limits are fixed at accept time, as they also size the WebSocket buffers.
+    codec: FrameCodec,
+    /// Follows `SessionCommand::UpdateConfig` for the life of the connection.
+    network_rx: watch::Receiver<NetworkConfig>,
+    /// Likewise; selects the peer's `LayoutMode`.
+    input_config_rx: watch::Receiver<InputConfig>,
+    /// Likewise; selects the peer's `PeerPolicy`.
+    security_rx: watch::Receiver<SecurityConfig>,
+    /// The pre-shared key in effect when the connection was accepted.
+    secret: Option<String>,
+    /// Likewise, this device's name.
+    name: String,
+}
+
 async fn handle_protocol_session(
-    ws_stream: tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
-    mut broadcast_rx: tokio::sync::broadcast::Receiver<Frame>,
+    peer: PeerConnection,
     event_tx: Sender<SessionEvent>,
     source: Arc<dyn InputSource>,
     last_remote_clip: Arc<Mutex<Option<LocalClipboardContent>>>,
     pending_sends: Arc<Mutex<HashMap<u32, PathBuf>>>,
-    broadcast_tx: tokio::sync::broadcast::Sender<Frame>,
+    broadcast_tx: tokio::sync::broadcast::Sender<Outbound>,
+    trust: DeviceTrust,
 ) -> Result<()> {
-    let (mut ws_sink, mut ws_stream) = ws_stream.split();
+    let PeerConnection { ws, addr, codec, mut network_rx, mut input_config_rx, mut security_rx, secret, name } = peer;
+    let (mut ws_sink, mut ws_stream) = ws.split();
     let clip = DefaultClipboard::new();
 
     // 1. Protocol Handshake
     log_debug!(&event_tx, "Awaiting application handshake...");
-    if let Some(Ok(WsMessage::Binary(bytes))) = ws_stream.next().await {
-        let frame: Frame = bincode::deserialize(&bytes)?;
-        match frame {
-            Frame::Handshake(h) => {
-                log_info!(&event_tx, "Received handshake (Client: {})", h.client_id);
-                // Update Source with remote topology
-                if let Some(info) = h.screen_info {
-                     // Assume Client is to the RIGHT for now (default behavior) or use config
-                     // Since we don't have a complex topology manager here, we default to adding it to the Right
-                     let _ = source.add_remote(
-                         platform_passer_core::config::RemoteScreen {
-                            id: h.client_id,
-                            position: platform_passer_core::config::ScreenPosition::Right, // Default
-                            info: info.clone(),
-                         }
-                     );
-                     log_info!(&event_tx, "Registered remote screen: {}x{}", info.width, info.height);
-                }
-                let resp = Frame::Handshake(Handshake {
-                    version: 1,
-                    client_id: format!("{}-server", std::env::consts::OS),
-                    capabilities: vec!["input".to_string(), "clipboard".to_string()],
-                    screen_info: None,
-                });
-                ws_sink.send(WsMessage::Binary(bincode::serialize(&resp)?)).await?;
-            }
+    let client_handshake = match ws_stream.next().await {
+        Some(Ok(WsMessage::Binary(bytes))) => match codec.decode_message(&bytes)? {
+            Frame::Handshake(h) => h,
             _ => {
                 log_error!(&event_tx, "Invalid handshake frame");
                 return Err(anyhow::anyhow!("Invalid handshake"));
             }
+        },
+        _ => return Err(anyhow::anyhow!("Connection closed before handshake")),
+    };
+    log_info!(&event_tx, "Received handshake (Client: {}, protocol {}..={})", client_handshake.client_id, client_handshake.min_version, client_handshake.version);
+
+    let screen_info = {
+        #[cfg(any(target_os = "macos", target_os = "windows"))] { platform_passer_input::get_screen_info() }
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))] { None }
+    };
+    let local_handshake = Handshake::new(trust.id(), CapabilitySet::all(), screen_info).with_device_name(name);
+    let protocol_version = match local_handshake.negotiate_version(&client_handshake) {
+        Ok(v) => v,
+        Err(reason) => {
+            log_warn!(&event_tx, "Rejecting client {}: {}", client_handshake.client_id, reason);
+            let _ = ws_sink.send(WsMessage::Binary(codec.encode_message(&Frame::HandshakeReject(reason.clone()))?)).await;
+            let _ = ws_sink.close().await;
+            return Err(anyhow::anyhow!("Handshake rejected: {}", reason));
         }
+    };
+
+    let capabilities = local_handshake.capabilities.intersection(&client_handshake.capabilities);
+    ws_sink.send(WsMessage::Binary(codec.encode_message(&Frame::Handshake(local_handshake))?)).await?;
+
+    // 2. Device authentication. Nothing reaches the client, nor does its screen join the
+    // topology, until the two devices are paired or it has proven the pre-shared key.
+    let mut link = AuthLink { sink: &mut ws_sink, stream: &mut ws_stream, codec: &codec, version: protocol_version };
+    let device = pairing::authenticate_client(&mut link, &trust, &client_handshake).await?;
+    pairing::admit(&mut link, &trust, &device, addr, &event_tx, secret.as_deref()).await?;
+    let mut broadcast_rx = broadcast_tx.subscribe();
+    let peer_id = device.id.clone();
+    log_info!(&event_tx, "{} [{}] connected from {}", device.name, peer_id, addr);
+    let _ = event_tx.send(SessionEvent::ProtocolNegotiated { peer: peer_id.clone(), name: device.name.clone(), version: protocol_version, capabilities }).await;
+
+    // Its screen stands in for the configured one under its device ID; where it sits is still the topology's call
+    if let Some(info) = client_handshake.screen_info {
+        log_info!(&event_tx, "{} has a {}x{} screen across {} display(s)", device.name, info.width, info.height, info.displays.len().max(1));
+        let _ = source.set_peer_screen(&peer_id, Some(info));
     }
 
-    let mut active_files: HashMap<u32, File> = HashMap::new();
+    let mut input_stats = InputLatencyTracker::new();
+    let mut sequencer = InputSequencer::new();
+    let mut route = InputRoute::new(peer_id.clone());
+    let mut active_files = IncomingFiles::new();
     // Batch Tracking
     let mut incoming_batches: HashMap<u64, (usize, Vec<PathBuf>)> = HashMap::new(); // batch_id -> (expected_count, received_paths)
     let mut active_downloads: HashMap<u32, (u64, PathBuf)> = HashMap::new(); // file_id -> (batch_id, path)
 
+    let mut network = *network_rx.borrow_and_update();
+    let mut rtt = RttTracker::new();
+    let mut hb_interval = tokio::time::interval(network.heartbeat_interval());
+    let mut unanswered_heartbeats = 0u32;
+    let mut last_rx = tokio::time::Instant::now();
+    let mut layout = LayoutFilter::new(input_config_rx.borrow_and_update().layout_mode_for(&peer_id));
+    let mut policy = security_rx.borrow_and_update().policy_for(&peer_id);
+    let mut denials = Denials::new(device.name.clone());
+
     log_debug!(&event_tx, "Entering protocol loop...");
     loop {
         tokio::select! {
+            Ok(()) = network_rx.changed() => {
+                network = *network_rx.borrow_and_update();
+                hb_interval = tokio::time::interval(network.heartbeat_interval());
+            }
+            Ok(()) = input_config_rx.changed() => {
+                layout.set_mode(input_config_rx.borrow_and_update().layout_mode_for(&peer_id));
+            }
+            Ok(()) = security_rx.changed() => {
+                policy = security_rx.borrow_and_update().policy_for(&peer_id);
+            }
+            _ = hb_interval.tick() => {
+                if unanswered_heartbeats >= network.missed_heartbeats.max(1) {
+                    log_warn!(&event_tx, "Client missed {} heartbeats.", unanswered_heartbeats);
+                    break;
+                }
+                unanswered_heartbeats += 1;
+                if let Err(e) = ws_sink.send(WsMessage::Binary(codec.encode_message(&Frame::Heartbeat(Heartbeat::ping()))?)).await {
+                    log_error!(&event_tx, "Failed to send heartbeat: {}", e);
+                    break;
+                }
+            }
             // Read from client
-            msg = tokio::time::timeout(std::time::Duration::from_secs(15), ws_stream.next()) => {
+            msg = tokio::time::timeout_at(last_rx + network.read_timeout(), ws_stream.next()) => {
+                if let Ok(Some(Ok(_))) = &msg {
+                    last_rx = tokio::time::Instant::now();
+                }
                 match msg {
                     Ok(Some(Ok(WsMessage::Binary(bytes)))) => {
-                        match bincode::deserialize::<Frame>(&bytes) {
+                        match codec.decode_message(&bytes) {
+                            Err(e) if e.is_recoverable() => {
+                                log_debug!(&event_tx, "Skipping frame: {}", e);
+                            }
+                            Err(e) => {
+                                log_warn!(&event_tx, "Disconnecting {}: {}", addr, e);
+                                let _ = event_tx.send(SessionEvent::ProtocolViolation { peer: peer_id.clone(), reason: e.to_string() }).await;
+                                break;
+                            }
+                            Ok(frame) if !capabilities.allows(&frame) => {
+                                log_debug!(&event_tx, "Ignoring frame outside negotiated capabilities: {:?}", frame.capability());
+                            }
                             Ok(frame) => {
+                                if let Err(denial) = policy.check_incoming(&frame) {
+                                    denials.record(&event_tx, denial).await;
+                                    // Declined, so the client does not wait on it
+                                    if let Frame::FileTransferRequest(req) = frame {
+                                        let resp = Frame::FileTransferResponse(platform_passer_core::FileTransferResponse { id: req.id, accepted: false });
+                                        let _ = ws_sink.send(WsMessage::Binary(codec.encode_message(&resp)?)).await;
+                                    }
+                                    continue;
+                                }
                                 match frame {
                                     Frame::Clipboard(ClipboardEvent::Text(text)) => {
                                         log_debug!(&event_tx, "Received clipboard update (Text)");
@@ -363,41 +526,51 @@ async fn handle_protocol_session(
                                         let _ = event_tx.send(SessionEvent::Log { level: LogLevel::Info, message: format!("Remote Notification: {} - {}", title, message) }).await;
                                     }
                                     Frame::FileTransferResponse(resp) => {
-                                        if resp.accepted {
-                                            let mut path_opt = None;
-                                            if let Ok(mut lock) = pending_sends.lock() {
-                                                path_opt = lock.remove(&resp.id);
+                                        // Answered either way, so it no longer waits
+                                        let path_opt = pending_sends.lock().ok().and_then(|mut lock| lock.remove(&resp.id));
+                                        if !resp.accepted {
+                                            if let Some(path) = path_opt {
+                                                let _ = event_tx.send(SessionEvent::TransferFailed { id: resp.id, reason: format!("{} declined {:?}", device.name, path) }).await;
                                             }
+                                        } else if let Some(path) = path_opt {
+                                            let broadcast_tx_file = broadcast_tx.clone();
+                                            let event_tx_file = event_tx.clone();
+                                            let file_id = resp.id;
                                             
-                                            if let Some(path) = path_opt {
-                                                let broadcast_tx_file = broadcast_tx.clone();
-                                                let event_tx_file = event_tx.clone();
-                                                let file_id = resp.id;
-                                                
-                                                tokio::spawn(async move {
-                                                    match tokio::fs::File::open(&path).await {
-                                                        Ok(mut file) => {
-                                                            let mut buffer = vec![0u8; 65536];
-                                                            while let Ok(n) = tokio::io::AsyncReadExt::read(&mut file, &mut buffer).await {
-                                                                if n == 0 { break; }
-                                                                let chunk = buffer[..n].to_vec();
-                                                                if broadcast_tx_file.send(Frame::FileData { id: file_id, chunk }).is_err() { break; }
-                                                            }
-                                                            let _ = broadcast_tx_file.send(Frame::FileEnd { id: file_id });
-                                                            log_info!(&event_tx_file, "File sender completed ID: {}", file_id);
-                                                        }
-                                                        Err(e) => {
-                                                            log_error!(&event_tx_file, "Failed to open file for sending {:?}: {}", path, e);
+                                            tokio::spawn(async move {
+                                                match tokio::fs::File::open(&path).await {
+                                                    Ok(mut file) => {
+                                                        let mut buffer = vec![0u8; 65536];
+                                                        while let Ok(n) = tokio::io::AsyncReadExt::read(&mut file, &mut buffer).await {
+                                                            if n == 0 { break; }
+                                                            let chunk = buffer[..n].to_vec();
+                                                            if broadcast_tx_file.send(Frame::FileData { id: file_id, chunk }.into()).is_err() { break; }
                                                         }
+                                                        let _ = broadcast_tx_file.send(Frame::FileEnd { id: file_id }.into());
+                                                        log_info!(&event_tx_file, "File sender completed ID: {}", file_id);
                                                     }
-                                                });
-                                            }
+                                                    Err(e) => {
+                                                        log_error!(&event_tx_file, "Failed to open file for sending {:?}: {}", path, e);
+                                                    }
+                                                }
+                                            });
                                         }
                                     }
+                                    Frame::Heartbeat(hb) if hb.reply => {
+                                        unanswered_heartbeats = 0;
+                                        let sample = rtt.record(hb.timestamp, unix_time_us());
+                                        let _ = event_tx.send(SessionEvent::Latency { peer: peer_id.clone(), rtt_ms: sample.rtt_ms, jitter_ms: sample.jitter_ms, quality: sample.quality }).await;
+                                    }
                                     Frame::Heartbeat(hb) => {
-                                        let _ = ws_sink.send(WsMessage::Binary(bincode::serialize(&Frame::Heartbeat(hb))?)).await;
+                                        let _ = ws_sink.send(WsMessage::Binary(codec.encode_message(&Frame::Heartbeat(hb.to_reply()))?)).await;
                                     }
-                                    Frame::Input(event) => {
+                                    Frame::Input(InputFrame { event, stamp }) => {
+                                        if let Some(stamp) = stamp {
+                                            input_stats.record(&stamp, unix_time_us());
+                                            if let Some(report) = input_stats.take_report_if_due() {
+                                                let _ = event_tx.send(SessionEvent::InputLatency { peer: peer_id.clone(), report }).await;
+                                            }
+                                        }
                                         match event {
                                             platform_passer_core::InputEvent::ScreenSwitch(_) => {
                                                 // When Server receives focus, ensure it stays in Local mode (not swallowing)
@@ -422,30 +595,34 @@ async fn handle_protocol_session(
                                             
                                             match File::create(&file_path).await {
                                                 Ok(file) => {
-                                                    active_files.insert(req.id, file);
+                                                    active_files.start(req.id, file, file_path.clone(), req.file_size, policy.max_transfer_bytes);
                                                     if let Some(bid) = batch_id_opt {
                                                         active_downloads.insert(req.id, (bid, file_path));
                                                     }
                                                     let resp = Frame::FileTransferResponse(platform_passer_core::FileTransferResponse { id: req.id, accepted: true });
-                                                    let _ = ws_sink.send(WsMessage::Binary(bincode::serialize(&resp)?)).await;
+                                                    let _ = ws_sink.send(WsMessage::Binary(codec.encode_message(&resp)?)).await;
                                                 }
                                                 Err(e) => {
                                                     log_error!(&event_tx, "Failed to create file {:?}: {}", file_path, e);
                                                     let resp = Frame::FileTransferResponse(platform_passer_core::FileTransferResponse { id: req.id, accepted: false });
-                                                    let _ = ws_sink.send(WsMessage::Binary(bincode::serialize(&resp)?)).await;
+                                                    let _ = ws_sink.send(WsMessage::Binary(codec.encode_message(&resp)?)).await;
                                                 }
                                             }
                                         }
                                     }
                                     Frame::FileData { id, chunk } => {
-                                        if let Some(file) = active_files.get_mut(&id) {
-                                            let _ = file.write_all(&chunk).await;
+                                        if let Err(overrun) = active_files.write(id, &chunk).await {
+                                            log_warn!(&event_tx, "Aborted {:?} from {}: {}", overrun.path, device.name, overrun);
+                                            let _ = event_tx.send(SessionEvent::TransferFailed { id, reason: overrun.to_string() }).await;
+                                            // A clipboard batch missing a file can never complete
+                                            if let Some((batch_id, _)) = active_downloads.remove(&id) {
+                                                incoming_batches.remove(&batch_id);
+                                            }
                                         }
                                     }
                                     Frame::FileEnd { id } => {
-                                        if let Some(mut file) = active_files.remove(&id) {
-                                            let _ = file.flush().await;
-                                            
+                                        let finished = active_files.finish(id).await;
+                                        if finished {
                                             if let Some((batch_id, path)) = active_downloads.remove(&id) {
                                                 if let Some((remaining, paths)) = incoming_batches.get_mut(&batch_id) {
                                                     paths.push(path);
@@ -473,15 +650,17 @@ async fn handle_protocol_session(
                                     _ => {}
                                 }
                             }
-                            Err(e) => {
-                                log_error!(&event_tx, "Error deserializing frame: {}", e);
-                            }
                         }
                     }
                     Ok(Some(Ok(WsMessage::Close(_)))) | Ok(None) => {
                         log_info!(&event_tx, "Client closed connection.");
                         break;
                     }
+                    Ok(Some(Err(WsError::Capacity(e)))) => {
+                        log_warn!(&event_tx, "Disconnecting {}: {}", addr, e);
+                        let _ = event_tx.send(SessionEvent::ProtocolViolation { peer: peer_id.clone(), reason: e.to_string() }).await;
+                        break;
+                    }
                     Ok(Some(Err(e))) => {
                         log_error!(&event_tx, "WebSocket read error: {}", e);
                         break;
@@ -496,8 +675,25 @@ async fn handle_protocol_session(
             // Send events to client
             result = broadcast_rx.recv() => {
                 match result {
-                    Ok(frame) => {
-                        let bytes = bincode::serialize(&frame)?;
+                    Ok(outbound) => {
+                        let Some(frame) = route.filter(outbound) else {
+                            continue;
+                        };
+                        if !capabilities.allows(&frame) {
+                            continue;
+                        }
+                        if let Err(denial) = policy.check_outgoing(&frame) {
+                            denials.record(&event_tx, denial).await;
+                            continue;
+                        }
+                        let Some(frame) = layout.filter(frame) else {
+                            continue;
+                        };
+                        let Some(mut frame) = frame.for_version(protocol_version) else {
+                            continue;
+                        };
+                        sequencer.number(&mut frame);
+                        let bytes = codec.encode_message(&frame)?;
                         if let Err(e) = ws_sink.send(WsMessage::Binary(bytes)).await {
                             log_error!(&event_tx, "Failed to send frame: {}", e);
                             break;
@@ -511,7 +707,11 @@ async fn handle_protocol_session(
     }
 
     log_info!(&event_tx, "Session terminated.");
-    let _ = source.set_remote(false);
+    // Control stays where it is if another client has it
+    if route.engaged() {
+        let _ = source.set_remote(false);
+    }
+    let _ = source.set_peer_screen(&peer_id, None);
     let _ = event_tx.send(SessionEvent::Disconnected).await;
     Ok(())
 }
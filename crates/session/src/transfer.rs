@@ -0,0 +1,85 @@
+//! Files being received from a peer. Each is held to the size its request declared and to the
+//! policy's `max_transfer_bytes`, so a peer cannot fill the disk by sending more than it asked to.
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+/// Incoming transfers by id.
+#[derive(Default)]
+pub struct IncomingFiles {
+    files: HashMap<u32, IncomingFile>,
+}
+
+struct IncomingFile {
+    file: File,
+    path: PathBuf,
+    received: u64,
+    limit: u64,
+}
+
+/// A transfer that was sent more bytes than it may hold. Its file has been deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overrun {
+    pub id: u32,
+    pub path: PathBuf,
+    /// Bytes received, counting the chunk that went over.
+    pub received: u64,
+    pub limit: u64,
+}
+
+impl fmt::Display for Overrun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transfer {} sent {} bytes, more than the {} allowed", self.id, self.received, self.limit)
+    }
+}
+
+impl std::error::Error for Overrun {}
+
+impl IncomingFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts receiving transfer `id` into `file` at `path`, which may take up to `declared`
+    /// bytes, and no more than `max` if set.
+    pub fn start(&mut self, id: u32, file: File, path: PathBuf, declared: u64, max: Option<u64>) {
+        let limit = max.map_or(declared, |max| declared.min(max));
+        self.files.insert(id, IncomingFile { file, path, received: 0, limit });
+    }
+
+    /// Writes a chunk of transfer `id`. Chunks of unknown transfers are dropped. A chunk that
+    /// takes the transfer past its limit aborts it and deletes the file.
+    pub async fn write(&mut self, id: u32, chunk: &[u8]) -> Result<(), Overrun> {
+        let Some(incoming) = self.files.get_mut(&id) else { return Ok(()) };
+        incoming.received += chunk.len() as u64;
+        if incoming.received <= incoming.limit {
+            let _ = incoming.file.write_all(chunk).await;
+            return Ok(());
+        }
+        let (received, limit) = (incoming.received, incoming.limit);
+        let path = self.abort(id).await.unwrap_or_default();
+        Err(Overrun { id, path, received, limit })
+    }
+
+    /// Stops receiving transfer `id` and deletes what arrived of it, returning its path.
+    pub async fn abort(&mut self, id: u32) -> Option<PathBuf> {
+        let IncomingFile { file, path, .. } = self.files.remove(&id)?;
+        // Closed first, or Windows refuses to delete it
+        drop(file);
+        let _ = tokio::fs::remove_file(&path).await;
+        Some(path)
+    }
+
+    /// Finishes transfer `id`, returning whether it was being received.
+    pub async fn finish(&mut self, id: u32) -> bool {
+        match self.files.remove(&id) {
+            Some(mut incoming) => {
+                let _ = incoming.file.flush().await;
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Input latency histogram, and loss and ordering from the stamps' sequence numbers.
+use platform_passer_core::InputStamp;
+use platform_passer_session::input_stats::{InputLatencyTracker, LATENCY_BUCKETS_MS};
+
+/// Records `seqs` in order, each received 1 ms after capture.
+fn feed(seqs: &[u64]) -> InputLatencyTracker {
+    let mut tracker = InputLatencyTracker::new();
+    for &seq in seqs {
+        tracker.record(&InputStamp { seq, captured_at_us: 0 }, 1_000);
+    }
+    tracker
+}
+
+#[test]
+fn latencies_land_in_their_buckets() {
+    let mut tracker = InputLatencyTracker::new();
+    // Bounds are inclusive; past the last one goes to the overflow bucket
+    for (seq, latency_us) in [0, 1_000, 1_001, 20_000, 250_000, 250_001, 900_000].into_iter().enumerate() {
+        tracker.record(&InputStamp { seq: seq as u64, captured_at_us: 10_000_000 }, 10_000_000 + latency_us);
+    }
+    let report = tracker.report();
+    assert_eq!(report.buckets.len(), LATENCY_BUCKETS_MS.len() + 1);
+    assert_eq!(report.buckets, vec![2, 1, 0, 0, 1, 0, 0, 1, 2]);
+    assert_eq!(report.samples, 7);
+    assert_eq!(report.max_ms, 900.0);
+    assert_eq!(report.fraction_within_ms(2), 3.0 / 7.0);
+    assert_eq!(report.fraction_within_ms(20), 4.0 / 7.0);
+}
+
+#[test]
+fn clock_skew_counts_as_zero_latency() {
+    let mut tracker = InputLatencyTracker::new();
+    tracker.record(&InputStamp { seq: 0, captured_at_us: 5_000 }, 1_000);
+    let report = tracker.report();
+    assert_eq!(report.buckets[0], 1);
+    assert_eq!(report.mean_ms, 0.0);
+}
+
+#[test]
+fn in_order_input_is_neither_lost_nor_reordered() {
+    let report = feed(&[3, 4, 5, 6]).report();
+    assert_eq!((report.lost, report.out_of_order), (0, 0));
+    assert_eq!(report.mean_ms, 1.0);
+}
+
+#[test]
+fn gaps_count_as_lost() {
+    let report = feed(&[0, 1, 4, 5, 9]).report();
+    assert_eq!((report.lost, report.out_of_order), (5, 0));
+}
+
+#[test]
+fn late_arrivals_are_out_of_order_not_lost() {
+    let report = feed(&[0, 3, 1, 4, 2]).report();
+    assert_eq!((report.lost, report.out_of_order), (0, 2));
+}
+
+#[test]
+fn duplicates_are_neither_lost_nor_out_of_order() {
+    let report = feed(&[0, 1, 1, 2, 2, 0]).report();
+    assert_eq!((report.lost, report.out_of_order), (0, 0));
+
+    // A repeat does not fill a gap, and a late frame only counts once
+    let report = feed(&[0, 2, 2, 5, 3, 3]).report();
+    assert_eq!((report.lost, report.out_of_order), (2, 1));
+}
+
+#[test]
+fn empty_windows_are_not_reported() {
+    let mut tracker = InputLatencyTracker::new();
+    assert!(tracker.take_report_if_due().is_none());
+    assert_eq!(tracker.report().samples, 0);
+    assert_eq!(tracker.report().fraction_within_ms(1), 1.0);
+}
@@ -0,0 +1,61 @@
+//! Round-trip smoothing and the link quality bands.
+use platform_passer_session::latency::{RttSample, RttTracker, RTT_WINDOW};
+use platform_passer_session::LinkQuality;
+
+/// Records a round trip of `rtt_ms`, sent at `at_ms`.
+fn echo(tracker: &mut RttTracker, at_ms: u64, rtt_ms: u64) -> RttSample {
+    tracker.record(at_ms * 1000, (at_ms + rtt_ms) * 1000)
+}
+
+#[test]
+fn quality_bands_follow_the_thresholds() {
+    assert_eq!(LinkQuality::from_rtt_ms(0.0), LinkQuality::Green);
+    assert_eq!(LinkQuality::from_rtt_ms(19.9), LinkQuality::Green);
+    assert_eq!(LinkQuality::from_rtt_ms(20.0), LinkQuality::Yellow);
+    assert_eq!(LinkQuality::from_rtt_ms(50.0), LinkQuality::Yellow);
+    assert_eq!(LinkQuality::from_rtt_ms(50.1), LinkQuality::Red);
+}
+
+#[test]
+fn first_sample_has_no_jitter() {
+    let sample = echo(&mut RttTracker::new(), 0, 12);
+    assert_eq!(sample.rtt_ms, 12.0);
+    assert_eq!(sample.jitter_ms, 0.0);
+    assert_eq!(sample.quality, LinkQuality::Green);
+}
+
+#[test]
+fn average_and_jitter_cover_the_window() {
+    let mut tracker = RttTracker::new();
+    echo(&mut tracker, 0, 10);
+    echo(&mut tracker, 1000, 30);
+    let sample = echo(&mut tracker, 2000, 20);
+    assert_eq!(sample.rtt_ms, 20.0);
+    // |30 - 10| and |20 - 30|
+    assert_eq!(sample.jitter_ms, 15.0);
+    assert_eq!(sample.quality, LinkQuality::Yellow);
+}
+
+#[test]
+fn old_samples_leave_the_window() {
+    let mut tracker = RttTracker::new();
+    let spike = echo(&mut tracker, 0, 400);
+    assert_eq!(spike.quality, LinkQuality::Red);
+
+    let mut sample = spike;
+    for i in 1..RTT_WINDOW as u64 {
+        sample = echo(&mut tracker, i * 1000, 8);
+    }
+    // The spike still weighs on the average until a full window has passed
+    assert!(sample.rtt_ms > 50.0);
+    let sample = echo(&mut tracker, RTT_WINDOW as u64 * 1000, 8);
+    assert_eq!(sample.rtt_ms, 8.0);
+    assert_eq!(sample.jitter_ms, 0.0);
+    assert_eq!(sample.quality, LinkQuality::Green);
+}
+
+#[test]
+fn echoes_from_before_they_were_sent_count_as_zero() {
+    let sample = RttTracker::new().record(5_000, 4_000);
+    assert_eq!(sample.rtt_ms, 0.0);
+}
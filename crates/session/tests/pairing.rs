@@ -0,0 +1,162 @@
+//! Pairing from the client's side, against a server that lets it in without its user ever
+//! entering the code, as a device in the middle would.
+use futures_util::{SinkExt, StreamExt};
+use platform_passer_core::config::{config_dir, AppConfig};
+use platform_passer_core::{CapabilitySet, DeviceAuth, Frame, FrameCodec, FrameLimits, Handshake};
+use platform_passer_session::{run_client_session, SessionCommand, SessionEvent};
+use platform_passer_transport::device::nonce;
+use platform_passer_transport::{accept_ws, DeviceKey, Exchange, PeerStream, Role, TrustedPeers};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// Points the config directory at a scratch home, with the client's device key in place so
+/// concurrent tests do not race to create it.
+fn scratch_home() -> &'static PathBuf {
+    static HOME: OnceLock<PathBuf> = OnceLock::new();
+    HOME.get_or_init(|| {
+        let home = std::env::temp_dir().join(format!("platform-passer-pairing-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::env::set_var("HOME", &home);
+        DeviceKey::load_or_generate(&config_dir().join("device.key")).unwrap();
+        home
+    })
+}
+
+fn is_trusted(server: &DeviceKey) -> bool {
+    TrustedPeers::load(config_dir().join("trusted_peers")).unwrap().get(&server.id()).is_some()
+}
+
+async fn send(ws: &mut WebSocketStream<PeerStream>, frame: Frame) {
+    ws.send(Message::Binary(FrameCodec::new(FrameLimits::default()).encode_message(&frame).unwrap())).await.unwrap();
+}
+
+async fn recv(ws: &mut WebSocketStream<PeerStream>) -> Option<Frame> {
+    loop {
+        match ws.next().await {
+            Some(Ok(Message::Binary(bytes))) => return Some(FrameCodec::new(FrameLimits::default()).decode_message(&bytes).unwrap()),
+            Some(Ok(_)) => continue,
+            _ => return None,
+        }
+    }
+}
+
+/// Serves one client: authenticates like a real server, then answers `Accepted` right away.
+async fn accepting_server(key: DeviceKey) -> (SocketAddr, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        // The client drops its first attempt when the plain-WebSocket config reaches it
+        let mut ws = loop {
+            let (tcp, _) = listener.accept().await.unwrap();
+            if let Ok(ws) = accept_ws(tcp, &FrameLimits::default()).await {
+                break ws;
+            }
+        };
+        let Some(Frame::Handshake(_)) = recv(&mut ws).await else { panic!("expected the client's handshake") };
+        let handshake = Handshake::new(key.id(), CapabilitySet::all(), None).with_device_name("server");
+        send(&mut ws, Frame::Handshake(handshake)).await;
+
+        let Some(Frame::DeviceAuth(DeviceAuth::Commit { public_key: client_key, .. })) = recv(&mut ws).await else {
+            panic!("expected the client's commitment")
+        };
+        let server_nonce = nonce().unwrap();
+        send(&mut ws, Frame::DeviceAuth(DeviceAuth::Hello { public_key: key.public_key(), nonce: server_nonce.clone() })).await;
+        let Some(Frame::DeviceAuth(DeviceAuth::Reveal { nonce: client_nonce })) = recv(&mut ws).await else {
+            panic!("expected the client's nonce")
+        };
+        let exchange = Exchange::new(&client_key, &client_nonce, &key.public_key(), &server_nonce).unwrap();
+        let Some(Frame::DeviceAuth(DeviceAuth::Proof(_))) = recv(&mut ws).await else { panic!("expected the client's proof") };
+        send(&mut ws, Frame::DeviceAuth(DeviceAuth::Proof(exchange.prove(&key, Role::Server)))).await;
+
+        let Some(Frame::DeviceAuth(DeviceAuth::PairingRequired)) = recv(&mut ws).await else { panic!("expected to pair") };
+        send(&mut ws, Frame::DeviceAuth(DeviceAuth::PairingRequired)).await;
+        send(&mut ws, Frame::DeviceAuth(DeviceAuth::Accepted)).await;
+        while recv(&mut ws).await.is_some() {}
+    });
+    (addr, server)
+}
+
+/// Starts a plain-WebSocket client session against `addr`.
+fn client(addr: SocketAddr) -> (mpsc::Sender<SessionCommand>, mpsc::Receiver<SessionEvent>, JoinHandle<anyhow::Result<()>>) {
+    let (cmd_tx, cmd_rx) = mpsc::channel(8);
+    let (event_tx, event_rx) = mpsc::channel(100);
+    let mut config = AppConfig::default();
+    config.network.tls = false;
+    cmd_tx.try_send(SessionCommand::UpdateConfig(Box::new(config))).unwrap();
+    (cmd_tx, event_rx, tokio::spawn(run_client_session(addr, None, cmd_rx, event_tx)))
+}
+
+/// Waits for the first event `pick` accepts.
+async fn wait_for<T>(events: &mut mpsc::Receiver<SessionEvent>, mut pick: impl FnMut(SessionEvent) -> Option<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let Some(found) = pick(events.recv().await.expect("session ended")) {
+                return found;
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for a session event")
+}
+
+#[tokio::test]
+async fn server_acceptance_alone_does_not_pair() {
+    let dir = scratch_home().join("unconfirmed");
+    let key = DeviceKey::load_or_generate(&dir.join("server.key")).unwrap();
+    let (addr, server) = accepting_server(DeviceKey::load_or_generate(&dir.join("server.key")).unwrap()).await;
+    let (cmd_tx, mut events, session) = client(addr);
+
+    let (pairing_addr, _code) = wait_for(&mut events, |event| match event {
+        SessionEvent::PairingCode { addr, code, .. } => Some((addr, code)),
+        _ => None,
+    })
+    .await;
+    // Give the server's `Accepted` time to arrive; the client must keep waiting for its user
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    while let Ok(event) = events.try_recv() {
+        assert!(!matches!(event, SessionEvent::Paired { .. } | SessionEvent::ProtocolNegotiated { .. }), "paired without the user: {:?}", event);
+    }
+    assert!(!is_trusted(&key));
+
+    cmd_tx.send(SessionCommand::ConfirmPairing { addr: pairing_addr, code: None }).await.unwrap();
+    let ended = tokio::time::timeout(Duration::from_secs(10), session).await.unwrap().unwrap();
+    assert!(ended.is_err(), "declined pairing should end the session");
+    assert!(!is_trusted(&key));
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn confirming_the_code_pairs() {
+    let dir = scratch_home().join("confirmed");
+    let key = DeviceKey::load_or_generate(&dir.join("server.key")).unwrap();
+    let (addr, server) = accepting_server(DeviceKey::load_or_generate(&dir.join("server.key")).unwrap()).await;
+    let (cmd_tx, mut events, session) = client(addr);
+
+    let (pairing_addr, code) = wait_for(&mut events, |event| match event {
+        SessionEvent::PairingCode { addr, code, .. } => Some((addr, code)),
+        _ => None,
+    })
+    .await;
+    assert!(!is_trusted(&key));
+
+    cmd_tx.send(SessionCommand::ConfirmPairing { addr: pairing_addr, code: Some(code) }).await.unwrap();
+    let peer = wait_for(&mut events, |event| match event {
+        SessionEvent::Paired { peer, .. } => Some(peer),
+        _ => None,
+    })
+    .await;
+    assert_eq!(peer, key.id());
+    assert!(is_trusted(&key));
+
+    cmd_tx.send(SessionCommand::Disconnect).await.unwrap();
+    let ended = tokio::time::timeout(Duration::from_secs(10), session).await.unwrap().unwrap();
+    assert!(ended.is_ok());
+    server.await.unwrap();
+}
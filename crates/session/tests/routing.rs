@@ -0,0 +1,67 @@
+//! Routing captured input: each client gets the input meant for it and the switches that concern it.
+use platform_passer_core::{ClipboardEvent, Frame, InputEvent, InputFrame, ScreenSide};
+use platform_passer_session::routing::{InputRoute, Outbound};
+
+fn input(to: Option<&str>, event: InputEvent) -> Outbound {
+    Outbound { to: to.map(str::to_string), frame: Frame::Input(InputFrame::captured(event)) }
+}
+
+fn switch(to: Option<&str>, side: ScreenSide) -> Outbound {
+    input(to, InputEvent::ScreenSwitch(side))
+}
+
+fn key(to: Option<&str>) -> Outbound {
+    input(to, InputEvent::Keyboard { usage: 0x04, is_down: true, hint: None })
+}
+
+/// The events `route` lets through, in order.
+fn run(route: &mut InputRoute, outbound: Vec<Outbound>) -> Vec<InputEvent> {
+    outbound
+        .into_iter()
+        .filter_map(|o| route.filter(o))
+        .map(|frame| match frame {
+            Frame::Input(input) => input.event,
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn input_goes_only_to_the_peer_it_is_meant_for() {
+    let mut laptop = InputRoute::new("laptop");
+    let mut desktop = InputRoute::new("desktop");
+    let frames = || vec![switch(Some("laptop"), ScreenSide::Remote), key(Some("laptop"))];
+
+    assert_eq!(run(&mut laptop, frames()).len(), 2);
+    assert!(laptop.engaged());
+    assert!(run(&mut desktop, frames()).is_empty());
+    assert!(!desktop.engaged());
+}
+
+#[test]
+fn switching_peers_sends_the_previous_one_home() {
+    let mut laptop = InputRoute::new("laptop");
+    let events = run(&mut laptop, vec![switch(Some("laptop"), ScreenSide::Remote), switch(Some("desktop"), ScreenSide::Remote), key(Some("desktop"))]);
+    assert!(matches!(events[..], [InputEvent::ScreenSwitch(ScreenSide::Remote), InputEvent::ScreenSwitch(ScreenSide::Local)]));
+    assert!(!laptop.engaged());
+}
+
+#[test]
+fn returning_home_reaches_the_controlled_peer_once() {
+    let mut laptop = InputRoute::new("laptop");
+    let mut desktop = InputRoute::new("desktop");
+    run(&mut laptop, vec![switch(Some("laptop"), ScreenSide::Remote)]);
+
+    // The source has already forgotten the peer when it switches back
+    assert_eq!(run(&mut laptop, vec![switch(None, ScreenSide::Local)]).len(), 1);
+    assert!(!laptop.engaged());
+    assert!(run(&mut desktop, vec![switch(Some("laptop"), ScreenSide::Local)]).is_empty());
+}
+
+#[test]
+fn untargeted_frames_reach_every_peer() {
+    let mut laptop = InputRoute::new("laptop");
+    assert_eq!(run(&mut laptop, vec![switch(None, ScreenSide::Remote), key(None)]).len(), 2);
+    let clipboard = Frame::Clipboard(ClipboardEvent::Text("hi".into()));
+    assert!(laptop.filter(clipboard.into()).is_some());
+}
@@ -0,0 +1,56 @@
+//! Receiving files: each transfer is held to the size it declared and to the policy's limit.
+use platform_passer_session::transfer::{IncomingFiles, Overrun};
+use std::path::PathBuf;
+use tokio::fs::File;
+
+/// A fresh file for `name` in a scratch directory for this test run.
+async fn create(name: &str) -> (File, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("platform-passer-transfer-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+    let path = dir.join(name);
+    (File::create(&path).await.unwrap(), path)
+}
+
+#[tokio::test]
+async fn a_transfer_of_the_declared_size_is_kept() {
+    let (file, path) = create("exact.bin").await;
+    let mut files = IncomingFiles::new();
+    files.start(1, file, path.clone(), 8, None);
+    files.write(1, b"abcd").await.unwrap();
+    files.write(1, b"efgh").await.unwrap();
+    assert!(files.finish(1).await);
+    assert_eq!(tokio::fs::read(&path).await.unwrap(), b"abcdefgh");
+}
+
+#[tokio::test]
+async fn sending_more_than_declared_aborts_and_deletes_the_file() {
+    let (file, path) = create("overrun.bin").await;
+    let mut files = IncomingFiles::new();
+    files.start(2, file, path.clone(), 8, None);
+    files.write(2, b"abcdef").await.unwrap();
+    let overrun = files.write(2, b"ghi").await.unwrap_err();
+    assert_eq!(overrun, Overrun { id: 2, path: path.clone(), received: 9, limit: 8 });
+    assert!(!path.exists());
+
+    // The rest of it is dropped, and its end finishes nothing
+    files.write(2, b"jkl").await.unwrap();
+    assert!(!files.finish(2).await);
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn the_policy_limit_applies_below_the_declared_size() {
+    let (file, path) = create("limited.bin").await;
+    let mut files = IncomingFiles::new();
+    files.start(3, file, path.clone(), 1024, Some(4));
+    files.write(3, b"abcd").await.unwrap();
+    assert_eq!(files.write(3, b"e").await.unwrap_err().limit, 4);
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn chunks_of_unknown_transfers_are_dropped() {
+    let mut files = IncomingFiles::new();
+    files.write(9, b"stray").await.unwrap();
+    assert!(!files.finish(9).await);
+}
@@ -1,30 +1,58 @@
 use anyhow::{Result, Context};
+use platform_passer_core::FrameLimits;
 use std::net::SocketAddr;
 use tokio::net::TcpStream;
-use tokio_tungstenite::{connect_async, WebSocketStream, MaybeTlsStream};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::{client_async_with_config, WebSocketStream};
+use crate::stream::PeerStream;
+use crate::tls::{self, TlsMismatch};
 
-pub async fn connect_ws(addr: SocketAddr) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
-    let url = format!("ws://{}", addr);
-    tracing::debug!("Connecting to WebSocket at {}", url);
-    
-    let (ws_stream, _) = connect_async(url)
-        .await
-        .context(format!("Failed to connect to WebSocket at {}", addr))?;
+/// WebSocket settings that refuse messages larger than any frame `limits` would accept.
+pub fn ws_config(limits: &FrameLimits) -> WebSocketConfig {
+    WebSocketConfig {
+        max_message_size: Some(limits.max_envelope_len()),
+        max_frame_size: Some(limits.max_envelope_len()),
+        ..Default::default()
+    }
+}
 
-    // Set TCP_NODELAY on the underlying TCP stream
-    let res = match ws_stream.get_ref() {
-        MaybeTlsStream::Plain(s) => s.set_nodelay(true),
-        _ => {
-            // Potentially handle other TLS streams if they are enabled via features
-            // and use nested get_ref() to reach the underlying TcpStream.
-            Ok(())
+pub async fn connect_ws(addr: SocketAddr, limits: &FrameLimits) -> Result<WebSocketStream<PeerStream>> {
+    let tcp = connect_tcp(addr).await?;
+    upgrade(format!("ws://{}", addr), PeerStream::Plain(tcp), limits).await
+}
+
+/// Like `connect_ws`, over TLS. A server without TLS fails with `tls::TlsMismatch`. Any
+/// certificate gets through: the caller checks
+/// `PeerStream::peer_fingerprint` against `KnownPeers` once the server has authenticated as a
+/// device, since the pin belongs to the device rather than to the address it answers at.
+pub async fn connect_wss(addr: SocketAddr, limits: &FrameLimits) -> Result<WebSocketStream<PeerStream>> {
+    let tcp = connect_tcp(addr).await?;
+    let tls_stream = match tls::connector().connect(rustls::ServerName::IpAddress(addr.ip()), tcp).await {
+        Ok(stream) => stream,
+        // Tell a server without TLS apart from a broken handshake
+        Err(_) if connect_ws(addr, limits).await.is_ok() => {
+            return Err(TlsMismatch { peer: addr.to_string(), peer_uses_tls: false }.into());
         }
+        Err(e) => return Err(e).context(format!("TLS handshake with {} failed", addr)),
     };
-    
-    if let Err(e) = res {
+    upgrade(format!("wss://{}", addr), PeerStream::Tls(Box::new(tls_stream.into())), limits).await
+}
+
+async fn connect_tcp(addr: SocketAddr) -> Result<TcpStream> {
+    tracing::debug!("Connecting to {}", addr);
+    let tcp = TcpStream::connect(addr)
+        .await
+        .context(format!("Failed to connect to {}", addr))?;
+    if let Err(e) = tcp.set_nodelay(true) {
         tracing::warn!("Failed to set TCP_NODELAY: {}", e);
     }
-    
-    tracing::info!("Connected to WebSocket successfully at {}", addr);
+    Ok(tcp)
+}
+
+async fn upgrade(url: String, stream: PeerStream, limits: &FrameLimits) -> Result<WebSocketStream<PeerStream>> {
+    let (ws_stream, _) = client_async_with_config(url.as_str(), stream, Some(ws_config(limits)))
+        .await
+        .context(format!("Failed to connect to WebSocket at {}", url))?;
+    tracing::info!("Connected to WebSocket successfully at {}", url);
     Ok(ws_stream)
 }
@@ -0,0 +1,239 @@
+//! Long-term device keys, the exchange in which two devices prove theirs to each other, and the
+//! store of devices this machine has paired with.
+//!
+//! The exchange is bound into a 6-digit pairing code. Both sides derive the same code only if
+//! they saw the same keys and nonces, so a server whose user enters the code the client shows
+//! knows that nobody sits in between. The client cannot see that check; it relies on its own
+//! user confirming the code once the server has accepted it.
+use crate::tls::write_private;
+use anyhow::{anyhow, bail, Context, Result};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Length of the nonces each side contributes to an exchange.
+pub const NONCE_LEN: usize = 32;
+
+const TRANSCRIPT_LABEL: &[u8] = b"platform-passer device auth v1";
+const COMMITMENT_LABEL: &[u8] = b"platform-passer nonce commitment";
+const CODE_LABEL: &[u8] = b"platform-passer pairing code";
+const ID_LABEL: &[u8] = b"platform-passer device id";
+
+/// This machine's Ed25519 key pair.
+pub struct DeviceKey {
+    pair: Ed25519KeyPair,
+}
+
+impl DeviceKey {
+    /// Loads the PKCS#8 key kept at `path`, generating and saving one on first run.
+    pub fn load_or_generate(path: &Path) -> Result<Self> {
+        let pkcs8 = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                    .map_err(|_| anyhow!("Failed to generate a device key"))?;
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).context(format!("Failed to create {}", parent.display()))?;
+                }
+                write_private(path, pkcs8.as_ref()).context(format!("Failed to write {}", path.display()))?;
+                tracing::info!("Generated a device key in {}", path.display());
+                pkcs8.as_ref().to_vec()
+            }
+            Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+        };
+        let pair = Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|_| anyhow!("Invalid device key in {}", path.display()))?;
+        Ok(Self { pair })
+    }
+
+    pub fn public_key(&self) -> Vec<u8> {
+        self.pair.public_key().as_ref().to_vec()
+    }
+
+    /// This machine's device ID.
+    pub fn id(&self) -> String {
+        device_id(&self.public_key())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.pair.sign(message).as_ref().to_vec()
+    }
+}
+
+/// Checks an Ed25519 `signature` by `public_key` over `message`.
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(message, signature)
+        .map_err(|_| anyhow!("Invalid signature"))
+}
+
+/// A fresh random nonce.
+pub fn nonce() -> Result<Vec<u8>> {
+    let mut nonce = vec![0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| anyhow!("Failed to generate a nonce"))?;
+    Ok(nonce)
+}
+
+/// What the client sends in place of its nonce until it has seen the server's.
+pub fn commitment(nonce: &[u8]) -> Vec<u8> {
+    Sha256::new().chain_update(COMMITMENT_LABEL).chain_update(nonce).finalize().to_vec()
+}
+
+/// Which end of the connection signs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+impl Role {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Role::Client => b"client",
+            Role::Server => b"server",
+        }
+    }
+}
+
+/// Both devices' keys and nonces, as each side signs them.
+pub struct Exchange {
+    transcript: Vec<u8>,
+}
+
+impl Exchange {
+    pub fn new(client_key: &[u8], client_nonce: &[u8], server_key: &[u8], server_nonce: &[u8]) -> Result<Self> {
+        if client_nonce.len() != NONCE_LEN || server_nonce.len() != NONCE_LEN {
+            bail!("Nonces must be {} bytes", NONCE_LEN);
+        }
+        let mut transcript = TRANSCRIPT_LABEL.to_vec();
+        for part in [client_key, client_nonce, server_key, server_nonce] {
+            transcript.extend_from_slice(&(part.len() as u32).to_le_bytes());
+            transcript.extend_from_slice(part);
+        }
+        Ok(Self { transcript })
+    }
+
+    /// Signs the exchange as `role`. The role is signed too, so a proof cannot be reflected back.
+    pub fn prove(&self, key: &DeviceKey, role: Role) -> Vec<u8> {
+        key.sign(&self.signed_by(role))
+    }
+
+    /// Checks that the device holding `public_key` signed the exchange as `role`.
+    pub fn verify(&self, public_key: &[u8], role: Role, signature: &[u8]) -> Result<()> {
+        verify(public_key, &self.signed_by(role), signature)
+    }
+
+    /// Proves knowledge of a pre-shared `secret` as `role`. Bound to this exchange, whose nonces
+    /// make it a fresh challenge, so a proof cannot be replayed on another connection.
+    pub fn prove_secret(&self, secret: &[u8], role: Role) -> Vec<u8> {
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret), &self.signed_by(role)).as_ref().to_vec()
+    }
+
+    /// Checks, in constant time, that `proof` came from a device holding `secret` as `role`.
+    pub fn verify_secret(&self, secret: &[u8], role: Role, proof: &[u8]) -> Result<()> {
+        hmac::verify(&hmac::Key::new(hmac::HMAC_SHA256, secret), &self.signed_by(role), proof)
+            .map_err(|_| anyhow!("Wrong pre-shared key"))
+    }
+
+    /// The 6-digit code both sides show or ask for while pairing.
+    pub fn pairing_code(&self) -> String {
+        let digest = Sha256::new().chain_update(CODE_LABEL).chain_update(&self.transcript).finalize();
+        let value = u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]]);
+        format!("{:06}", value % 1_000_000)
+    }
+
+    fn signed_by(&self, role: Role) -> Vec<u8> {
+        let mut message = role.label().to_vec();
+        message.extend_from_slice(&self.transcript);
+        message
+    }
+}
+
+/// Hex of a device public key, as it is kept in `TrustedPeers`.
+pub fn key_id(public_key: &[u8]) -> String {
+    public_key.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The stable ID a device goes by, derived from its public key: the first 8 bytes of its
+/// SHA-256, in groups of four hex digits such as `3f2a-91bc-07de-4411`.
+pub fn device_id(public_key: &[u8]) -> String {
+    let digest = Sha256::new().chain_update(ID_LABEL).chain_update(public_key).finalize();
+    digest[..8].chunks(2).map(key_id).collect::<Vec<_>>().join("-")
+}
+
+/// A device this machine has paired with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedPeer {
+    /// Hex of its public key; the ID alone is too short to trust on.
+    pub key: String,
+    /// What it was called when it paired.
+    pub name: String,
+}
+
+/// The devices this machine has paired with, by device ID. Kept one `id key name` line each.
+pub struct TrustedPeers {
+    path: PathBuf,
+    peers: BTreeMap<String, TrustedPeer>,
+}
+
+impl TrustedPeers {
+    /// Reads the store at `path`. A missing file trusts nobody.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let peers = match std::fs::read_to_string(&path) {
+            Ok(text) => text
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.splitn(3, ' ');
+                    let (id, key) = (fields.next()?, fields.next()?);
+                    let name = fields.next().unwrap_or_default().trim().to_string();
+                    Some((id.to_string(), TrustedPeer { key: key.to_string(), name }))
+                })
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+        };
+        Ok(Self { path, peers })
+    }
+
+    /// Whether the device holding `public_key` has paired with this machine.
+    pub fn is_trusted(&self, public_key: &[u8]) -> bool {
+        self.peers.get(&device_id(public_key)).is_some_and(|peer| peer.key == key_id(public_key))
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TrustedPeer> {
+        self.peers.get(id)
+    }
+
+    /// Paired devices by device ID.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TrustedPeer)> {
+        self.peers.iter().map(|(id, peer)| (id.as_str(), peer))
+    }
+
+    /// Trusts `public_key` from now on, under `name`.
+    pub fn trust(&mut self, public_key: &[u8], name: &str) -> Result<()> {
+        // One line per peer: the name must not run onto the next
+        let name = name.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+        self.peers.insert(device_id(public_key), TrustedPeer { key: key_id(public_key), name });
+        self.save()
+    }
+
+    /// Stops trusting the device `id`; it has to pair again.
+    pub fn forget(&mut self, id: &str) -> Result<bool> {
+        let removed = self.peers.remove(id).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text: String = self.peers.iter().map(|(id, peer)| format!("{} {} {}\n", id, peer.key, peer.name)).collect();
+        std::fs::write(&self.path, text).context(format!("Failed to write {}", self.path.display()))
+    }
+}
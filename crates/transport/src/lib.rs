@@ -1,5 +1,13 @@
 pub mod client;
+pub mod device;
 pub mod server;
+pub mod stream;
+pub mod throttle;
+pub mod tls;
 
 pub use client::*;
+pub use device::{device_id, DeviceKey, Exchange, Role, TrustedPeer, TrustedPeers};
 pub use server::*;
+pub use stream::PeerStream;
+pub use throttle::FailedAttempts;
+pub use tls::{KnownPeers, TlsIdentity, FingerprintMismatch, TlsMismatch};
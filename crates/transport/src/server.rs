@@ -1,6 +1,14 @@
 use anyhow::{Result, Context};
+use platform_passer_core::FrameLimits;
 use std::net::SocketAddr;
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::{accept_async_with_config, WebSocketStream};
+use crate::client::ws_config;
+use crate::stream::PeerStream;
+use crate::tls::TlsMismatch;
+
+const TLS_HANDSHAKE_RECORD: u8 = 0x16;
 
 pub async fn make_ws_listener(bind_addr: SocketAddr) -> Result<TcpListener> {
     tracing::debug!("Creating WebSocket TCP listener on {}", bind_addr);
@@ -9,6 +17,37 @@ pub async fn make_ws_listener(bind_addr: SocketAddr) -> Result<TcpListener> {
         .context(format!("Failed to bind TCP listener to {}", bind_addr))?;
     
     tracing::info!("WebSocket server listener created successfully on {}", bind_addr);
-    // Note: TCP_NODELAY must be set on accepted streams in the session loop
     Ok(listener)
 }
+
+/// Performs the WebSocket upgrade on an accepted stream, bounding message sizes by `limits`.
+pub async fn accept_ws(stream: TcpStream, limits: &FrameLimits) -> Result<WebSocketStream<PeerStream>> {
+    set_nodelay(&stream);
+    upgrade(PeerStream::Plain(stream), limits).await
+}
+
+/// Like `accept_ws`, after a TLS handshake presenting `acceptor`'s certificate. A client without
+/// TLS fails with `tls::TlsMismatch`.
+pub async fn accept_wss(stream: TcpStream, limits: &FrameLimits, acceptor: &TlsAcceptor) -> Result<WebSocketStream<PeerStream>> {
+    set_nodelay(&stream);
+    // Every TLS connection opens with a handshake record; a plain WebSocket client sends "GET"
+    let mut first = [0u8; 1];
+    if stream.peek(&mut first).await.is_ok_and(|n| n == 1) && first[0] != TLS_HANDSHAKE_RECORD {
+        let peer = stream.peer_addr().map_or_else(|_| "The client".to_string(), |addr| addr.to_string());
+        return Err(TlsMismatch { peer, peer_uses_tls: false }.into());
+    }
+    let tls_stream = acceptor.accept(stream).await.context("TLS handshake failed")?;
+    upgrade(PeerStream::Tls(Box::new(tls_stream.into())), limits).await
+}
+
+fn set_nodelay(stream: &TcpStream) {
+    if let Err(e) = stream.set_nodelay(true) {
+        tracing::warn!("Failed to set TCP_NODELAY on server: {}", e);
+    }
+}
+
+async fn upgrade(stream: PeerStream, limits: &FrameLimits) -> Result<WebSocketStream<PeerStream>> {
+    accept_async_with_config(stream, Some(ws_config(limits)))
+        .await
+        .context("WebSocket upgrade failed")
+}
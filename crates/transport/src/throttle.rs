@@ -0,0 +1,56 @@
+//! Refusing addresses that keep failing authentication, so a secret cannot be guessed at
+//! network speed.
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Recent authentication failures by remote address.
+pub struct FailedAttempts {
+    max_failures: u32,
+    window: Duration,
+    lockout: Duration,
+    by_addr: HashMap<IpAddr, Failures>,
+}
+
+struct Failures {
+    count: u32,
+    since: Instant,
+    locked_until: Option<Instant>,
+}
+
+impl FailedAttempts {
+    /// Locks an address out for `lockout` once it fails `max_failures` times within `window`.
+    pub fn new(max_failures: u32, window: Duration, lockout: Duration) -> Self {
+        Self { max_failures: max_failures.max(1), window, lockout, by_addr: HashMap::new() }
+    }
+
+    /// Records a failure from `addr`. Returns whether it locked the address out.
+    pub fn record(&mut self, addr: IpAddr, now: Instant) -> bool {
+        self.by_addr.retain(|_, f| f.locked_until.map_or(now < f.since + self.window, |until| now < until));
+        let failures = self.by_addr.entry(addr).or_insert(Failures { count: 0, since: now, locked_until: None });
+        failures.count += 1;
+        if failures.count >= self.max_failures && failures.locked_until.is_none() {
+            failures.locked_until = Some(now + self.lockout);
+            return true;
+        }
+        false
+    }
+
+    /// How long an address stays locked out.
+    pub fn lockout(&self) -> Duration {
+        self.lockout
+    }
+
+    /// How much longer `addr` is locked out, if it is.
+    pub fn locked_out(&self, addr: IpAddr, now: Instant) -> Option<Duration> {
+        let until = self.by_addr.get(&addr)?.locked_until?;
+        (now < until).then(|| until - now)
+    }
+}
+
+impl Default for FailedAttempts {
+    /// Five failures a minute lock an address out for five minutes.
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(60), Duration::from_secs(300))
+    }
+}
@@ -0,0 +1,234 @@
+//! Encryption for `wss://` connections: a self-signed certificate per machine, and
+//! trust-on-first-use pinning of the certificates peers present.
+use anyhow::{Context, Result};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, PrivateKey, ServerName};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+const CERT_FILE: &str = "cert.der";
+const KEY_FILE: &str = "key.der";
+
+/// This machine's certificate and private key, DER encoded.
+pub struct TlsIdentity {
+    cert: Vec<u8>,
+    key: Vec<u8>,
+}
+
+impl TlsIdentity {
+    /// Loads the identity kept in `dir`, generating and saving a self-signed one on first run.
+    pub fn load_or_generate(dir: &Path) -> Result<Self> {
+        let (cert_path, key_path) = (dir.join(CERT_FILE), dir.join(KEY_FILE));
+        if cert_path.exists() && key_path.exists() {
+            let cert = std::fs::read(&cert_path).context(format!("Failed to read {}", cert_path.display()))?;
+            let key = std::fs::read(&key_path).context(format!("Failed to read {}", key_path.display()))?;
+            return Ok(Self { cert, key });
+        }
+
+        let generated = rcgen::generate_simple_self_signed(vec!["platform-passer".to_string()])
+            .context("Failed to generate a TLS certificate")?;
+        let identity = Self {
+            cert: generated.serialize_der().context("Failed to encode the TLS certificate")?,
+            key: generated.serialize_private_key_der(),
+        };
+        std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+        write_private(&key_path, &identity.key).context(format!("Failed to write {}", key_path.display()))?;
+        std::fs::write(&cert_path, &identity.cert).context(format!("Failed to write {}", cert_path.display()))?;
+        tracing::info!("Generated a TLS certificate in {}", dir.display());
+        Ok(identity)
+    }
+
+    /// What peers pin this machine by.
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.cert)
+    }
+
+    pub fn acceptor(&self) -> Result<TlsAcceptor> {
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![Certificate(self.cert.clone())], PrivateKey(self.key.clone()))
+            .context("Invalid TLS certificate or key")?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+/// Owner-only on Unix; the key is what the fingerprint vouches for.
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(bytes)
+}
+
+/// SHA-256 of a DER certificate, as colon-separated uppercase hex.
+pub fn fingerprint(cert_der: &[u8]) -> String {
+    Sha256::digest(cert_der).iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":")
+}
+
+/// A connector that lets any certificate through the TLS handshake. Peers are self-signed, so
+/// there is no authority to check against: once the handshake has proven the peer holds the
+/// key, `KnownPeers` decides whether its certificate is the one expected.
+pub fn connector() -> TlsConnector {
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AnyCertificate))
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+struct AnyCertificate;
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// How `KnownPeers::verify` trusted a certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trust {
+    /// First contact: the fingerprint is now pinned.
+    Pinned,
+    /// Matches the pinned fingerprint.
+    Known,
+}
+
+/// A peer presented a different certificate from the one pinned for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FingerprintMismatch {
+    pub peer: String,
+    pub pinned: String,
+    pub presented: String,
+    /// The file the pin lives in.
+    pub store: PathBuf,
+}
+
+impl fmt::Display for FingerprintMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The TLS certificate of {} has changed (pinned {}, presented {}). Someone may be intercepting the \
+             connection. If that machine was reinstalled, remove its line from {} and connect again.",
+            self.peer,
+            self.pinned,
+            self.presented,
+            self.store.display()
+        )
+    }
+}
+
+impl std::error::Error for FingerprintMismatch {}
+
+/// One end has `network.tls` on and the other has it off, as with a peer older than TLS support.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsMismatch {
+    pub peer: String,
+    /// Whether the peer is the end using TLS.
+    pub peer_uses_tls: bool,
+}
+
+impl fmt::Display for TlsMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (peer, here) = if self.peer_uses_tls { ("uses TLS", "does not") } else { ("does not use TLS", "does") };
+        write!(
+            f,
+            "{} {} but this machine {} (network.tls). Both ends must agree; turn it on at both to keep \
+             input and clipboard encrypted.",
+            self.peer, peer, here
+        )
+    }
+}
+
+impl std::error::Error for TlsMismatch {}
+
+/// Certificate fingerprints of the peers this machine has connected to, by device ID, pinned on
+/// first contact. Kept one `peer fingerprint` pair per line, like ssh's `known_hosts`.
+pub struct KnownPeers {
+    path: PathBuf,
+    pins: BTreeMap<String, String>,
+}
+
+impl KnownPeers {
+    /// Reads the pins in `path`. A missing file has none.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let pins = match std::fs::read_to_string(&path) {
+            Ok(text) => text
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(peer, fp)| (peer.trim().to_string(), fp.trim().to_string()))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+        };
+        Ok(Self { path, pins })
+    }
+
+    pub fn pinned(&self, peer: &str) -> Option<&str> {
+        self.pins.get(peer).map(String::as_str)
+    }
+
+    /// Checks `fingerprint` against the one pinned for `peer`, pinning it if there is none yet.
+    pub fn verify(&mut self, peer: &str, fingerprint: &str) -> Result<Trust, FingerprintMismatch> {
+        match self.pins.get(peer) {
+            Some(pinned) if pinned == fingerprint => Ok(Trust::Known),
+            Some(pinned) => Err(FingerprintMismatch {
+                peer: peer.to_string(),
+                pinned: pinned.clone(),
+                presented: fingerprint.to_string(),
+                store: self.path.clone(),
+            }),
+            None => {
+                self.pins.insert(peer.to_string(), fingerprint.to_string());
+                if let Err(e) = self.save() {
+                    tracing::warn!("Failed to save pinned fingerprint for {}: {}", peer, e);
+                }
+                Ok(Trust::Pinned)
+            }
+        }
+    }
+
+    /// Moves the pin kept under `from` to `to`, unless `to` has one already. Returns whether it moved.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<bool> {
+        if self.pins.contains_key(to) {
+            return Ok(false);
+        }
+        let Some(fingerprint) = self.pins.remove(from) else { return Ok(false) };
+        self.pins.insert(to.to_string(), fingerprint);
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Drops the pin for `peer`, so its next certificate is trusted afresh.
+    pub fn forget(&mut self, peer: &str) -> Result<bool> {
+        let removed = self.pins.remove(peer).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text: String = self.pins.iter().map(|(peer, fp)| format!("{} {}\n", peer, fp)).collect();
+        std::fs::write(&self.path, text).context(format!("Failed to write {}", self.path.display()))
+    }
+}
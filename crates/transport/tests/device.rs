@@ -0,0 +1,118 @@
+//! Device keys, the signed exchange and pairing code built on them, and the trusted-peer store.
+use platform_passer_transport::device::{commitment, nonce, NONCE_LEN};
+use platform_passer_transport::{device_id, DeviceKey, Exchange, Role, TrustedPeers};
+use std::path::PathBuf;
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("platform-passer-device-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn device_key_is_generated_once_and_reloaded() {
+    let dir = scratch_dir("key");
+    let first = DeviceKey::load_or_generate(&dir.join("device.key")).unwrap();
+    let again = DeviceKey::load_or_generate(&dir.join("device.key")).unwrap();
+    assert_eq!(first.public_key(), again.public_key());
+    assert_eq!(first.public_key().len(), 32);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn both_sides_agree_and_proofs_check_out() {
+    let dir = scratch_dir("exchange");
+    let client = DeviceKey::load_or_generate(&dir.join("client.key")).unwrap();
+    let server = DeviceKey::load_or_generate(&dir.join("server.key")).unwrap();
+    let (client_nonce, server_nonce) = (nonce().unwrap(), nonce().unwrap());
+    assert_eq!(client_nonce.len(), NONCE_LEN);
+    assert_ne!(commitment(&client_nonce), commitment(&server_nonce));
+
+    let on_client = Exchange::new(&client.public_key(), &client_nonce, &server.public_key(), &server_nonce).unwrap();
+    let on_server = Exchange::new(&client.public_key(), &client_nonce, &server.public_key(), &server_nonce).unwrap();
+    let code = on_client.pairing_code();
+    assert_eq!(code, on_server.pairing_code());
+    assert!(code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()));
+
+    let proof = on_client.prove(&client, Role::Client);
+    assert!(on_server.verify(&client.public_key(), Role::Client, &proof).is_ok());
+    // Not valid as the other role, nor from another key
+    assert!(on_server.verify(&client.public_key(), Role::Server, &proof).is_err());
+    assert!(on_server.verify(&server.public_key(), Role::Client, &proof).is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn a_different_peer_changes_the_code() {
+    let dir = scratch_dir("code");
+    let client = DeviceKey::load_or_generate(&dir.join("client.key")).unwrap();
+    let server = DeviceKey::load_or_generate(&dir.join("server.key")).unwrap();
+    let impostor = DeviceKey::load_or_generate(&dir.join("impostor.key")).unwrap();
+    let (client_nonce, server_nonce) = (nonce().unwrap(), nonce().unwrap());
+    let real = Exchange::new(&client.public_key(), &client_nonce, &server.public_key(), &server_nonce).unwrap();
+    let relayed = Exchange::new(&client.public_key(), &client_nonce, &impostor.public_key(), &server_nonce).unwrap();
+    assert_ne!(real.prove(&client, Role::Client), relayed.prove(&client, Role::Client));
+    assert!(Exchange::new(&client.public_key(), &[0; 4], &server.public_key(), &server_nonce).is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn device_ids_are_stable_and_distinct() {
+    let dir = scratch_dir("id");
+    let key = DeviceKey::load_or_generate(&dir.join("device.key")).unwrap();
+    let reloaded = DeviceKey::load_or_generate(&dir.join("device.key")).unwrap();
+    assert_eq!(key.id(), reloaded.id());
+    assert_eq!(key.id(), device_id(&key.public_key()));
+    assert_eq!(key.id().len(), 19);
+    assert_eq!(key.id().split('-').count(), 4);
+    assert_ne!(device_id(&[7u8; 32]), device_id(&[8u8; 32]));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn trust_survives_a_reload_and_can_be_revoked() {
+    let dir = scratch_dir("trusted");
+    let path = dir.join("trusted_peers");
+    let mut peers = TrustedPeers::load(&path).unwrap();
+    let key = [7u8; 32];
+    assert!(!peers.is_trusted(&key));
+    peers.trust(&key, "office laptop\nsecond line").unwrap();
+
+    let mut reloaded = TrustedPeers::load(&path).unwrap();
+    assert!(reloaded.is_trusted(&key));
+    let id = device_id(&key);
+    assert_eq!(reloaded.get(&id).map(|peer| peer.name.as_str()), Some("office laptop second line"));
+    assert_eq!(reloaded.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![id.as_str()]);
+    assert!(reloaded.forget(&id).unwrap());
+    assert!(!TrustedPeers::load(&path).unwrap().is_trusted(&key));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn a_key_claiming_a_trusted_id_is_not_trusted() {
+    let dir = scratch_dir("claimed");
+    let path = dir.join("trusted_peers");
+    let key = [7u8; 32];
+    // A store edited to list another key under this device's ID
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&path, format!("{} {} laptop\n", device_id(&key), "00".repeat(32))).unwrap();
+    assert!(!TrustedPeers::load(&path).unwrap().is_trusted(&key));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pre_shared_key_proofs_need_the_same_secret() {
+    let dir = scratch_dir("secret");
+    let client = DeviceKey::load_or_generate(&dir.join("client.key")).unwrap();
+    let server = DeviceKey::load_or_generate(&dir.join("server.key")).unwrap();
+    let exchange = Exchange::new(&client.public_key(), &nonce().unwrap(), &server.public_key(), &nonce().unwrap()).unwrap();
+    let proof = exchange.prove_secret(b"lab secret", Role::Client);
+    assert!(exchange.verify_secret(b"lab secret", Role::Client, &proof).is_ok());
+    assert!(exchange.verify_secret(b"other secret", Role::Client, &proof).is_err());
+    assert!(exchange.verify_secret(b"lab secret", Role::Server, &proof).is_err());
+
+    // A fresh exchange is a fresh challenge
+    let next = Exchange::new(&client.public_key(), &nonce().unwrap(), &server.public_key(), &nonce().unwrap()).unwrap();
+    assert!(next.verify_secret(b"lab secret", Role::Client, &proof).is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,31 @@
+//! Locking out addresses that keep failing authentication.
+use platform_passer_transport::FailedAttempts;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+fn ip(last: u8) -> IpAddr {
+    IpAddr::from([10, 0, 0, last])
+}
+
+#[test]
+fn repeated_failures_lock_an_address_out_for_a_while() {
+    let mut attempts = FailedAttempts::new(3, Duration::from_secs(60), Duration::from_secs(300));
+    let start = Instant::now();
+    assert!(!attempts.record(ip(1), start));
+    assert!(!attempts.record(ip(1), start + Duration::from_secs(1)));
+    assert!(attempts.locked_out(ip(1), start).is_none());
+    assert!(attempts.record(ip(1), start + Duration::from_secs(2)));
+
+    assert_eq!(attempts.locked_out(ip(1), start + Duration::from_secs(2)), Some(Duration::from_secs(300)));
+    assert!(attempts.locked_out(ip(2), start).is_none());
+    assert!(attempts.locked_out(ip(1), start + Duration::from_secs(302)).is_none());
+}
+
+#[test]
+fn failures_spread_out_never_add_up() {
+    let mut attempts = FailedAttempts::new(2, Duration::from_secs(60), Duration::from_secs(300));
+    let start = Instant::now();
+    assert!(!attempts.record(ip(1), start));
+    assert!(!attempts.record(ip(1), start + Duration::from_secs(61)));
+    assert!(attempts.locked_out(ip(1), start + Duration::from_secs(61)).is_none());
+}
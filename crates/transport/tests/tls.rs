@@ -0,0 +1,151 @@
+//! `wss://` connections over loopback: certificates generated on first run, pinned by device ID
+//! on first contact and refused once they change.
+use futures_util::{SinkExt, StreamExt};
+use platform_passer_core::FrameLimits;
+use platform_passer_transport::tls::{fingerprint, Trust};
+use platform_passer_transport::{accept_ws, accept_wss, connect_ws, connect_wss, make_ws_listener, FingerprintMismatch, KnownPeers, TlsIdentity, TlsMismatch};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio_tungstenite::tungstenite::Message;
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("platform-passer-tls-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Accepts one `wss://` connection presenting `identity` and echoes one message back.
+async fn echo_server(identity: &TlsIdentity) -> SocketAddr {
+    let listener = make_ws_listener("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = identity.acceptor().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let Ok(mut ws) = accept_wss(stream, &FrameLimits::default(), &acceptor).await else {
+            return;
+        };
+        if let Some(Ok(msg)) = ws.next().await {
+            let _ = ws.send(msg).await;
+        }
+    });
+    addr
+}
+
+#[test]
+fn identity_is_generated_once_and_reloaded() {
+    let dir = scratch_dir("identity");
+    let first = TlsIdentity::load_or_generate(&dir).unwrap();
+    let again = TlsIdentity::load_or_generate(&dir).unwrap();
+    assert_eq!(first.fingerprint(), again.fingerprint());
+    // 32 bytes of SHA-256 as colon-separated hex
+    assert_eq!(first.fingerprint().len(), 32 * 3 - 1);
+    assert_eq!(fingerprint(b"abc").split(':').next(), Some("BA"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pins_survive_a_reload_and_can_be_forgotten() {
+    let dir = scratch_dir("pins");
+    let path = dir.join("known_peers");
+    let mut peers = KnownPeers::load(&path).unwrap();
+    assert_eq!(peers.verify("DEVICE-A", "AA:BB"), Ok(Trust::Pinned));
+    assert_eq!(peers.verify("DEVICE-A", "AA:BB"), Ok(Trust::Known));
+
+    let mut reloaded = KnownPeers::load(&path).unwrap();
+    assert_eq!(reloaded.pinned("DEVICE-A"), Some("AA:BB"));
+    let mismatch = reloaded.verify("DEVICE-A", "CC:DD").unwrap_err();
+    assert_eq!(mismatch.pinned, "AA:BB");
+    assert_eq!(mismatch.presented, "CC:DD");
+    assert!(mismatch.to_string().contains(&path.display().to_string()));
+
+    assert!(reloaded.forget("DEVICE-A").unwrap());
+    assert_eq!(KnownPeers::load(&path).unwrap().pinned("DEVICE-A"), None);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pins_kept_by_address_move_to_the_device() {
+    let dir = scratch_dir("rename");
+    let path = dir.join("known_peers");
+    let mut peers = KnownPeers::load(&path).unwrap();
+    peers.verify("10.0.0.2:4433", "AA:BB").unwrap();
+    assert!(peers.rename("10.0.0.2:4433", "DEVICE-A").unwrap());
+    assert!(!peers.rename("10.0.0.2:4433", "DEVICE-A").unwrap());
+
+    let mut reloaded = KnownPeers::load(&path).unwrap();
+    assert_eq!(reloaded.pinned("10.0.0.2:4433"), None);
+    assert_eq!(reloaded.verify("DEVICE-A", "AA:BB"), Ok(Trust::Known));
+    // A device that has a pin of its own keeps it
+    reloaded.verify("10.0.0.3:4433", "CC:DD").unwrap();
+    assert!(!reloaded.rename("10.0.0.3:4433", "DEVICE-A").unwrap());
+    assert_eq!(reloaded.pinned("DEVICE-A"), Some("AA:BB"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn the_presented_certificate_is_pinned_to_the_device_and_a_new_one_is_refused() {
+    let dir = scratch_dir("connect");
+    let mut peers = KnownPeers::load(dir.join("known_peers")).unwrap();
+    let server = TlsIdentity::load_or_generate(&dir.join("server")).unwrap();
+
+    let addr = echo_server(&server).await;
+    let mut ws = connect_wss(addr, &FrameLimits::default()).await.unwrap();
+    assert!(ws.get_ref().is_encrypted());
+    ws.send(Message::Binary(vec![1, 2, 3])).await.unwrap();
+    assert_eq!(ws.next().await.unwrap().unwrap(), Message::Binary(vec![1, 2, 3]));
+    let presented = ws.get_ref().peer_fingerprint().unwrap();
+    assert_eq!(presented, server.fingerprint());
+    assert_eq!(peers.verify("SERVER", &presented), Ok(Trust::Pinned));
+
+    // Another machine claiming to be the same device, wherever it answers
+    let impostor = TlsIdentity::load_or_generate(&dir.join("impostor")).unwrap();
+    let addr = echo_server(&impostor).await;
+    let ws = connect_wss(addr, &FrameLimits::default()).await.unwrap();
+    let mismatch: FingerprintMismatch = peers.verify("SERVER", &ws.get_ref().peer_fingerprint().unwrap()).unwrap_err();
+    assert_eq!(mismatch.pinned, server.fingerprint());
+    assert_eq!(mismatch.presented, impostor.fingerprint());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Accepts plain `ws://` connections until the test ends.
+async fn plain_server() -> SocketAddr {
+    let listener = make_ws_listener("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                if let Ok(mut ws) = accept_ws(stream, &FrameLimits::default()).await {
+                    while let Some(Ok(_)) = ws.next().await {}
+                }
+            });
+        }
+    });
+    addr
+}
+
+#[tokio::test]
+async fn plain_client_cannot_talk_to_a_tls_server_and_the_server_says_why() {
+    let dir = scratch_dir("plain");
+    let server = TlsIdentity::load_or_generate(&dir).unwrap();
+    let listener = make_ws_listener("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = server.acceptor().unwrap();
+    let accepted = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        accept_wss(stream, &FrameLimits::default(), &acceptor).await.map(|_| ())
+    });
+
+    assert!(connect_ws(addr, &FrameLimits::default()).await.is_err());
+    let err = accepted.await.unwrap().unwrap_err();
+    assert!(!err.downcast_ref::<TlsMismatch>().unwrap().peer_uses_tls);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn tls_client_names_a_server_without_tls() {
+    let addr = plain_server().await;
+    let err = connect_wss(addr, &FrameLimits::default()).await.unwrap_err();
+    let mismatch = err.downcast_ref::<TlsMismatch>().unwrap();
+    assert_eq!(mismatch, &TlsMismatch { peer: addr.to_string(), peer_uses_tls: false });
+    assert!(mismatch.to_string().contains("network.tls"));
+}